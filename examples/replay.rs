@@ -0,0 +1,120 @@
+//! Replay a page-access trace through the buffer pool and print its stats.
+//!
+//! # Usage
+//! ```text
+//! cargo run --example replay -- <trace-file> [pool-size] [policy]
+//! ```
+//! The trace holds one page ID per line; blank lines and lines starting with
+//! `#` are skipped. `pool-size` defaults to 64 and `policy` to `fifo`, the
+//! only eviction policy implemented so far.
+//!
+//! The pages are created in a scratch database first, so any trace can be
+//! replayed without preparing a database file. Each distinct page ID is
+//! mapped to the next page in first-seen order, so sparse or huge IDs cost
+//! no more disk than the trace's working set.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use interchangedb::buffer::BufferPoolManager;
+use interchangedb::common::{Error, PageId, Result};
+use interchangedb::storage::DiskManager;
+use interchangedb::StatsSnapshot;
+
+const DEFAULT_POOL_SIZE: usize = 64;
+const DEFAULT_POLICY: &str = "fifo";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args, &mut std::io::stdout()) {
+        eprintln!("replay: {}", e);
+        eprintln!("usage: replay <trace-file> [pool-size] [policy]");
+        std::process::exit(1);
+    }
+}
+
+/// Parse arguments, replay the trace, and print the stats to `out`.
+pub fn run(args: &[String], out: &mut impl Write) -> Result<()> {
+    let trace_path = args
+        .first()
+        .ok_or_else(|| Error::InvalidConfig("missing trace file".to_string()))?;
+    let pool_size = match args.get(1) {
+        Some(s) => s
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| Error::InvalidConfig(format!("invalid pool size: {}", s)))?,
+        None => DEFAULT_POOL_SIZE,
+    };
+    let policy = args.get(2).map_or(DEFAULT_POLICY, String::as_str);
+    if !policy.eq_ignore_ascii_case("fifo") {
+        return Err(Error::InvalidConfig(format!(
+            "unsupported policy: {}",
+            policy
+        )));
+    }
+
+    let file = std::fs::File::open(trace_path)?;
+    let trace = parse_trace(BufReader::new(file))?;
+
+    let scratch = tempfile::tempdir()?;
+    let stats = replay(&scratch.path().join("replay.db"), pool_size, &trace)?;
+
+    writeln!(out, "trace:     {} ({} accesses)", trace_path, trace.len())?;
+    writeln!(out, "policy:    {}", policy.to_ascii_lowercase())?;
+    writeln!(out, "pool size: {}", pool_size)?;
+    writeln!(out, "{}", stats)?;
+    writeln!(out, "hit rate:  {:.4}", stats.hit_rate())?;
+    Ok(())
+}
+
+/// Read one page ID per line, skipping blank lines and `#` comments.
+pub fn parse_trace(reader: impl BufRead) -> Result<Vec<PageId>> {
+    let mut trace = Vec::new();
+    for (lineno, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let id = line.parse::<u32>().map_err(|_| {
+            Error::InvalidConfig(format!("line {}: invalid page ID {:?}", lineno + 1, line))
+        })?;
+        trace.push(PageId::new(id));
+    }
+    Ok(trace)
+}
+
+/// Replay `trace` as reads through a fresh pool of `pool_size` frames.
+///
+/// Trace IDs are remapped densely (see [`remap_trace`]), which leaves hits
+/// and misses unchanged. Stats are reset after the pages are created, so
+/// only the trace counts.
+pub fn replay(db_path: &Path, pool_size: usize, trace: &[PageId]) -> Result<StatsSnapshot> {
+    let (trace, distinct) = remap_trace(trace);
+    let mut dm = DiskManager::create(db_path)?;
+    dm.preallocate(distinct)?;
+
+    let bpm = BufferPoolManager::new(pool_size, dm);
+    bpm.stats().reset();
+    for page_id in trace {
+        bpm.fetch_page_read(page_id)?;
+    }
+    Ok(bpm.stats().snapshot())
+}
+
+/// Map each distinct page ID to `0, 1, 2, ...` in first-seen order.
+///
+/// Returns the remapped trace and the number of distinct pages.
+pub fn remap_trace(trace: &[PageId]) -> (Vec<PageId>, usize) {
+    let mut ids: HashMap<PageId, PageId> = HashMap::new();
+    let remapped = trace
+        .iter()
+        .map(|&pid| {
+            let next = PageId::new(ids.len() as u32);
+            *ids.entry(pid).or_insert(next)
+        })
+        .collect();
+    (remapped, ids.len())
+}
@@ -6,16 +6,86 @@
 //! - Automatic dirty page write-back
 //! - Pluggable eviction policies
 
-use std::collections::HashMap;
-use std::sync::atomic::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-use parking_lot::{Mutex, RwLock};
+use parking_lot::{Condvar, Mutex, MutexGuard, RwLock};
 
-use crate::buffer::replacer::FifoReplacer;
-use crate::buffer::{BufferPoolStats, Frame, PageReadGuard, PageWriteGuard};
+use crate::buffer::replacer::{FifoReplacer, Replacer};
+use crate::buffer::{
+    AccessLatency, BufferPoolStats, Frame, FrameSnapshot, LatencyReservoir, LossyReadGuard,
+    PageReadGuard, PageUpgradableGuard, PageWriteGuard, PoolSnapshot, StatsSnapshot,
+};
+use crate::buffer::{LoggedOp, PoolOp};
+use crate::common::config::{self, PAGE_SIZE};
+use crate::common::trace::trace_event;
 use crate::common::{Error, FrameId, PageId, Result};
+use crate::storage::page::{Page, PageHeader, PageType};
 use crate::storage::DiskManager;
 
+/// Page history used to classify cache misses.
+#[derive(Default)]
+struct MissHistory {
+    /// Every page that has ever been resident.
+    seen: HashSet<PageId>,
+    /// Pages evicted to make room and not reloaded since.
+    evicted: HashSet<PageId>,
+}
+
+/// Number of nonexistent page IDs remembered so repeated fetches fail fast.
+const NOT_FOUND_CACHE_SIZE: usize = 64;
+
+/// Pre-images saved while a copy-on-write snapshot is active.
+struct CowSnapshot {
+    /// Number of pages on disk when the snapshot began.
+    page_count: u32,
+    /// Contents of each page as of the snapshot, saved on first write.
+    pages: HashMap<PageId, Page>,
+}
+
+/// Second tier holding copies of recently evicted pages.
+///
+/// Copies are always clean: pages are flushed before eviction. A page is
+/// either resident in the pool or in this cache, never both.
+struct VictimCache {
+    /// Maximum number of pages held.
+    capacity: usize,
+    /// Page copies by page ID.
+    pages: HashMap<PageId, Page>,
+    /// Insertion order, oldest first.
+    order: VecDeque<PageId>,
+}
+
+impl VictimCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            pages: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Add a page, dropping the oldest if full.
+    fn insert(&mut self, page_id: PageId, page: Page) {
+        if self.pages.insert(page_id, page).is_some() {
+            self.order.retain(|&pid| pid != page_id);
+        } else if self.pages.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.pages.remove(&oldest);
+            }
+        }
+        self.order.push_back(page_id);
+    }
+
+    /// Remove and return a page's copy.
+    fn take(&mut self, page_id: PageId) -> Option<Page> {
+        let page = self.pages.remove(&page_id)?;
+        self.order.retain(|&pid| pid != page_id);
+        Some(page)
+    }
+}
+
 /// Manages a pool of buffer frames for caching disk pages.
 ///
 /// # Architecture
@@ -28,7 +98,7 @@ use crate::storage::DiskManager;
 /// │  └──────────────┘  └───────────────────────────────────┘   │
 /// │  ┌──────────────┐  ┌──────────────┐  ┌──────────────┐      │
 /// │  │  free_list   │  │   replacer   │  │disk_manager  │      │
-/// │  │ Vec<FrameId> │  │ FifoReplacer │  │   Mutex      │      │
+/// │  │ Vec<FrameId> │  │dyn Replacer  │  │   Mutex      │      │
 /// │  └──────────────┘  └──────────────┘  └──────────────┘      │
 /// └─────────────────────────────────────────────────────────────┘
 /// ```
@@ -36,10 +106,21 @@ use crate::storage::DiskManager;
 /// # Thread Safety
 /// - `page_table`: `RwLock` — many readers, few writers
 /// - `free_list`: `Mutex` — always modified
-/// - `replacer`: `Mutex` — internal state changes on access
+/// - `replacer`: `Mutex` — taken on misses and eviction; cache hits only
+///   flag the frame, and the access is reported at the next eviction
 /// - `disk_manager`: `Mutex` — single-threaded I/O
 /// - `frames`: No lock — fixed size, each Frame has internal locks
 /// - `stats`: No lock — all atomic counters
+///
+/// # Cache Hit Lock Sequence
+/// A hit takes a single pool lock, then the page latch:
+/// 1. `page_table` read lock: look up the frame, pin it (atomic), and flag
+///    the access for the replacer (atomic)
+/// 2. The frame's page latch, held by the returned guard
+///
+/// The frame's own `page_id` mutex isn't needed: the pin is taken while the
+/// read lock keeps the mapping stable, and eviction re-checks the pin count
+/// under the `page_table` write lock before unmapping a frame.
 pub struct BufferPoolManager {
     /// Fixed pool of frames allocated at startup.
     frames: Vec<Frame>,
@@ -51,7 +132,13 @@ pub struct BufferPoolManager {
     free_list: Mutex<Vec<FrameId>>,
 
     /// Eviction policy for selecting victim frames.
-    replacer: Mutex<FifoReplacer>,
+    replacer: Mutex<Box<dyn Replacer + Send>>,
+
+    /// Frames with an access not yet reported to the replacer.
+    ///
+    /// Holds exactly the frames whose pending-access flag is set, each once,
+    /// so it never outgrows the pool. The flag only changes under this lock.
+    deferred: Mutex<Vec<FrameId>>,
 
     /// Handles all disk I/O.
     disk_manager: Mutex<DiskManager>,
@@ -59,8 +146,80 @@ pub struct BufferPoolManager {
     /// Performance statistics.
     stats: BufferPoolStats,
 
-    /// Number of frames in the pool (immutable after construction).
-    pool_size: usize,
+    /// Number of frames in circulation; shrinks with `resize_down`.
+    pool_size: AtomicUsize,
+
+    /// Pages to prefetch after a sequential read (0 = disabled).
+    readahead: AtomicUsize,
+
+    /// Last page fetched via `fetch_page_read` (u32::MAX = none).
+    last_read_page: AtomicU32,
+
+    /// Maximum pins per frame (0 = unlimited).
+    max_pin_count: AtomicU32,
+
+    /// Maximum pins outstanding across the pool (0 = unlimited).
+    pin_budget: AtomicUsize,
+
+    /// Pins currently held across the pool.
+    pins_outstanding: AtomicUsize,
+
+    /// Lock and condition used to wait for a pin under the budget.
+    pin_budget_lock: Mutex<()>,
+    pin_released: Condvar,
+
+    /// Longest a fetch waits for a pin permit, in microseconds (0 = forever).
+    lock_timeout_us: AtomicU64,
+
+    /// Seen/evicted page sets for the miss breakdown in `stats`.
+    miss_history: Mutex<MissHistory>,
+
+    /// Recently fetched page IDs that weren't on disk, oldest first. Only
+    /// changed with the disk manager lock held, so an allocation can't
+    /// race with an insert.
+    not_found: Mutex<VecDeque<PageId>>,
+
+    /// Active copy-on-write snapshot, if any.
+    cow_snapshot: Mutex<Option<CowSnapshot>>,
+
+    /// Second tier for evicted pages, if enabled.
+    victim_cache: Option<Mutex<VictimCache>>,
+
+    /// Last flushed contents of resident pages, if lossy reads are enabled.
+    shadows: Option<Mutex<HashMap<PageId, Page>>>,
+
+    /// Public operations served so far, if recording is enabled.
+    op_log: Option<Mutex<Vec<LoggedOp>>>,
+
+    /// Reference point for frame write timestamps.
+    epoch: Instant,
+
+    /// Dirty pages written within this many microseconds are passed over
+    /// for eviction when possible (0 = disabled).
+    write_coalesce_window_us: AtomicU64,
+
+    /// Evict clean frames before dirty ones when possible.
+    prefer_clean_victims: AtomicBool,
+
+    /// Frames to keep free by evicting ahead of demand (0 = disabled).
+    min_free_reserve: AtomicUsize,
+
+    /// Sample the latency of one in this many fetches (0 = disabled).
+    latency_sample_every: AtomicU32,
+
+    /// Fetches counted toward latency sampling.
+    latency_sample_tick: AtomicU64,
+
+    /// Number of `flush_all_pages` calls currently running.
+    flushes_running: AtomicUsize,
+
+    /// Sampled latencies of fetches that hit and missed.
+    hit_latency: Mutex<LatencyReservoir>,
+    miss_latency: Mutex<LatencyReservoir>,
+
+    /// Number of times the replacer lock was taken.
+    #[cfg(test)]
+    replacer_locks: AtomicU64,
 }
 
 impl BufferPoolManager {
@@ -71,22 +230,135 @@ impl BufferPoolManager {
     /// * `disk_manager` - Handles disk I/O
     ///
     /// # Panics
-    /// Panics if `pool_size` is 0.
+    /// Panics if `pool_size` is 0 or too large to allocate. Use
+    /// [`try_new`](Self::try_new) to get an error instead.
     pub fn new(pool_size: usize, disk_manager: DiskManager) -> Self {
-        assert!(pool_size > 0, "pool_size must be > 0");
+        Self::try_new(pool_size, disk_manager).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Create a new buffer pool manager, rejecting invalid sizes.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidConfig` if [`config::validate`] rejects
+    /// `pool_size`, or the frames would need more than `isize::MAX` bytes.
+    pub fn try_new(pool_size: usize, disk_manager: DiskManager) -> Result<Self> {
+        config::validate(pool_size, PAGE_SIZE)?;
+        let fits = pool_size
+            .checked_mul(std::mem::size_of::<Frame>())
+            .is_some_and(|bytes| bytes <= isize::MAX as usize);
+        if !fits {
+            return Err(Error::InvalidConfig(format!(
+                "pool_size {} is too large to allocate",
+                pool_size
+            )));
+        }
 
         let frames: Vec<Frame> = (0..pool_size).map(|_| Frame::new()).collect();
         let free_list: Vec<FrameId> = (0..pool_size).map(FrameId::new).collect();
 
-        Self {
+        Ok(Self {
             frames,
             page_table: RwLock::new(HashMap::new()),
             free_list: Mutex::new(free_list),
-            replacer: Mutex::new(FifoReplacer::new()),
+            replacer: Mutex::new(Box::new(FifoReplacer::new())),
+            deferred: Mutex::new(Vec::new()),
             disk_manager: Mutex::new(disk_manager),
             stats: BufferPoolStats::new(),
-            pool_size,
+            pool_size: AtomicUsize::new(pool_size),
+            readahead: AtomicUsize::new(0),
+            last_read_page: AtomicU32::new(PageId::INVALID.0),
+            max_pin_count: AtomicU32::new(0),
+            pin_budget: AtomicUsize::new(0),
+            pins_outstanding: AtomicUsize::new(0),
+            pin_budget_lock: Mutex::new(()),
+            pin_released: Condvar::new(),
+            lock_timeout_us: AtomicU64::new(0),
+            miss_history: Mutex::new(MissHistory::default()),
+            not_found: Mutex::new(VecDeque::with_capacity(NOT_FOUND_CACHE_SIZE)),
+            cow_snapshot: Mutex::new(None),
+            victim_cache: None,
+            shadows: None,
+            op_log: None,
+            epoch: Instant::now(),
+            write_coalesce_window_us: AtomicU64::new(0),
+            prefer_clean_victims: AtomicBool::new(false),
+            min_free_reserve: AtomicUsize::new(0),
+            flushes_running: AtomicUsize::new(0),
+            latency_sample_every: AtomicU32::new(0),
+            latency_sample_tick: AtomicU64::new(0),
+            hit_latency: Mutex::new(LatencyReservoir::new()),
+            miss_latency: Mutex::new(LatencyReservoir::new()),
+            #[cfg(test)]
+            replacer_locks: AtomicU64::new(0),
+        })
+    }
+
+    /// Keep copies of up to `pages` evicted pages in a second tier.
+    ///
+    /// A miss checks the victim cache before reading from disk, which saves
+    /// reads for pages that oscillate around the pool's capacity. Hits there
+    /// still count as misses, and are also counted in `victim_hits`.
+    /// Pass 0 to disable (the default).
+    pub fn with_victim_cache(mut self, pages: usize) -> Self {
+        self.victim_cache = (pages > 0).then(|| Mutex::new(VictimCache::new(pages)));
+        self
+    }
+
+    /// Keep a copy of each resident page as of its last flush, for
+    /// [`fetch_page_read_lossy`](Self::fetch_page_read_lossy).
+    ///
+    /// Costs a 4KB copy per page write and up to one extra page of memory
+    /// per frame. Without it, lossy reads of a write-latched page fall back
+    /// to reading the page from disk.
+    pub fn with_lossy_reads(mut self) -> Self {
+        self.shadows = Some(Mutex::new(HashMap::new()));
+        self
+    }
+
+    /// Use `replacer` as the eviction policy instead of the default FIFO.
+    ///
+    /// Pages already resident are handed to the new replacer as evictable,
+    /// in frame order, since no guards can be outstanding while the pool is
+    /// being moved.
+    pub fn with_replacer(self, mut replacer: Box<dyn Replacer + Send>) -> Self {
+        self.deferred.lock().clear();
+        for (i, frame) in self.frames.iter().enumerate() {
+            frame.take_pending_access();
+            if let Some(page_id) = frame.page_id() {
+                replacer.record_access(FrameId::new(i), page_id);
+                replacer.set_evictable(FrameId::new(i), true);
+                frame.set_replacer_evictable(true);
+            }
         }
+        *self.replacer.lock() = replacer;
+        self
+    }
+
+    /// Record every public page operation for [`replay`](crate::buffer::replay).
+    ///
+    /// Each call appends one entry under a mutex, so this is a debugging
+    /// aid, not something to leave on in production. The log is unbounded;
+    /// drain it with [`take_op_log`](Self::take_op_log) on long runs.
+    pub fn with_op_log(mut self) -> Self {
+        self.op_log = Some(Mutex::new(Vec::new()));
+        self
+    }
+
+    /// Copy of the operations recorded so far, or None without
+    /// [`with_op_log`](Self::with_op_log).
+    pub fn op_log(&self) -> Option<Vec<LoggedOp>> {
+        self.op_log.as_ref().map(|log| log.lock().clone())
+    }
+
+    /// Remove and return the operations recorded so far, or None without
+    /// [`with_op_log`](Self::with_op_log).
+    ///
+    /// Recording continues into an empty log, so successive drains
+    /// concatenate to the full sequence.
+    pub fn take_op_log(&self) -> Option<Vec<LoggedOp>> {
+        self.op_log
+            .as_ref()
+            .map(|log| std::mem::take(&mut *log.lock()))
     }
 
     // ========================================================================
@@ -102,25 +374,210 @@ impl BufferPoolManager {
     /// - `Error::PageNotFound` if the page doesn't exist on disk
     /// - `Error::NoFreeFrames` if all frames are pinned
     pub fn fetch_page_read(&self, page_id: PageId) -> Result<PageReadGuard<'_>> {
-        let frame_id = self.fetch_page_internal(page_id)?;
+        let frame_id = self.fetch_page_internal(page_id);
+        self.log_op(PoolOp::FetchRead(page_id), &frame_id);
+        let frame_id = frame_id?;
+        self.maybe_readahead(page_id);
         let lock = self.frames[frame_id.0].page();
         Ok(PageReadGuard::new(self, frame_id, page_id, lock))
     }
 
+    /// Fetch a page for reading without waiting on its write latch.
+    ///
+    /// If a writer holds the page, returns a copy of the page as of its last
+    /// flush instead of blocking, marked stale. The copy comes from the
+    /// shadow kept by [`with_lossy_reads`](Self::with_lossy_reads), or from
+    /// disk when there is none. Meant for read-mostly analytics that prefer
+    /// availability over freshness.
+    ///
+    /// # Errors
+    /// Same as `fetch_page_read`.
+    pub fn fetch_page_read_lossy(&self, page_id: PageId) -> Result<LossyReadGuard<'_>> {
+        let frame_id = self.fetch_page_internal(page_id);
+        self.log_op(PoolOp::FetchRead(page_id), &frame_id);
+        let frame_id = frame_id?;
+        if let Some(lock) = self.frames[frame_id.0].try_page() {
+            let guard = PageReadGuard::new(self, frame_id, page_id, lock);
+            return Ok(LossyReadGuard::Fresh(guard));
+        }
+        self.unpin_page_internal(frame_id, false)?;
+
+        let mut copy = Box::new(Page::new());
+        let shadow = self.shadows.as_ref().is_some_and(|shadows| {
+            shadows.lock().get(&page_id).is_some_and(|page| {
+                copy.as_mut_slice().copy_from_slice(page.as_slice());
+                true
+            })
+        });
+        if !shadow {
+            copy = Box::new(self.disk_manager.lock().read_page(page_id)?);
+        }
+        Ok(LossyReadGuard::Stale(copy))
+    }
+
+    /// Fetch a page and return an owned copy of it.
+    ///
+    /// The page is pinned and latched only for the duration of the copy, so
+    /// the caller never holds anything that could block eviction or writers.
+    /// Costs one 4KB memcpy over [`fetch_page_read`](Self::fetch_page_read).
+    ///
+    /// # Errors
+    /// Same as `fetch_page_read`.
+    pub fn read_page_copy(&self, page_id: PageId) -> Result<Page> {
+        let guard = self.fetch_page_read(page_id)?;
+        let mut copy = Page::new();
+        copy.as_mut_slice().copy_from_slice(guard.as_slice());
+        Ok(copy)
+    }
+
+    /// Copy several pages as of a single instant.
+    ///
+    /// Read latches on all the pages are held at once while copying, so no
+    /// copy can reflect a write that started after another was taken: a
+    /// writer updating several of these pages under
+    /// [`fetch_pages_write`](Self::fetch_pages_write) is seen either wholly
+    /// or not at all. Latches are taken in ascending page ID order, like
+    /// `fetch_pages_write`. Copies are returned in the order requested;
+    /// duplicates are allowed.
+    ///
+    /// All distinct pages are pinned together, so the set must fit in the
+    /// pool.
+    ///
+    /// # Errors
+    /// - Any error from `fetch_page_read`; latches taken so far are released
+    /// - `Error::Internal` if a requested page was somehow not latched
+    pub fn read_snapshot(&self, page_ids: &[PageId]) -> Result<Vec<Page>> {
+        let mut distinct = page_ids.to_vec();
+        distinct.sort();
+        distinct.dedup();
+
+        let guards = distinct
+            .iter()
+            .map(|&pid| self.fetch_page_read(pid))
+            .collect::<Result<Vec<_>>>()?;
+        page_ids
+            .iter()
+            .map(|pid| {
+                let i = distinct.binary_search(pid).map_err(|_| {
+                    Error::Internal(format!("{} missing from read_snapshot latches", pid))
+                })?;
+                let mut copy = Page::new();
+                copy.as_mut_slice().copy_from_slice(guards[i].as_slice());
+                Ok(copy)
+            })
+            .collect()
+    }
+
     /// Fetch a page for writing (exclusive access).
     ///
     /// Same as `fetch_page_read`, but returns an exclusive guard.
     /// The page is automatically marked dirty when the guard drops.
     ///
+    /// Page latches are task-fair: once a writer is waiting, new readers
+    /// queue behind it, so a steady stream of readers can't starve writers
+    /// (e.g. a B-tree root update under read-heavy load). No per-pool option
+    /// is needed to get writer priority.
+    ///
     /// # Errors
     /// - `Error::PageNotFound` if the page doesn't exist on disk
     /// - `Error::NoFreeFrames` if all frames are pinned
     pub fn fetch_page_write(&self, page_id: PageId) -> Result<PageWriteGuard<'_>> {
+        let guard = self.write_guard(page_id);
+        self.log_op(PoolOp::FetchWrite(page_id), &guard);
+        guard
+    }
+
+    /// `fetch_page_write` without logging.
+    fn write_guard(&self, page_id: PageId) -> Result<PageWriteGuard<'_>> {
         let frame_id = self.fetch_page_internal(page_id)?;
         let lock = self.frames[frame_id.0].page_mut();
+        self.preserve_for_snapshot(page_id, &lock);
         Ok(PageWriteGuard::new(self, frame_id, page_id, lock))
     }
 
+    /// Fetch several pages for writing, all or nothing.
+    ///
+    /// Latches are taken in ascending page ID order regardless of the order
+    /// of `page_ids`, so callers locking overlapping sets can't deadlock each
+    /// other. The guards are returned in the order requested. If any fetch
+    /// fails, the guards acquired so far are released before returning.
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `page_ids` contains duplicates
+    /// - Any error from `fetch_page_write`
+    pub fn fetch_pages_write(&self, page_ids: &[PageId]) -> Result<Vec<PageWriteGuard<'_>>> {
+        let mut order: Vec<usize> = (0..page_ids.len()).collect();
+        order.sort_by_key(|&i| page_ids[i]);
+        if let Some(w) = order.windows(2).find(|w| page_ids[w[0]] == page_ids[w[1]]) {
+            return Err(Error::InvalidConfig(format!(
+                "{} requested twice in fetch_pages_write",
+                page_ids[w[0]]
+            )));
+        }
+
+        let mut guards: Vec<Option<PageWriteGuard<'_>>> =
+            (0..page_ids.len()).map(|_| None).collect();
+        for i in order {
+            guards[i] = Some(self.fetch_page_write(page_ids[i])?);
+        }
+        Ok(guards.into_iter().flatten().collect())
+    }
+
+    /// Fetch a page with write intent, without blocking readers yet.
+    ///
+    /// The returned guard takes an upgradable read lock: plain readers can
+    /// still share the page, but no writer or other upgradable guard can.
+    /// Call [`PageUpgradableGuard::upgrade`] to wait for the readers to
+    /// drain and get exclusive access, or
+    /// [`downgrade`](PageUpgradableGuard::downgrade) to give up the intent.
+    /// This suits optimistic B-tree descent, where most visits don't modify
+    /// the node.
+    ///
+    /// # Errors
+    /// - `Error::PageNotFound` if the page doesn't exist on disk
+    /// - `Error::NoFreeFrames` if all frames are pinned
+    pub fn fetch_page_upgradable(&self, page_id: PageId) -> Result<PageUpgradableGuard<'_>> {
+        let frame_id = self.fetch_page_internal(page_id);
+        self.log_op(PoolOp::FetchUpgradable(page_id), &frame_id);
+        let frame_id = frame_id?;
+        let lock = self.frames[frame_id.0].page_upgradable();
+        Ok(PageUpgradableGuard::new(self, frame_id, page_id, lock))
+    }
+
+    /// Fetch a page for writing without reading its old contents from disk.
+    ///
+    /// For callers that will overwrite the entire page (e.g. bulk loading a
+    /// fresh B-tree leaf). If the page isn't resident, its frame is zeroed
+    /// instead of being read from disk. The page is marked dirty immediately.
+    ///
+    /// While a copy-on-write snapshot is active the old contents are needed
+    /// for the snapshot, so this falls back to a normal `fetch_page_write`.
+    ///
+    /// # Errors
+    /// - `Error::PageNotFound` if the page hasn't been allocated on disk
+    /// - `Error::NoFreeFrames` if all frames are pinned
+    pub fn fetch_page_write_no_read(&self, page_id: PageId) -> Result<PageWriteGuard<'_>> {
+        let guard = self.write_guard_no_read(page_id);
+        self.log_op(PoolOp::FetchWriteNoRead(page_id), &guard);
+        guard
+    }
+
+    fn write_guard_no_read(&self, page_id: PageId) -> Result<PageWriteGuard<'_>> {
+        if self.contains_page(page_id) || self.cow_snapshot_active() {
+            let guard = self.write_guard(page_id)?;
+            self.frames[guard.frame_id().0].mark_dirty();
+            return Ok(guard);
+        }
+
+        if page_id.0 >= self.disk_manager.lock().page_count() {
+            return Err(Error::PageNotFound(page_id.0));
+        }
+
+        let guard = self.fetch_page_write_new(page_id)?;
+        self.frames[guard.frame_id().0].mark_dirty();
+        Ok(guard)
+    }
+
     /// Fetch a page for reading, returning None if not possible.
     ///
     /// Matches BusTub's `CheckedReadPage()`.
@@ -135,6 +592,20 @@ impl BufferPoolManager {
         self.fetch_page_write(page_id).ok()
     }
 
+    /// Tell the replacer a resident page was used, without fetching it.
+    ///
+    /// Records an access exactly as a fetch would (updating recency or
+    /// frequency for policies that track them) but takes no pin or latch and
+    /// doesn't count as a hit or miss. Higher layers can use it to keep pages
+    /// they know are hot from being evicted. No-op if the page isn't
+    /// resident.
+    pub fn reference(&self, page_id: PageId) {
+        let pt = self.page_table.read();
+        if let Some(&frame_id) = pt.get(&page_id) {
+            self.lock_replacer().record_access(frame_id, page_id);
+        }
+    }
+
     // ========================================================================
     // Public API: Create and delete pages
     // ========================================================================
@@ -149,8 +620,16 @@ impl BufferPoolManager {
     /// # Errors
     /// - I/O errors from disk allocation
     pub fn allocate_page_id(&self) -> Result<PageId> {
+        let page_id = self.allocate_on_disk();
+        self.log_op(PoolOp::AllocatePageId, &page_id);
+        page_id
+    }
+
+    fn allocate_on_disk(&self) -> Result<PageId> {
         let mut dm = self.disk_manager.lock();
-        dm.allocate_page()
+        let page_id = dm.allocate_page()?;
+        self.forget_not_found(page_id);
+        Ok(page_id)
     }
 
     /// Allocate a new page on disk and load it into the buffer pool.
@@ -165,12 +644,72 @@ impl BufferPoolManager {
     /// - I/O errors from disk allocation
     pub fn new_page(&self) -> Result<PageWriteGuard<'_>> {
         // Allocate page ID first (this always succeeds unless I/O error)
-        let page_id = self.allocate_page_id()?;
+        let guard = self.allocate_on_disk().and_then(|page_id| {
+            // Now try to bring it into the buffer pool
+            // If this fails with NoFreeFrames, the page ID is "leaked" on disk
+            // but that's acceptable - BusTub has the same behavior
+            self.fetch_page_write_new(page_id)
+        });
+        self.log_op(PoolOp::NewPage, &guard);
+        guard
+    }
+
+    /// Allocate a new page with its header stamped with `page_type`.
+    ///
+    /// Like [`new_page`](Self::new_page), but the header is initialized
+    /// (`page_type` set, LSN zero) and the checksum computed before the guard
+    /// is returned, so the page verifies even if the caller writes nothing.
+    ///
+    /// # Errors
+    /// Same as `new_page`.
+    pub fn new_page_typed(&self, page_type: PageType) -> Result<PageWriteGuard<'_>> {
+        let mut guard = self.new_page()?;
+        guard.set_header(&PageHeader::new(page_type));
+        guard.update_checksum();
+        Ok(guard)
+    }
+
+    /// Fetch a page for writing, allocating it on disk if it is the next page ID.
+    ///
+    /// Useful for idempotent initialization of well-known pages (e.g. a
+    /// superblock at page 0): the first call creates the page, later calls
+    /// simply fetch it.
+    ///
+    /// # Errors
+    /// - `Error::InvalidPageId` if the page is neither on disk nor the next
+    ///   page ID to be allocated
+    /// - `Error::NoFreeFrames` if all frames are pinned
+    /// - I/O errors from disk allocation
+    pub fn fetch_or_create(&self, page_id: PageId) -> Result<PageWriteGuard<'_>> {
+        let guard = self.fetch_or_create_unlogged(page_id);
+        self.log_op(PoolOp::FetchOrCreate(page_id), &guard);
+        guard
+    }
+
+    fn fetch_or_create_unlogged(&self, page_id: PageId) -> Result<PageWriteGuard<'_>> {
+        if self.contains_page(page_id) {
+            return self.write_guard(page_id);
+        }
+
+        let created = {
+            let mut dm = self.disk_manager.lock();
+            let next = dm.page_count();
+            if page_id.0 < next {
+                false
+            } else if page_id.0 == next {
+                dm.allocate_page()?;
+                self.forget_not_found(page_id);
+                true
+            } else {
+                return Err(Error::InvalidPageId(page_id.0));
+            }
+        };
 
-        // Now try to bring it into the buffer pool
-        // If this fails with NoFreeFrames, the page ID is "leaked" on disk
-        // but that's acceptable - BusTub has the same behavior
-        self.fetch_page_write_new(page_id)
+        if created {
+            self.fetch_page_write_new(page_id)
+        } else {
+            self.write_guard(page_id)
+        }
     }
 
     /// Fetch a newly allocated page for writing.
@@ -178,27 +717,34 @@ impl BufferPoolManager {
     /// Unlike `fetch_page_write`, this initializes the page to zeros
     /// instead of reading from disk (since it's a new page).
     fn fetch_page_write_new(&self, page_id: PageId) -> Result<PageWriteGuard<'_>> {
-        let frame_id = self.get_free_frame()?;
+        self.acquire_pin_permit()?;
+        let frame_id = match self.get_free_frame() {
+            Ok(frame_id) => frame_id,
+            Err(e) => {
+                self.release_pin_permit();
+                return Err(e);
+            }
+        };
 
         let frame = &self.frames[frame_id.0];
 
-        // Initialize to zeros (new page)
-        frame.page_mut().reset();
-        frame.set_page_id(Some(page_id));
-        frame.pin();
-
-        {
-            let mut pt = self.page_table.write();
-            pt.insert(page_id, frame_id);
+        // Any older copy is stale once the page is overwritten
+        if let Some(victims) = &self.victim_cache {
+            victims.lock().take(page_id);
         }
 
-        {
-            let mut replacer = self.replacer.lock();
-            replacer.record_access(frame_id, page_id);
-            replacer.set_evictable(frame_id, false);
-        }
+        // Initialize to zeros (new page). The page was just zeroed on disk
+        // too, so a fresh or reset frame needs no fill.
+        frame.zero_page();
 
-        let lock = frame.page_mut();
+        let frame_id = match self.install_frame(frame_id, page_id) {
+            Ok(frame_id) => frame_id,
+            Err(e) => {
+                self.release_pin_permit();
+                return Err(e);
+            }
+        };
+        let lock = self.frames[frame_id.0].page_mut();
         Ok(PageWriteGuard::new(self, frame_id, page_id, lock))
     }
 
@@ -207,9 +753,30 @@ impl BufferPoolManager {
     /// The page must not be pinned. This removes the page from the buffer pool
     /// but does NOT deallocate it on disk.
     ///
+    /// Unflushed changes are discarded: if the page is dirty, the next fetch
+    /// reads the version last written to disk. Use
+    /// [`delete_page_flushing`](Self::delete_page_flushing) to keep them.
+    ///
     /// # Errors
     /// - Returns error if page is still pinned
     pub fn delete_page(&self, page_id: PageId) -> Result<()> {
+        let result = self.delete_page_internal(page_id, false);
+        self.log_op(PoolOp::DeletePage(page_id), &result);
+        result
+    }
+
+    /// Delete a page from the buffer pool, writing it to disk first if dirty.
+    ///
+    /// # Errors
+    /// - Returns error if page is still pinned
+    /// - I/O errors from the flush, in which case the page stays in the pool
+    pub fn delete_page_flushing(&self, page_id: PageId) -> Result<()> {
+        let result = self.delete_page_internal(page_id, true);
+        self.log_op(PoolOp::DeletePageFlushing(page_id), &result);
+        result
+    }
+
+    fn delete_page_internal(&self, page_id: PageId, flush: bool) -> Result<()> {
         let mut pt = self.page_table.write();
 
         let frame_id = match pt.get(&page_id) {
@@ -223,15 +790,24 @@ impl BufferPoolManager {
             return Err(Error::PageNotPinned(page_id.0));
         }
 
+        // No one can pin the page while the page table is locked, so nothing
+        // dirties it again after the flush
+        if flush {
+            self.flush_frame(frame_id, page_id)?;
+        }
+
         pt.remove(&page_id);
         drop(pt);
+        self.drop_shadow(page_id);
 
         frame.set_page_id(None);
         frame.clear_dirty();
 
         {
-            let mut replacer = self.replacer.lock();
+            let mut replacer = self.lock_replacer();
             replacer.remove(frame_id);
+            frame.set_replacer_evictable(false);
+            self.forget_deferred_access(frame_id);
         }
 
         {
@@ -242,292 +818,2946 @@ impl BufferPoolManager {
         Ok(())
     }
 
+    /// Zero a resident page in place and mark it dirty.
+    ///
+    /// Useful for repurposing a freed page without reading it from disk.
+    /// The whole page is zeroed, header included, and then given a fresh
+    /// checksum, so it passes [`Page::verify_checksum`] like any other
+    /// written page. Pages not in the pool are left alone.
+    ///
+    /// # Errors
+    /// - `Error::PageNotPinned` if the page is pinned (as in `delete_page`)
+    pub fn clear_page(&self, page_id: PageId) -> Result<()> {
+        let result = self.clear_page_internal(page_id);
+        self.log_op(PoolOp::ClearPage(page_id), &result);
+        result
+    }
+
+    fn clear_page_internal(&self, page_id: PageId) -> Result<()> {
+        // Holding the page table write lock keeps hits from pinning the page
+        let pt = self.page_table.write();
+        let Some(&frame_id) = pt.get(&page_id) else {
+            return Ok(());
+        };
+        let frame = &self.frames[frame_id.0];
+        if frame.is_pinned() {
+            return Err(Error::PageNotPinned(page_id.0));
+        }
+
+        // Unpinned, so at most an evictor's flush holds the latch, briefly
+        let mut page = frame.page_mut();
+        self.preserve_for_snapshot(page_id, &page);
+        page.reset();
+        page.update_checksum();
+        frame.mark_dirty();
+        frame.record_write(self.now_us());
+        Ok(())
+    }
+
     // ========================================================================
     // Public API: Flush pages
     // ========================================================================
 
     /// Flush a specific page to disk if it's dirty.
     pub fn flush_page(&self, page_id: PageId) -> Result<()> {
-        let frame_id = {
-            let pt = self.page_table.read();
-            match pt.get(&page_id) {
-                Some(&fid) => fid,
-                None => return Ok(()),
-            }
+        let frame_id = self.page_table.read().get(&page_id).copied();
+        let result = match frame_id {
+            Some(frame_id) => self.flush_frame(frame_id, page_id).map(drop),
+            None => Ok(()),
         };
-        self.flush_frame(frame_id, page_id)
+        self.log_op(PoolOp::FlushPage(page_id), &result);
+        result
     }
 
     /// Flush all dirty pages to disk.
+    ///
+    /// A failure on one page doesn't stop the others from being attempted;
+    /// pages that were written have their dirty flags cleared either way.
+    ///
+    /// If the disk manager has a double-write buffer enabled, the dirty pages
+    /// are written as a single batch through it, which succeeds or fails as
+    /// a whole.
+    ///
+    /// # Errors
+    /// - `Error::PartialFlush` listing each page that failed, and how many
+    ///   were flushed
+    /// - Any error from the double-write batch
     pub fn flush_all_pages(&self) -> Result<()> {
+        self.flushes_running.fetch_add(1, Ordering::AcqRel);
+        let result = self.flush_all_pages_inner();
+        self.flushes_running.fetch_sub(1, Ordering::AcqRel);
+        self.log_op(PoolOp::FlushAll, &result);
+        result
+    }
+
+    /// Check if a [`flush_all_pages`](Self::flush_all_pages) call is running.
+    ///
+    /// Together with [`dirty_ratio`](Self::dirty_ratio), lets writers back
+    /// off while the pool is draining dirty pages.
+    pub fn flush_in_progress(&self) -> bool {
+        self.flushes_running.load(Ordering::Acquire) > 0
+    }
+
+    fn flush_all_pages_inner(&self) -> Result<()> {
         let pages: Vec<(PageId, FrameId)> = {
             let pt = self.page_table.read();
             pt.iter().map(|(&pid, &fid)| (pid, fid)).collect()
         };
 
-        for (page_id, frame_id) in pages {
-            self.flush_frame(frame_id, page_id)?;
+        let (flushed, failed) = if self.disk_manager.lock().has_double_write() {
+            self.flush_batch(&pages)
+        } else {
+            let mut flushed = 0;
+            let mut failed = Vec::new();
+            for (page_id, frame_id) in pages {
+                match self.flush_frame(frame_id, page_id) {
+                    Ok(true) => flushed += 1,
+                    Ok(false) => {}
+                    Err(e) => failed.push((page_id, e)),
+                }
+            }
+            (flushed, failed)
+        };
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::PartialFlush { flushed, failed })
         }
-        Ok(())
     }
 
     // ========================================================================
-    // Public API: Stats and info
+    // Public API: Copy-on-write snapshots
     // ========================================================================
 
-    /// Get buffer pool statistics.
-    pub fn stats(&self) -> &BufferPoolStats {
-        &self.stats
-    }
-
-    /// Get the pool size.
-    pub fn pool_size(&self) -> usize {
-        self.pool_size
+    /// Mark a point in time for a consistent online backup.
+    ///
+    /// Until [`end_cow_snapshot`](Self::end_cow_snapshot), the first write
+    /// latch taken on each page saves its current contents aside, so
+    /// [`read_cow_page`](Self::read_cow_page) keeps returning the page as of
+    /// this instant while writers proceed.
+    ///
+    /// Returns false (and changes nothing) if a snapshot is already active.
+    pub fn begin_cow_snapshot(&self) -> bool {
+        let mut cow = self.cow_snapshot.lock();
+        if cow.is_some() {
+            return false;
+        }
+        let page_count = self.disk_manager.lock().page_count();
+        *cow = Some(CowSnapshot {
+            page_count,
+            pages: HashMap::new(),
+        });
+        true
     }
 
-    /// Get the number of free frames.
-    pub fn free_frame_count(&self) -> usize {
-        self.free_list.lock().len()
+    /// End the active snapshot and discard all saved page versions.
+    pub fn end_cow_snapshot(&self) {
+        *self.cow_snapshot.lock() = None;
     }
 
-    /// Get the number of pages in the buffer pool.
-    pub fn page_count(&self) -> usize {
-        self.page_table.read().len()
+    /// Check if a copy-on-write snapshot is active.
+    pub fn cow_snapshot_active(&self) -> bool {
+        self.cow_snapshot.lock().is_some()
     }
 
-    /// Get pin count for a page. Returns None if page not in pool.
+    /// Read a page as it was when the active snapshot began.
     ///
-    /// Matches BusTub's `GetPinCount()`.
-    pub fn get_pin_count(&self, page_id: PageId) -> Option<u32> {
-        let pt = self.page_table.read();
-        let &frame_id = pt.get(&page_id)?;
-        Some(self.frames[frame_id.0].pin_count())
+    /// Without an active snapshot this returns the current contents.
+    ///
+    /// # Errors
+    /// - `Error::PageNotFound` if the page didn't exist at the snapshot instant
+    /// - Any error from `fetch_page_read`
+    pub fn read_cow_page(&self, page_id: PageId) -> Result<Page> {
+        if let Some(cow) = self.cow_snapshot.lock().as_ref() {
+            if page_id.0 >= cow.page_count {
+                return Err(Error::PageNotFound(page_id.0));
+            }
+        }
+
+        // Holding the read latch means no writer is mid-modification, and any
+        // writer since the snapshot has already saved its pre-image.
+        let guard = self.fetch_page_read(page_id)?;
+        let mut copy = Page::new();
+        let cow = self.cow_snapshot.lock();
+        let source = cow
+            .as_ref()
+            .and_then(|c| c.pages.get(&page_id))
+            .unwrap_or(&*guard);
+        copy.as_mut_slice().copy_from_slice(source.as_slice());
+        Ok(copy)
     }
 
-    /// Check if a page is in the buffer pool.
-    pub fn contains_page(&self, page_id: PageId) -> bool {
-        self.page_table.read().contains_key(&page_id)
+    /// Save a page's pre-image if a snapshot is active and it isn't saved yet.
+    ///
+    /// Must be called with the page's write latch held, before any changes.
+    pub(crate) fn preserve_for_snapshot(&self, page_id: PageId, page: &Page) {
+        let mut cow = self.cow_snapshot.lock();
+        if let Some(cow) = cow.as_mut() {
+            if page_id.0 < cow.page_count {
+                cow.pages.entry(page_id).or_insert_with(|| {
+                    let mut copy = Page::new();
+                    copy.as_mut_slice().copy_from_slice(page.as_slice());
+                    copy
+                });
+            }
+        }
     }
 
     // ========================================================================
-    // Internal: Called by PageGuard on drop
+    // Public API: Resizing
     // ========================================================================
 
-    /// Unpin a page. Called by PageReadGuard/PageWriteGuard on drop.
-    pub(crate) fn unpin_page_internal(&self, frame_id: FrameId, is_dirty: bool) {
-        let frame = &self.frames[frame_id.0];
+    /// Shrink the pool to `new_size` frames. Returns the size achieved.
+    ///
+    /// Free frames are retired first, then unpinned pages are evicted in
+    /// replacer order, flushing any that are dirty. Pinned frames are never
+    /// dropped, so the result is larger than `new_size` if too many frames
+    /// are pinned.
+    ///
+    /// Retired frames are reset, which drops the page they held, and are
+    /// never reused. Their page memory is handed back to the OS where
+    /// possible (see [`Frame::release_page_memory`]); the frame array itself
+    /// is fixed, so the slots stay allocated until the pool is dropped.
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `new_size` is 0
+    /// - Any error flushing an evicted page; frames retired before the
+    ///   failure stay retired
+    pub fn resize_down(&self, new_size: usize) -> Result<usize> {
+        if new_size == 0 {
+            return Err(Error::InvalidConfig("pool size must be > 0".to_string()));
+        }
 
-        if is_dirty {
-            frame.mark_dirty();
+        let mut size = self.pool_size();
+        while size > new_size {
+            let free = self.free_list.lock().pop();
+            let frame_id = match free {
+                Some(frame_id) => frame_id,
+                None => match self.evict_page() {
+                    Ok(frame_id) => frame_id,
+                    Err(Error::NoFreeFrames) => break,
+                    Err(e) => return Err(e),
+                },
+            };
+            self.forget_deferred_access(frame_id);
+            let frame = &self.frames[frame_id.0];
+            frame.release_page_memory();
+            frame.reset();
+            size = self.pool_size.fetch_sub(1, Ordering::Relaxed) - 1;
         }
+        Ok(size)
+    }
 
-        let new_pin_count = frame.unpin();
+    /// Evict every unpinned, clean page and return how many were released.
+    ///
+    /// Nothing is written to disk. The released frames go back on the free
+    /// list with their memory handed back to the OS where possible (see
+    /// [`Frame::release_page_memory`]), so a host under memory pressure can
+    /// call this to shrink RSS quickly. Released pages skip the victim cache.
+    pub fn release_clean_pages(&self) -> usize {
+        let mut tried = HashSet::new();
+        let mut released = 0;
+        loop {
+            // Claim a victim through the replacer, as an evictor would, so
+            // no concurrent eviction can take the same frame
+            let victim = self.lock_replacer().evict_if(&mut |fid| {
+                let frame = &self.frames[fid.0];
+                !tried.contains(&fid) && !frame.is_pinned() && !frame.is_dirty()
+            });
+            let Some(frame_id) = victim else {
+                return released;
+            };
+            tried.insert(frame_id);
+            let frame = &self.frames[frame_id.0];
+            frame.set_replacer_evictable(true);
+            let Some(pid) = frame.page_id() else {
+                continue;
+            };
 
-        if new_pin_count == 0 {
-            let mut replacer = self.replacer.lock();
-            replacer.set_evictable(frame_id, true);
+            let mut pt = self.page_table.write();
+            if frame.is_pinned() || frame.is_dirty() {
+                drop(pt);
+                self.restore_victim(frame_id, pid);
+                continue;
+            }
+            pt.remove(&pid);
+            drop(pt);
+            self.drop_shadow(pid);
+            self.miss_history.lock().evicted.insert(pid);
+            trace_event!(DEBUG, "eviction", page_id = pid.0, frame_id = frame_id.0);
+
+            self.stats.evictions.fetch_add(1, Ordering::Relaxed);
+            frame.set_page_id(None);
+            self.forget_deferred_access(frame_id);
+            frame.set_replacer_evictable(false);
+            frame.release_page_memory();
+            self.free_list.lock().push(frame_id);
+            released += 1;
         }
     }
 
     // ========================================================================
-    // Internal: Core fetch logic
+    // Public API: Disk manager access
     // ========================================================================
 
-    fn fetch_page_internal(&self, page_id: PageId) -> Result<FrameId> {
-        // Fast path: cache hit
-        {
+    /// Run `f` with exclusive access to the underlying `DiskManager`.
+    ///
+    /// The disk manager lock is held only for the duration of `f`. All pool
+    /// I/O waits meanwhile, and `f` must not call back into the pool or it
+    /// will deadlock. Writing pages that are resident in the pool, or
+    /// operations like [`DiskManager::compact`] that move pages, leave the
+    /// cached copies stale: flush and quiesce the pool first.
+    pub fn with_disk_manager<R>(&self, f: impl FnOnce(&mut DiskManager) -> R) -> R {
+        let mut dm = self.disk_manager.lock();
+        let result = f(&mut dm);
+        // `f` may have allocated pages
+        self.not_found.lock().clear();
+        result
+    }
+
+    // ========================================================================
+    // Public API: Stats and info
+    // ========================================================================
+
+    /// Get buffer pool statistics.
+    pub fn stats(&self) -> &BufferPoolStats {
+        &self.stats
+    }
+
+    /// Get the pool size: the number of frames in circulation.
+    pub fn pool_size(&self) -> usize {
+        self.pool_size.load(Ordering::Relaxed)
+    }
+
+    /// Set how many pages to prefetch when reads look sequential.
+    ///
+    /// When `fetch_page_read(N)` directly follows `fetch_page_read(N - 1)`,
+    /// pages `N + 1 ..= N + pages` are loaded into the pool unpinned.
+    /// Pass 0 to disable read-ahead (the default).
+    pub fn set_readahead(&self, pages: usize) {
+        self.readahead.store(pages, Ordering::Relaxed);
+    }
+
+    /// Get the current read-ahead window in pages.
+    pub fn readahead(&self) -> usize {
+        self.readahead.load(Ordering::Relaxed)
+    }
+
+    /// Limit how many times a single frame may be pinned concurrently.
+    ///
+    /// Fetching a page that already has `max` pins fails with
+    /// `Error::PinLimitExceeded`, catching pin leaks early. `None` (the
+    /// default) means unlimited.
+    pub fn set_max_pin_count(&self, max: Option<u32>) {
+        let max = max.map_or(0, |m| m.max(1));
+        self.max_pin_count.store(max, Ordering::Relaxed);
+    }
+
+    /// Get the configured maximum pin count, if any.
+    pub fn max_pin_count(&self) -> Option<u32> {
+        match self.max_pin_count.load(Ordering::Relaxed) {
+            0 => None,
+            max => Some(max),
+        }
+    }
+
+    /// Limit how many pins may be outstanding across the whole pool.
+    ///
+    /// Once `budget` pins are held, fetches and new pages block until a
+    /// guard drops. Setting it below `pool_size` keeps headroom so the pool
+    /// never becomes fully pinned and fails with `Error::NoFreeFrames`. A
+    /// thread that already holds pins must not fetch more than the budget
+    /// allows or it waits on itself forever. `None` (the default) means
+    /// unlimited.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidConfig` if `budget` is `Some(0)`.
+    pub fn set_pin_budget(&self, budget: Option<usize>) -> Result<()> {
+        if budget == Some(0) {
+            return Err(Error::InvalidConfig("pin budget must be > 0".to_string()));
+        }
+        self.pin_budget
+            .store(budget.unwrap_or(0), Ordering::Release);
+
+        // Waiters re-check against the new budget
+        let _lock = self.pin_budget_lock.lock();
+        self.pin_released.notify_all();
+        Ok(())
+    }
+
+    /// Get the configured pin budget, if any.
+    pub fn pin_budget(&self) -> Option<usize> {
+        match self.pin_budget.load(Ordering::Acquire) {
+            0 => None,
+            budget => Some(budget),
+        }
+    }
+
+    /// Bound how long a fetch blocks waiting for the pin budget.
+    ///
+    /// With a timeout set, a fetch or new page that can't get a pin permit
+    /// within `timeout` fails with `Error::LockTimeout` instead of waiting
+    /// forever, so threads that deadlock on each other's pins get an error
+    /// back. Page latch waits are not covered. `None` (the default) waits
+    /// indefinitely.
+    pub fn set_lock_timeout(&self, timeout: Option<Duration>) {
+        let us = timeout.map_or(0, |t| (t.as_micros() as u64).max(1));
+        self.lock_timeout_us.store(us, Ordering::Relaxed);
+    }
+
+    /// Get the configured lock timeout, if any.
+    pub fn lock_timeout(&self) -> Option<Duration> {
+        match self.lock_timeout_us.load(Ordering::Relaxed) {
+            0 => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    /// Sample the latency of one in every `every` fetches.
+    ///
+    /// Sampled fetches are timed from the pin request until the page is
+    /// pinned, and recorded separately for hits and misses (which include
+    /// the disk read). Each class keeps a bounded uniform sample; see
+    /// [`latency_percentiles`](Self::latency_percentiles). Changing the
+    /// setting discards the samples collected so far. `None` (the default)
+    /// disables sampling, leaving a single atomic load on the fetch path.
+    pub fn set_latency_sampling(&self, every: Option<u32>) {
+        let every = every.map_or(0, |n| n.max(1));
+        self.latency_sample_every.store(every, Ordering::Relaxed);
+        self.hit_latency.lock().clear();
+        self.miss_latency.lock().clear();
+    }
+
+    /// Get the configured latency sampling interval, if any.
+    pub fn latency_sampling(&self) -> Option<u32> {
+        match self.latency_sample_every.load(Ordering::Relaxed) {
+            0 => None,
+            every => Some(every),
+        }
+    }
+
+    /// p50/p99 of the sampled hit and miss latencies.
+    ///
+    /// A class with no samples reports zero latencies and `samples == 0`.
+    pub fn latency_percentiles(&self) -> AccessLatency {
+        AccessLatency {
+            hit: self.hit_latency.lock().percentiles(),
+            miss: self.miss_latency.lock().percentiles(),
+        }
+    }
+
+    /// Avoid evicting dirty pages written within `window`.
+    ///
+    /// When the replacer's victim is dirty and was written that recently,
+    /// the next victims are tried first, so a hot page absorbs further writes
+    /// in memory instead of being flushed after each burst. If every
+    /// evictable frame is such a page, the replacer's first choice is evicted
+    /// anyway. `None` (the default) disables the check.
+    pub fn set_write_coalesce_window(&self, window: Option<Duration>) {
+        let us = window.map_or(0, |w| (w.as_micros() as u64).max(1));
+        self.write_coalesce_window_us.store(us, Ordering::Relaxed);
+    }
+
+    /// Get the configured write coalescing window, if any.
+    pub fn write_coalesce_window(&self) -> Option<Duration> {
+        match self.write_coalesce_window_us.load(Ordering::Relaxed) {
+            0 => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    /// Evict clean pages before dirty ones.
+    ///
+    /// Evicting a dirty page blocks on writing it back, which is slow on a
+    /// busy disk. When enabled, the replacer is asked for its first clean
+    /// candidate and only falls back to a dirty one if no clean frame is
+    /// evictable. Off by default, which keeps the replacer's order exact.
+    pub fn set_prefer_clean_victims(&self, enabled: bool) {
+        self.prefer_clean_victims.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether eviction prefers clean pages.
+    pub fn prefer_clean_victims(&self) -> bool {
+        self.prefer_clean_victims.load(Ordering::Relaxed)
+    }
+
+    /// Keep at least `frames` frames free, so fetches that miss rarely have
+    /// to evict (and possibly flush) a page first.
+    ///
+    /// The reserve is topped up right away and then whenever a page is
+    /// unpinned, by evicting unpinned pages in replacer order; the thread
+    /// that unpins pays for any flush. If too few pages are unpinned, the
+    /// pool runs below the reserve until more are. Pass 0 to disable (the
+    /// default).
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `frames` isn't less than the pool size
+    /// - Any error flushing an evicted page; the reserve stays configured
+    pub fn set_min_free_reserve(&self, frames: usize) -> Result<()> {
+        if frames >= self.pool_size() {
+            return Err(Error::InvalidConfig(format!(
+                "free frame reserve {} must be less than pool size {}",
+                frames,
+                self.pool_size()
+            )));
+        }
+        self.min_free_reserve.store(frames, Ordering::Relaxed);
+        self.top_up_reserve()
+    }
+
+    /// Get the configured free frame reserve.
+    pub fn min_free_reserve(&self) -> usize {
+        self.min_free_reserve.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of free frames.
+    pub fn free_frame_count(&self) -> usize {
+        self.free_list.lock().len()
+    }
+
+    /// Get the number of pages in the buffer pool.
+    pub fn page_count(&self) -> usize {
+        self.page_table.read().len()
+    }
+
+    /// Fraction of the pool's frames holding dirty pages (0.0 to 1.0).
+    ///
+    /// Frames are checked without locking, so the value is approximate
+    /// while writers are active.
+    pub fn dirty_ratio(&self) -> f64 {
+        let dirty = self.frames.iter().filter(|f| f.is_dirty()).count();
+        dirty as f64 / self.pool_size() as f64
+    }
+
+    /// Append one CSV row of pool metrics to `out`, preceded by a header
+    /// line if `header` is true.
+    ///
+    /// Columns are `timestamp_us` (microseconds since the pool was created),
+    /// `resident_pages`, `dirty_ratio`, then the [`StatsSnapshot`] counters
+    /// in [`StatsSnapshot::CSV_HEADER`] order. Call it periodically with the
+    /// same writer to log a time series for offline plotting.
+    pub fn export_metrics_csv<W: std::io::Write>(&self, out: &mut W, header: bool) -> Result<()> {
+        if header {
+            writeln!(
+                out,
+                "timestamp_us,resident_pages,dirty_ratio,{}",
+                StatsSnapshot::CSV_HEADER
+            )?;
+        }
+        writeln!(
+            out,
+            "{},{},{:.4},{}",
+            self.now_us(),
+            self.page_count(),
+            self.dirty_ratio(),
+            self.stats.snapshot().csv_fields()
+        )?;
+        Ok(())
+    }
+
+    /// Get pin count for a page. Returns None if page not in pool.
+    ///
+    /// Matches BusTub's `GetPinCount()`.
+    pub fn get_pin_count(&self, page_id: PageId) -> Option<u32> {
+        let pt = self.page_table.read();
+        let &frame_id = pt.get(&page_id)?;
+        Some(self.frames[frame_id.0].pin_count())
+    }
+
+    /// Describe the eviction policy's internal state for debugging.
+    pub fn replacer_debug(&self) -> String {
+        self.lock_replacer().debug_state()
+    }
+
+    /// Report the page the replacer would evict if a frame were needed now.
+    ///
+    /// Applies the same rules as eviction (pinned frames, clean-first
+    /// preference, the write coalescing window) but only looks: no frame
+    /// is claimed and the replacer's queue is left untouched. Cache hits
+    /// not yet reported to the replacer are ignored, which FIFO doesn't
+    /// reorder on anyway. Returns None if every frame is pinned. Meant for
+    /// debugging and visualizing the policy; another thread can change the
+    /// answer before it is used.
+    pub fn would_evict_next(&self) -> Option<PageId> {
+        let replacer = self.lock_replacer();
+        let mut unpinned = |fid: FrameId| !self.frames[fid.0].is_pinned();
+        let window = self.write_coalesce_window_us.load(Ordering::Relaxed);
+        let mut eligible =
+            |fid: FrameId| unpinned(fid) && (window == 0 || !self.written_within(fid, window));
+        let victim = if self.prefer_clean_victims() {
+            let dirty = |fid: FrameId| self.frames[fid.0].is_dirty();
+            replacer
+                .peek_if(&mut |fid| eligible(fid) && !dirty(fid))
+                .or_else(|| replacer.peek_if(&mut eligible))
+        } else {
+            replacer.peek_if(&mut eligible)
+        };
+        let victim = match victim {
+            None if window != 0 => replacer.peek_if(&mut unpinned),
+            victim => victim,
+        };
+        victim.and_then(|fid| self.frames[fid.0].page_id())
+    }
+
+    /// Get the generation of a frame.
+    ///
+    /// Compare against a previously observed generation to detect that a
+    /// cached `FrameId` has since been reassigned to a different page.
+    pub fn frame_generation(&self, frame_id: FrameId) -> Option<u64> {
+        self.frames.get(frame_id.0).map(Frame::generation)
+    }
+
+    /// Check if a page is in the buffer pool.
+    pub fn contains_page(&self, page_id: PageId) -> bool {
+        self.page_table.read().contains_key(&page_id)
+    }
+
+    /// When a resident page was last pinned, in microseconds since the pool
+    /// was created. None if the page isn't resident.
+    ///
+    /// The timestamp lives on the frame, so time-aware replacement policies
+    /// can read it instead of keeping their own per-frame map. Eviction
+    /// clears it.
+    pub fn last_access(&self, page_id: PageId) -> Option<u64> {
+        let pt = self.page_table.read();
+        let &frame_id = pt.get(&page_id)?;
+        self.frames[frame_id.0].last_access()
+    }
+
+    /// Capture a consistent, read-only copy of pool metadata.
+    ///
+    /// Frame residency is taken from the page table while its read lock is
+    /// held, so the resident set and per-frame page IDs always agree. Pin
+    /// counts and dirty flags are read from the frames' atomics.
+    pub fn snapshot(&self) -> PoolSnapshot {
+        let mut frames: Vec<FrameSnapshot> = (0..self.frames.len())
+            .map(|i| FrameSnapshot {
+                frame_id: FrameId::new(i),
+                page_id: None,
+                pin_count: 0,
+                is_dirty: false,
+                generation: self.frames[i].generation(),
+            })
+            .collect();
+
+        let pt = self.page_table.read();
+        for (&page_id, &frame_id) in pt.iter() {
+            let frame = &self.frames[frame_id.0];
+            let entry = &mut frames[frame_id.0];
+            entry.page_id = Some(page_id);
+            entry.pin_count = frame.pin_count();
+            entry.is_dirty = frame.is_dirty();
+        }
+        drop(pt);
+
+        PoolSnapshot::new(frames)
+    }
+
+    // ========================================================================
+    // Internal: Called by PageGuard
+    // ========================================================================
+
+    /// Write a latched page straight to disk. Used by `PageWriteGuard::flush`.
+    pub(crate) fn write_through(&self, page_id: PageId, page: &Page) -> Result<()> {
+        self.disk_manager.lock().write_page(page_id, page)?;
+        self.record_shadow(page_id, page);
+        self.stats.pages_written.fetch_add(1, Ordering::Relaxed);
+        trace_event!(DEBUG, "flush", page_id = page_id.0);
+        Ok(())
+    }
+
+    /// Add a pin to a frame the caller already holds pinned. Used by
+    /// `PageReadGuard::clone_ref`.
+    ///
+    /// The existing pin keeps the frame resident, so neither the page table
+    /// nor the replacer is touched. The pin counts against the pin budget
+    /// but never waits on it, since the caller may be the only holder able
+    /// to release a pin; the per-frame pin limit is likewise not applied.
+    pub(crate) fn repin_held(&self, frame_id: FrameId) {
+        let frame = &self.frames[frame_id.0];
+        debug_assert!(frame.is_pinned(), "repin of unpinned {}", frame_id);
+        frame.pin();
+        self.pins_outstanding.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Unpin a page. Called by the page guards on drop.
+    ///
+    /// Unpinning a frame that isn't pinned is a bookkeeping bug. Debug builds
+    /// assert; release builds leave the pin count at 0 and return
+    /// `Error::PageNotPinned` rather than taking down the process.
+    pub(crate) fn unpin_page_internal(&self, frame_id: FrameId, is_dirty: bool) -> Result<()> {
+        let result = self.unpin_frame(frame_id, is_dirty);
+        if let Err(Error::PageNotPinned(pid)) = &result {
+            debug_assert!(
+                false,
+                "unpin of unpinned {} in {}",
+                PageId::new(*pid),
+                frame_id
+            );
+        }
+        result
+    }
+
+    /// [`unpin_page_internal`](Self::unpin_page_internal) without the debug
+    /// assertion.
+    fn unpin_frame(&self, frame_id: FrameId, is_dirty: bool) -> Result<()> {
+        let frame = &self.frames[frame_id.0];
+
+        if is_dirty {
+            frame.mark_dirty();
+            frame.record_write(self.now_us());
+        }
+
+        let Some(new_pin_count) = frame.try_unpin() else {
+            let page_id = frame.page_id().unwrap_or(PageId::INVALID);
+            return Err(Error::PageNotPinned(page_id.0));
+        };
+        self.release_pin_permit();
+        if new_pin_count == 0 {
+            self.on_unpinned(frame_id);
+        }
+        Ok(())
+    }
+
+    /// Bookkeeping once a frame's last pin is dropped.
+    fn on_unpinned(&self, frame_id: FrameId) {
+        let frame = &self.frames[frame_id.0];
+
+        // Frames pinned by a cache hit were never marked unevictable, so only
+        // the replacer's view of frames pinned on load, or found pinned when
+        // deferred accesses were reported, needs updating. The fence pairs
+        // with the one in `report_deferred_accesses`.
+        fence(Ordering::SeqCst);
+        if !frame.replacer_evictable() {
+            let mut replacer = self.lock_replacer();
+            replacer.set_evictable(frame_id, true);
+            frame.set_replacer_evictable(true);
+        }
+
+        // A failed flush leaves its page resident; the next unpin retries
+        let _ = self.top_up_reserve();
+    }
+
+    /// Evict unpinned pages until the free frame reserve is met or nothing
+    /// else is evictable.
+    fn top_up_reserve(&self) -> Result<()> {
+        let reserve = self.min_free_reserve();
+        while reserve > 0 && self.free_frame_count() < reserve {
+            match self.evict_page() {
+                Ok(frame_id) => self.free_list.lock().push(frame_id),
+                Err(Error::NoFreeFrames) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // Internal: Core fetch logic
+    // ========================================================================
+
+    fn fetch_page_internal(&self, page_id: PageId) -> Result<FrameId> {
+        // Wait for the pin budget before taking any pool locks, so waiting
+        // doesn't block the threads that would release a pin
+        self.acquire_pin_permit()?;
+        let sample_start = self.latency_sample_start();
+        match self.pin_page(page_id) {
+            Ok((frame_id, hit)) => {
+                if let Some(start) = sample_start {
+                    let reservoir = if hit {
+                        &self.hit_latency
+                    } else {
+                        &self.miss_latency
+                    };
+                    reservoir.lock().record(start.elapsed());
+                }
+                Ok(frame_id)
+            }
+            Err(e) => {
+                self.release_pin_permit();
+                Err(e)
+            }
+        }
+    }
+
+    /// Pin `page_id`, loading it on a miss. The caller holds a pin permit.
+    ///
+    /// Returns the frame and whether the page was already resident.
+    fn pin_page(&self, page_id: PageId) -> Result<(FrameId, bool)> {
+        // Fast path: cache hit
+        {
             let pt = self.page_table.read();
             if let Some(&frame_id) = pt.get(&page_id) {
-                self.handle_cache_hit(frame_id, page_id);
-                return Ok(frame_id);
+                self.handle_cache_hit(frame_id, page_id)?;
+                return Ok((frame_id, true));
             }
         }
         // Cache miss
-        self.handle_cache_miss(page_id)
+        Ok((self.handle_cache_miss(page_id)?, false))
+    }
+
+    /// Start timing this fetch if it's picked for latency sampling.
+    fn latency_sample_start(&self) -> Option<Instant> {
+        let every = self.latency_sample_every.load(Ordering::Relaxed);
+        if every == 0 {
+            return None;
+        }
+        let tick = self.latency_sample_tick.fetch_add(1, Ordering::Relaxed);
+        tick.is_multiple_of(every as u64).then(Instant::now)
+    }
+
+    fn handle_cache_hit(&self, frame_id: FrameId, page_id: PageId) -> Result<()> {
+        self.pin_resident(frame_id, page_id)?;
+        self.stats.cache_hits.fetch_add(1, Ordering::Relaxed);
+        trace_event!(
+            TRACE,
+            "cache_hit",
+            page_id = page_id.0,
+            frame_id = frame_id.0
+        );
+        Ok(())
+    }
+
+    /// Pin a frame found in the page table. The caller holds the page table
+    /// lock, so the frame can't be evicted before the pin lands.
+    fn pin_resident(&self, frame_id: FrameId, page_id: PageId) -> Result<()> {
+        let frame = &self.frames[frame_id.0];
+        match self.max_pin_count() {
+            Some(max) => {
+                frame
+                    .try_pin(max)
+                    .ok_or(Error::PinLimitExceeded(page_id.0))?;
+            }
+            None => {
+                frame.pin();
+            }
+        }
+
+        frame.record_access_time(self.now_us());
+
+        // Report the access to the replacer at the next eviction instead of
+        // taking its lock here. If the frame was evictable, the replacer
+        // keeps listing it so; eviction skips frames that are pinned.
+        if !frame.has_pending_access() {
+            let mut deferred = self.deferred.lock();
+            if frame.defer_access() {
+                deferred.push(frame_id);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_cache_miss(&self, page_id: PageId) -> Result<FrameId> {
+        self.stats.cache_misses.fetch_add(1, Ordering::Relaxed);
+        self.classify_miss(page_id);
+        let frame_id = self.load_page(page_id)?;
+        trace_event!(
+            TRACE,
+            "cache_miss",
+            page_id = page_id.0,
+            frame_id = frame_id.0
+        );
+        Ok(frame_id)
+    }
+
+    /// Attribute a miss to compulsory, capacity, or conflict.
+    fn classify_miss(&self, page_id: PageId) {
+        let history = self.miss_history.lock();
+        let counter = if !history.seen.contains(&page_id) {
+            &self.stats.compulsory_misses
+        } else if history.evicted.contains(&page_id) {
+            &self.stats.capacity_misses
+        } else {
+            &self.stats.conflict_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that a page became resident.
+    fn record_resident(&self, page_id: PageId) {
+        let mut history = self.miss_history.lock();
+        history.seen.insert(page_id);
+        history.evicted.remove(&page_id);
+    }
+
+    /// Read a page from disk (or the victim cache) into a free frame and pin it.
+    ///
+    /// Page IDs recently found missing on disk fail with
+    /// `Error::PageNotFound` before a frame is taken.
+    fn load_page(&self, page_id: PageId) -> Result<FrameId> {
+        if self.not_found.lock().contains(&page_id) {
+            return Err(Error::PageNotFound(page_id.0));
+        }
+
+        // Take the victim copy first: making room may evict a page into the
+        // victim cache and push this one out
+        let victim = self
+            .victim_cache
+            .as_ref()
+            .and_then(|victims| victims.lock().take(page_id));
+        let frame_id = match self.get_free_frame() {
+            Ok(frame_id) => frame_id,
+            Err(e) => {
+                if let (Some(victims), Some(page)) = (&self.victim_cache, victim) {
+                    victims.lock().insert(page_id, page);
+                }
+                return Err(e);
+            }
+        };
+
+        let page_data = match victim {
+            Some(page) => {
+                self.stats.victim_hits.fetch_add(1, Ordering::Relaxed);
+                page
+            }
+            None => {
+                let read = {
+                    let mut dm = self.disk_manager.lock();
+                    let read = dm.read_page(page_id);
+                    if let Err(Error::PageNotFound(_)) = read {
+                        self.remember_not_found(page_id);
+                    }
+                    read
+                };
+                match read {
+                    Ok(page) => {
+                        self.stats.pages_read.fetch_add(1, Ordering::Relaxed);
+                        page
+                    }
+                    Err(e) => {
+                        self.free_list.lock().push(frame_id);
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
+        {
+            let mut page = self.frames[frame_id.0].page_mut();
+            page.as_mut_slice().copy_from_slice(page_data.as_slice());
+        }
+
+        self.install_frame(frame_id, page_id)
+    }
+
+    /// Remember that `page_id` isn't on disk. Call with the disk manager
+    /// lock held.
+    fn remember_not_found(&self, page_id: PageId) {
+        let mut not_found = self.not_found.lock();
+        if !not_found.contains(&page_id) {
+            if not_found.len() == NOT_FOUND_CACHE_SIZE {
+                not_found.pop_front();
+            }
+            not_found.push_back(page_id);
+        }
+    }
+
+    /// Drop `page_id` from the not-found cache once it's allocated. Call
+    /// with the disk manager lock held.
+    fn forget_not_found(&self, page_id: PageId) {
+        self.not_found.lock().retain(|&pid| pid != page_id);
+    }
+
+    /// Map a filled, unmapped frame to `page_id` and pin it.
+    ///
+    /// Two threads can miss on the same page and both fill a frame. Only the
+    /// first to take the page table write lock installs its frame; the other
+    /// pins that frame instead and returns its own to the free list. Returns
+    /// the frame that ends up holding the page.
+    fn install_frame(&self, frame_id: FrameId, page_id: PageId) -> Result<FrameId> {
+        let frame = &self.frames[frame_id.0];
+        {
+            let mut pt = self.page_table.write();
+            if let Some(&existing) = pt.get(&page_id) {
+                let pinned = self.pin_resident(existing, page_id);
+                drop(pt);
+                self.free_list.lock().push(frame_id);
+                return pinned.map(|()| existing);
+            }
+            frame.set_page_id(Some(page_id));
+            frame.pin();
+            frame.record_access_time(self.now_us());
+            pt.insert(page_id, frame_id);
+        }
+
+        {
+            let mut replacer = self.lock_replacer();
+            replacer.record_access(frame_id, page_id);
+            replacer.set_evictable(frame_id, false);
+            frame.set_replacer_evictable(false);
+        }
+
+        self.record_resident(page_id);
+
+        Ok(frame_id)
+    }
+
+    // ========================================================================
+    // Internal: Read-ahead
+    // ========================================================================
+
+    /// Prefetch the pages following `page_id` if the last read was `page_id - 1`.
+    fn maybe_readahead(&self, page_id: PageId) {
+        let last = self.last_read_page.swap(page_id.0, Ordering::Relaxed);
+        let window = self.readahead.load(Ordering::Relaxed);
+        if window == 0 || last == PageId::INVALID.0 || last.wrapping_add(1) != page_id.0 {
+            return;
+        }
+
+        let disk_pages = self.disk_manager.lock().page_count();
+        let end = (page_id.0 as u64 + window as u64).min(disk_pages as u64 - 1);
+        for next in (page_id.0 as u64 + 1)..=end {
+            // Best effort: stop at the first page we can't bring in
+            if self.prefetch_page(PageId::new(next as u32)).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Load a page into the pool without leaving it pinned.
+    fn prefetch_page(&self, page_id: PageId) -> Result<()> {
+        if self.contains_page(page_id) {
+            return Ok(());
+        }
+        // Best effort: never wait for the pin budget
+        if !self.try_acquire_pin_permit() {
+            return Err(Error::NoFreeFrames);
+        }
+        let frame_id = match self.load_page(page_id) {
+            Ok(frame_id) => frame_id,
+            Err(e) => {
+                self.release_pin_permit();
+                return Err(e);
+            }
+        };
+        self.unpin_page_internal(frame_id, false)
+    }
+
+    // ========================================================================
+    // Internal: Pin budget
+    // ========================================================================
+
+    /// Take a pin permit, waiting while the pin budget is exhausted.
+    fn acquire_pin_permit(&self) -> Result<()> {
+        let deadline = self.lock_timeout().map(|t| Instant::now() + t);
+        while !self.try_acquire_pin_permit() {
+            let mut lock = self.pin_budget_lock.lock();
+            while self.pin_budget_exhausted() {
+                match deadline {
+                    Some(deadline) => {
+                        if self
+                            .pin_released
+                            .wait_until(&mut lock, deadline)
+                            .timed_out()
+                            && self.pin_budget_exhausted()
+                        {
+                            return Err(Error::LockTimeout);
+                        }
+                    }
+                    None => self.pin_released.wait(&mut lock),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Take a pin permit if the pin budget allows it.
+    fn try_acquire_pin_permit(&self) -> bool {
+        let budget = self.pin_budget.load(Ordering::Acquire);
+        if budget == 0 {
+            self.pins_outstanding.fetch_add(1, Ordering::AcqRel);
+            return true;
+        }
+        self.pins_outstanding
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < budget).then_some(n + 1)
+            })
+            .is_ok()
+    }
+
+    fn pin_budget_exhausted(&self) -> bool {
+        let budget = self.pin_budget.load(Ordering::Acquire);
+        budget != 0 && self.pins_outstanding.load(Ordering::Acquire) >= budget
+    }
+
+    /// Return a pin permit, waking a waiter if a budget is set.
+    fn release_pin_permit(&self) {
+        self.pins_outstanding.fetch_sub(1, Ordering::AcqRel);
+        if self.pin_budget.load(Ordering::Acquire) != 0 {
+            // Taking the lock orders this wakeup after any waiter's check
+            let _lock = self.pin_budget_lock.lock();
+            self.pin_released.notify_one();
+        }
+    }
+
+    // ========================================================================
+    // Internal: Frame allocation and eviction
+    // ========================================================================
+
+    fn get_free_frame(&self) -> Result<FrameId> {
+        {
+            let mut fl = self.free_list.lock();
+            if let Some(frame_id) = fl.pop() {
+                return Ok(frame_id);
+            }
+        }
+        self.evict_page()
+    }
+
+    fn evict_page(&self) -> Result<FrameId> {
+        loop {
+            let frame_id = self.select_victim()?;
+            let frame = &self.frames[frame_id.0];
+            let old_page_id = frame.page_id();
+
+            if let Some(pid) = old_page_id {
+                if let Err(e) = self.flush_frame(frame_id, pid) {
+                    self.restore_victim(frame_id, pid);
+                    return Err(e);
+                }
+
+                // A hit may have pinned (or pinned and written) the frame
+                // since it was selected. Hits pin while holding the page table
+                // read lock, so under the write lock this check is final.
+                let mut pt = self.page_table.write();
+                if frame.is_pinned() || frame.is_dirty() {
+                    drop(pt);
+                    self.restore_victim(frame_id, pid);
+                    continue;
+                }
+                pt.remove(&pid);
+                drop(pt);
+                self.drop_shadow(pid);
+                self.miss_history.lock().evicted.insert(pid);
+                if let Some(victims) = &self.victim_cache {
+                    let mut copy = Page::new();
+                    copy.as_mut_slice().copy_from_slice(frame.page().as_slice());
+                    victims.lock().insert(pid, copy);
+                }
+                trace_event!(DEBUG, "eviction", page_id = pid.0, frame_id = frame_id.0);
+            }
+
+            self.stats.evictions.fetch_add(1, Ordering::Relaxed);
+            frame.clear_dirty();
+            frame.set_page_id(None);
+
+            return Ok(frame_id);
+        }
+    }
+
+    /// Ask the replacer for an unpinned victim, honoring the write
+    /// coalescing window.
+    ///
+    /// Accesses deferred by cache hits are reported to the replacer first,
+    /// so it decides on up-to-date information.
+    fn select_victim(&self) -> Result<FrameId> {
+        let mut replacer = self.lock_replacer();
+        self.report_deferred_accesses(&mut **replacer);
+
+        // Frames pinned by a hit may still be listed as evictable
+        let mut unpinned = |fid: FrameId| !self.frames[fid.0].is_pinned();
+        let window = self.write_coalesce_window_us.load(Ordering::Relaxed);
+        let mut eligible =
+            |fid: FrameId| unpinned(fid) && (window == 0 || !self.written_within(fid, window));
+        let victim = if self.prefer_clean_victims() {
+            let mut dirty = |fid: FrameId| self.frames[fid.0].is_dirty();
+            replacer.evict_preferring_clean(&mut eligible, &mut dirty)
+        } else {
+            replacer.evict_if(&mut eligible)
+        };
+        // Pass over recently written dirty frames unless nothing else is left
+        let victim = match victim {
+            None if window != 0 => replacer.evict_if(&mut unpinned),
+            victim => victim,
+        };
+
+        let victim = victim.ok_or(Error::NoFreeFrames)?;
+        // The evictor now owns the frame's replacer state: it either restores
+        // the frame or installs it for a new page. Leaving the flag set keeps
+        // a hit that unpins meanwhile from listing it as evictable again,
+        // which would let a second evictor hand out the same frame.
+        self.frames[victim.0].set_replacer_evictable(true);
+        Ok(victim)
+    }
+
+    /// Report accesses deferred by cache hits to the replacer.
+    ///
+    /// Only frames hit since the last report are visited. Those still pinned
+    /// are marked unevictable, so the replacer's size doesn't count them; the
+    /// unpin that releases them lists them as evictable again.
+    fn report_deferred_accesses(&self, replacer: &mut dyn Replacer) {
+        let deferred = {
+            let mut deferred = self.deferred.lock();
+            for &frame_id in deferred.iter() {
+                self.frames[frame_id.0].take_pending_access();
+            }
+            std::mem::take(&mut *deferred)
+        };
+
+        for frame_id in deferred {
+            let frame = &self.frames[frame_id.0];
+            let Some(page_id) = frame.page_id() else {
+                continue;
+            };
+            replacer.record_access(frame_id, page_id);
+            if frame.is_pinned() && frame.replacer_evictable() {
+                replacer.set_evictable(frame_id, false);
+                frame.set_replacer_evictable(false);
+                // The last unpin may have checked the flag before it was
+                // cleared; if so, nobody else will list the frame again
+                fence(Ordering::SeqCst);
+                if !frame.is_pinned() {
+                    replacer.set_evictable(frame_id, true);
+                    frame.set_replacer_evictable(true);
+                }
+            }
+        }
+    }
+
+    /// Drop a frame's unreported access, e.g. when its page leaves the pool.
+    fn forget_deferred_access(&self, frame_id: FrameId) {
+        let mut deferred = self.deferred.lock();
+        if self.frames[frame_id.0].take_pending_access() {
+            deferred.retain(|&fid| fid != frame_id);
+        }
+    }
+
+    /// Give a selected victim back to the replacer when eviction is aborted.
+    fn restore_victim(&self, frame_id: FrameId, page_id: PageId) {
+        let mut replacer = self.lock_replacer();
+        replacer.record_access(frame_id, page_id);
+        replacer.set_evictable(frame_id, true);
+        self.frames[frame_id.0].set_replacer_evictable(true);
+    }
+
+    /// Lock the replacer (counted in tests).
+    fn lock_replacer(&self) -> MutexGuard<'_, Box<dyn Replacer + Send>> {
+        #[cfg(test)]
+        self.replacer_locks.fetch_add(1, Ordering::Relaxed);
+        self.replacer.lock()
+    }
+
+    /// Check if a frame is dirty and was written in the last `window_us`.
+    fn written_within(&self, frame_id: FrameId, window_us: u64) -> bool {
+        let frame = &self.frames[frame_id.0];
+        frame.is_dirty()
+            && frame
+                .last_write()
+                .is_some_and(|ts| self.now_us().saturating_sub(ts) < window_us)
+    }
+
+    /// Append an operation to the op log, if recording.
+    fn log_op<T>(&self, op: PoolOp, result: &Result<T>) {
+        if let Some(log) = &self.op_log {
+            log.lock().push(LoggedOp::new(op, result));
+        }
+    }
+
+    /// Microseconds since the pool was created, offset so it is never 0.
+    fn now_us(&self) -> u64 {
+        self.epoch.elapsed().as_micros() as u64 + 1
+    }
+
+    /// Write every dirty page in `pages` as one double-write batch.
+    ///
+    /// `pages` is a page table snapshot, so each frame is pinned only if it
+    /// still holds its page; the pin keeps it from being evicted (and its
+    /// page reloaded from the not yet written file) until the batch is on
+    /// disk. Dirty flags are cleared under the page latch as each copy is
+    /// taken, so a write landing during the I/O dirties the page again. If
+    /// the batch fails, its pages are marked dirty and retried one at a
+    /// time, so each failure is reported with its own error.
+    ///
+    /// Returns the number of pages written and the pages that failed.
+    fn flush_batch(&self, pages: &[(PageId, FrameId)]) -> (usize, Vec<(PageId, Error)>) {
+        let mut pinned = Vec::new();
+        for &(page_id, frame_id) in pages {
+            let pt = self.page_table.read();
+            if pt.get(&page_id) == Some(&frame_id) {
+                self.frames[frame_id.0].pin();
+                pinned.push((page_id, frame_id));
+            }
+        }
+
+        let mut batch: Vec<(PageId, FrameId, Page)> = Vec::new();
+        for &(page_id, frame_id) in &pinned {
+            let frame = &self.frames[frame_id.0];
+            let page = frame.page();
+            if frame.is_dirty() {
+                let mut copy = Page::new();
+                copy.as_mut_slice().copy_from_slice(page.as_slice());
+                frame.clear_dirty();
+                batch.push((page_id, frame_id, copy));
+            }
+        }
+
+        let written = if batch.is_empty() {
+            Ok(())
+        } else {
+            let refs: Vec<(PageId, &Page)> = batch.iter().map(|(pid, _, p)| (*pid, p)).collect();
+            self.disk_manager.lock().write_pages(&refs)
+        };
+
+        let mut flushed = 0;
+        let mut failed = Vec::new();
+        match written {
+            Ok(()) => {
+                for (page_id, frame_id, page) in &batch {
+                    self.record_shadow(*page_id, page);
+                    trace_event!(DEBUG, "flush", page_id = page_id.0, frame_id = frame_id.0);
+                }
+                flushed = batch.len();
+                self.stats
+                    .pages_written
+                    .fetch_add(flushed as u64, Ordering::Relaxed);
+            }
+            Err(_) => {
+                for &(page_id, frame_id, _) in &batch {
+                    self.frames[frame_id.0].mark_dirty();
+                    match self.flush_frame(frame_id, page_id) {
+                        Ok(true) => flushed += 1,
+                        Ok(false) => {}
+                        Err(e) => failed.push((page_id, e)),
+                    }
+                }
+            }
+        }
+
+        for (_, frame_id) in pinned {
+            if self.frames[frame_id.0].try_unpin() == Some(0) {
+                self.on_unpinned(frame_id);
+            }
+        }
+        (flushed, failed)
+    }
+
+    /// Save `page` as the last flushed contents of `page_id`, if lossy reads
+    /// are enabled.
+    fn record_shadow(&self, page_id: PageId, page: &Page) {
+        if let Some(shadows) = &self.shadows {
+            let mut shadows = shadows.lock();
+            let shadow = shadows.entry(page_id).or_default();
+            shadow.as_mut_slice().copy_from_slice(page.as_slice());
+        }
+    }
+
+    /// Forget the shadow of a page leaving the pool.
+    fn drop_shadow(&self, page_id: PageId) {
+        if let Some(shadows) = &self.shadows {
+            shadows.lock().remove(&page_id);
+        }
+    }
+
+    /// Write the frame's page to disk if dirty. Returns whether it was written.
+    fn flush_frame(&self, frame_id: FrameId, page_id: PageId) -> Result<bool> {
+        let frame = &self.frames[frame_id.0];
+
+        if frame.is_dirty() {
+            let page = frame.page();
+            {
+                let mut dm = self.disk_manager.lock();
+                dm.write_page(page_id, &page)?;
+            }
+            self.record_shadow(page_id, &page);
+            drop(page);
+
+            frame.clear_dirty();
+            self.stats.pages_written.fetch_add(1, Ordering::Relaxed);
+            trace_event!(DEBUG, "flush", page_id = page_id.0, frame_id = frame_id.0);
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::config::PAGE_SIZE;
+    use crate::common::Lsn;
+    use crate::storage::format::SUPERBLOCK_SIZE;
+    use crate::storage::page::{
+        btree_set_key_count, btree_set_sibling, init_btree_leaf, BTREE_NODE_HEADER_SIZE,
+    };
+    use tempfile::tempdir;
+
+    fn create_test_bpm(pool_size: usize) -> (BufferPoolManager, tempfile::TempDir) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let dm = DiskManager::create(&path).unwrap();
+        (BufferPoolManager::new(pool_size, dm), dir)
+    }
+
+    /// Read a page straight from the file, bypassing the `DiskManager` lock.
+    fn read_page_from_file(path: &std::path::Path, page_id: PageId) -> Page {
+        let bytes = std::fs::read(path).unwrap();
+        let off = SUPERBLOCK_SIZE as usize + page_id.0 as usize * PAGE_SIZE;
+        let mut page = Page::new();
+        page.as_mut_slice()
+            .copy_from_slice(&bytes[off..off + PAGE_SIZE]);
+        page
+    }
+
+    // ========================================================================
+    // Core functionality tests
+    // ========================================================================
+
+    #[test]
+    fn test_new_page_and_fetch() {
+        let (bpm, _dir) = create_test_bpm(10);
+        let data = b"Hello, world!";
+
+        // Create and write
+        let pid = {
+            let mut guard = bpm.new_page().unwrap();
+            assert_eq!(guard.page_id(), PageId::new(0));
+            guard.as_mut_slice()[..data.len()].copy_from_slice(data);
+            guard.page_id()
+        };
+
+        // Read back
+        {
+            let guard = bpm.fetch_page_read(pid).unwrap();
+            assert_eq!(&guard.as_slice()[..data.len()], data);
+        }
+
+        // Delete
+        bpm.delete_page(pid).unwrap();
+        assert!(!bpm.contains_page(pid));
+    }
+
+    #[test]
+    fn test_try_new_validates_pool_size() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let err = BufferPoolManager::try_new(0, DiskManager::create(&path).unwrap());
+        assert!(matches!(err, Err(Error::InvalidConfig(_))));
+        let err = BufferPoolManager::try_new(usize::MAX, DiskManager::open(&path).unwrap());
+        assert!(matches!(err, Err(Error::InvalidConfig(_))));
+
+        let bpm = BufferPoolManager::try_new(2, DiskManager::open(&path).unwrap()).unwrap();
+        assert_eq!(bpm.pool_size(), 2);
+        let pid = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[100] = 7;
+            guard.page_id()
+        };
+        bpm.new_page().unwrap();
+        bpm.new_page().unwrap();
+        assert_eq!(bpm.fetch_page_read(pid).unwrap().as_slice()[100], 7);
+    }
+
+    #[test]
+    fn test_new_page_reads_back_zeroed() {
+        let (bpm, _dir) = create_test_bpm(1);
+
+        // The first page lands in a fresh frame, the second in a frame
+        // that held dirty data
+        let first = bpm.new_page().unwrap().page_id();
+        bpm.fetch_page_write(first)
+            .unwrap()
+            .as_mut_slice()
+            .fill(0xEE);
+        let second = bpm.new_page().unwrap().page_id();
+
+        assert!(bpm
+            .fetch_page_read(second)
+            .unwrap()
+            .as_slice()
+            .iter()
+            .all(|&b| b == 0));
+        let page = bpm.fetch_page_read(first).unwrap();
+        assert!(page.as_slice().iter().all(|&b| b == 0xEE));
+    }
+
+    #[test]
+    fn test_eviction_persists_data() {
+        let (bpm, _dir) = create_test_bpm(1); // Only 1 frame!
+
+        // Create page 0, write data
+        let pid0 = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[0] = 0x42;
+            guard.page_id()
+        };
+
+        // Create page 1 (evicts page 0)
+        let _pid1 = bpm.new_page().unwrap().page_id();
+
+        assert_eq!(bpm.stats().snapshot().evictions, 1);
+
+        // Fetch page 0 - should load from disk with data intact
+        {
+            let guard = bpm.fetch_page_read(pid0).unwrap();
+            assert_eq!(guard.as_slice()[0], 0x42);
+        }
+    }
+
+    #[test]
+    fn test_no_free_frames_when_all_pinned() {
+        let (bpm, _dir) = create_test_bpm(2);
+
+        let _guard1 = bpm.new_page().unwrap();
+        let _guard2 = bpm.new_page().unwrap();
+
+        // All frames pinned
+        assert!(bpm.new_page().is_err());
+    }
+
+    // ========================================================================
+    // fetch_or_create
+    // ========================================================================
+
+    #[test]
+    fn test_fetch_or_create_existing() {
+        let (bpm, _dir) = create_test_bpm(10);
+
+        let pid = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[0] = 0x7E;
+            guard.page_id()
+        };
+        bpm.flush_page(pid).unwrap();
+        bpm.delete_page(pid).unwrap();
+
+        let guard = bpm.fetch_or_create(pid).unwrap();
+        assert_eq!(guard.page_id(), pid);
+        assert_eq!(guard.as_slice()[0], 0x7E);
+    }
+
+    #[test]
+    fn test_fetch_or_create_next() {
+        let (bpm, _dir) = create_test_bpm(10);
+
+        {
+            let mut guard = bpm.fetch_or_create(PageId::new(0)).unwrap();
+            guard.as_mut_slice()[0] = 0x11;
+        }
+
+        // Second call fetches rather than re-allocating
+        {
+            let guard = bpm.fetch_or_create(PageId::new(0)).unwrap();
+            assert_eq!(guard.as_slice()[0], 0x11);
+        }
+
+        assert_eq!(bpm.allocate_page_id().unwrap(), PageId::new(1));
+    }
+
+    #[test]
+    fn test_not_found_cache() {
+        let (bpm, _dir) = create_test_bpm(2);
+        let pid0 = bpm.new_page().unwrap().page_id();
+        let missing = PageId::new(3);
+
+        let free = bpm.free_frame_count();
+        assert!(matches!(
+            bpm.fetch_page_read(missing),
+            Err(Error::PageNotFound(3))
+        ));
+        assert_eq!(bpm.free_frame_count(), free);
+
+        // With every frame pinned, the repeat fetch still fails fast instead
+        // of looking for a frame
+        {
+            let _g0 = bpm.fetch_page_read(pid0).unwrap();
+            let _g1 = bpm.new_page().unwrap();
+            assert_eq!(bpm.free_frame_count(), 0);
+            assert!(matches!(
+                bpm.fetch_page_read(missing),
+                Err(Error::PageNotFound(3))
+            ));
+        }
+
+        // Allocating the ID clears its entry
+        assert_eq!(bpm.allocate_page_id().unwrap(), PageId::new(2));
+        assert_eq!(bpm.allocate_page_id().unwrap(), missing);
+        assert_eq!(bpm.fetch_page_read(missing).unwrap().page_id(), missing);
+    }
+
+    #[test]
+    fn test_fetch_or_create_gap_fails() {
+        let (bpm, _dir) = create_test_bpm(10);
+
+        let result = bpm.fetch_or_create(PageId::new(3));
+        assert!(matches!(result, Err(Error::InvalidPageId(3))));
+        assert_eq!(bpm.allocate_page_id().unwrap(), PageId::new(0));
+    }
+
+    #[test]
+    fn test_replacer_debug() {
+        let (bpm, _dir) = create_test_bpm(3);
+
+        let _p0 = bpm.new_page().unwrap().page_id();
+        let _held = bpm.new_page().unwrap();
+
+        // Free list is LIFO, so the first page lands in frame 2
+        assert_eq!(bpm.replacer_debug(), "FIFO [2*, 1] evictable=1");
+    }
+
+    #[test]
+    fn test_with_boxed_fifo_replacer() {
+        let (bpm, _dir) = create_test_bpm(3);
+        let pids: Vec<PageId> = (0..3).map(|_| bpm.new_page().unwrap().page_id()).collect();
+        let bpm = bpm.with_replacer(Box::new(FifoReplacer::new()));
+
+        // Resident pages were handed over in frame order (free list is LIFO)
+        assert_eq!(bpm.replacer_debug(), "FIFO [0*, 1*, 2*] evictable=3");
+        assert_eq!(bpm.would_evict_next(), Some(pids[2]));
+
+        // Eviction still follows FIFO order and skips pinned pages
+        let held = bpm.fetch_page_read(pids[2]).unwrap();
+        let p3 = bpm.new_page().unwrap().page_id();
+        assert!(bpm.contains_page(pids[2]));
+        assert!(!bpm.contains_page(pids[1]));
+        drop(held);
+
+        // The oldest frame is eligible again once unpinned
+        let guard = bpm.fetch_page_read(pids[1]).unwrap();
+        assert_eq!(guard.page_id(), pids[1]);
+        assert!(!bpm.contains_page(pids[2]));
+        assert!(bpm.contains_page(pids[0]));
+        assert!(bpm.contains_page(p3));
+    }
+
+    #[test]
+    fn test_would_evict_next() {
+        let (bpm, _dir) = create_test_bpm(3);
+        assert_eq!(bpm.would_evict_next(), None);
+
+        let pids: Vec<PageId> = (0..3).map(|_| bpm.new_page().unwrap().page_id()).collect();
+        let _ = bpm.fetch_page_read(pids[0]).unwrap();
+        let before = bpm.replacer_debug();
+
+        // Asking repeatedly doesn't change the answer or the replacer
+        for _ in 0..3 {
+            assert_eq!(bpm.would_evict_next(), Some(pids[0]));
+        }
+        assert_eq!(bpm.replacer_debug(), before);
+
+        // The reported victim is the one actually evicted
+        for _ in 0..3 {
+            let next = bpm.would_evict_next().unwrap();
+            let _ = bpm.new_page().unwrap();
+            assert!(!bpm.contains_page(next));
+        }
+
+        // Pinned pages are never reported
+        let _held: Vec<_> = (0..3).map(|_| bpm.new_page().unwrap()).collect();
+        assert_eq!(bpm.would_evict_next(), None);
+    }
+
+    // ========================================================================
+    // Overwrite-only fetch
+    // ========================================================================
+
+    #[test]
+    fn test_fetch_page_write_no_read() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut dm = DiskManager::create(&path).unwrap();
+        let pid = dm.allocate_page().unwrap();
+        let bpm = BufferPoolManager::new(2, dm);
+
+        {
+            let mut guard = bpm.fetch_page_write_no_read(pid).unwrap();
+            assert!(guard.as_slice().iter().all(|&b| b == 0));
+            guard.as_mut_slice().fill(0xC3);
+            assert!(bpm.snapshot().frame_for(pid).unwrap().is_dirty);
+        }
+        assert_eq!(bpm.stats().snapshot().pages_read, 0);
+
+        assert!(matches!(
+            bpm.fetch_page_write_no_read(PageId::new(5)),
+            Err(Error::PageNotFound(5))
+        ));
+
+        bpm.flush_all_pages().unwrap();
+        drop(bpm);
+
+        let mut dm = DiskManager::open(&path).unwrap();
+        assert!(dm
+            .read_page(pid)
+            .unwrap()
+            .as_slice()
+            .iter()
+            .all(|&b| b == 0xC3));
+    }
+
+    // ========================================================================
+    // Pin limit
+    // ========================================================================
+
+    #[test]
+    fn test_max_pin_count() {
+        let (bpm, _dir) = create_test_bpm(4);
+        let pid = bpm.new_page().unwrap().page_id();
+
+        // Default is unbounded
+        assert_eq!(bpm.max_pin_count(), None);
+        let guards: Vec<_> = (0..100)
+            .map(|_| bpm.fetch_page_read(pid).unwrap())
+            .collect();
+        assert_eq!(bpm.get_pin_count(pid), Some(100));
+        drop(guards);
+
+        bpm.set_max_pin_count(Some(2));
+        let g1 = bpm.fetch_page_read(pid).unwrap();
+        let _g2 = bpm.fetch_page_read(pid).unwrap();
+        assert!(matches!(
+            bpm.fetch_page_read(pid),
+            Err(Error::PinLimitExceeded(p)) if p == pid.0
+        ));
+        assert_eq!(bpm.get_pin_count(pid), Some(2));
+
+        drop(g1);
+        assert!(bpm.fetch_page_read(pid).is_ok());
+    }
+
+    #[test]
+    fn test_pin_budget_blocks_until_release() {
+        use std::sync::atomic::AtomicBool;
+        use std::thread;
+        use std::time::Duration;
+
+        let (bpm, _dir) = create_test_bpm(4);
+        let p0 = bpm.new_page().unwrap().page_id();
+        let p1 = bpm.new_page().unwrap().page_id();
+
+        assert_eq!(bpm.pin_budget(), None);
+        assert!(matches!(
+            bpm.set_pin_budget(Some(0)),
+            Err(Error::InvalidConfig(_))
+        ));
+        bpm.set_pin_budget(Some(2)).unwrap();
+        assert_eq!(bpm.pin_budget(), Some(2));
+
+        let g0 = bpm.fetch_page_read(p0).unwrap();
+        let _g1 = bpm.fetch_page_read(p1).unwrap();
+
+        let fetched = AtomicBool::new(false);
+        thread::scope(|s| {
+            let waiter = s.spawn(|| {
+                let guard = bpm.fetch_page_read(p0).unwrap();
+                fetched.store(true, Ordering::SeqCst);
+                drop(guard);
+            });
+
+            thread::sleep(Duration::from_millis(50));
+            assert!(!fetched.load(Ordering::SeqCst));
+            assert_eq!(bpm.get_pin_count(p0), Some(1));
+
+            drop(g0);
+            waiter.join().unwrap();
+        });
+        assert!(fetched.load(Ordering::SeqCst));
+
+        // A failed fetch returns its permit
+        assert!(bpm.fetch_page_read(PageId::new(99)).is_err());
+        let _g0 = bpm.fetch_page_read(p0).unwrap();
+    }
+
+    #[test]
+    fn test_lock_timeout_when_all_frames_pinned() {
+        let (bpm, _dir) = create_test_bpm(2);
+        let p0 = bpm.new_page().unwrap().page_id();
+        let p1 = bpm.new_page().unwrap().page_id();
+        bpm.set_pin_budget(Some(2)).unwrap();
+
+        assert_eq!(bpm.lock_timeout(), None);
+        let timeout = Duration::from_millis(50);
+        bpm.set_lock_timeout(Some(timeout));
+        assert_eq!(bpm.lock_timeout(), Some(timeout));
+
+        // Both frames pinned for the rest of the test
+        let _g0 = bpm.fetch_page_read(p0).unwrap();
+        let _g1 = bpm.fetch_page_read(p1).unwrap();
+
+        let start = Instant::now();
+        assert!(matches!(bpm.fetch_page_read(p0), Err(Error::LockTimeout)));
+        assert!(start.elapsed() >= timeout);
+        assert!(matches!(bpm.new_page(), Err(Error::LockTimeout)));
+
+        // The timed-out fetches didn't leak pins
+        assert_eq!(bpm.get_pin_count(p0), Some(1));
+        bpm.set_lock_timeout(None);
+        assert_eq!(bpm.lock_timeout(), None);
+    }
+
+    // ========================================================================
+    // Copy-on-write snapshots
+    // ========================================================================
+
+    #[test]
+    fn test_cow_snapshot_backup_during_writes() {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+        use std::thread;
+
+        let (bpm, _dir) = create_test_bpm(4);
+        let bpm = Arc::new(bpm);
+        let pids: Vec<PageId> = (0..8)
+            .map(|_| {
+                let mut guard = bpm.new_page().unwrap();
+                guard.as_mut_slice()[0] = 1;
+                guard.page_id()
+            })
+            .collect();
+
+        assert!(bpm.begin_cow_snapshot());
+        assert!(!bpm.begin_cow_snapshot());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let bpm = Arc::clone(&bpm);
+            let stop = Arc::clone(&stop);
+            let pids = pids.clone();
+            thread::spawn(move || {
+                let mut value = 2u8;
+                while !stop.load(Ordering::Relaxed) {
+                    for &pid in &pids {
+                        bpm.fetch_page_write(pid).unwrap().as_mut_slice()[0] = value;
+                    }
+                    value = value.wrapping_add(1).max(2);
+                }
+            })
+        };
+
+        // Backup reads interleave with the writer but see the snapshot instant
+        for _ in 0..5 {
+            for &pid in &pids {
+                assert_eq!(bpm.read_cow_page(pid).unwrap().as_slice()[0], 1);
+            }
+        }
+
+        // Pages allocated after the snapshot aren't part of it
+        let late = bpm.allocate_page_id().unwrap();
+        assert!(matches!(
+            bpm.read_cow_page(late),
+            Err(Error::PageNotFound(_))
+        ));
+
+        stop.store(true, Ordering::Relaxed);
+        writer.join().unwrap();
+
+        bpm.end_cow_snapshot();
+        assert!(!bpm.cow_snapshot_active());
+        assert_ne!(bpm.read_cow_page(pids[0]).unwrap().as_slice()[0], 1);
+    }
+
+    // ========================================================================
+    // Miss classification
+    // ========================================================================
+
+    #[test]
+    fn test_miss_breakdown() {
+        let dir = tempdir().unwrap();
+        let mut dm = DiskManager::create(dir.path().join("test.db")).unwrap();
+        let pids: Vec<PageId> = (0..4).map(|_| dm.allocate_page().unwrap()).collect();
+        let bpm = BufferPoolManager::new(2, dm);
+
+        // First pass: every page is new
+        for &pid in &pids {
+            bpm.fetch_page_read(pid).unwrap();
+        }
+        let snap = bpm.stats().snapshot();
+        assert_eq!(snap.compulsory_misses, 4);
+        assert_eq!(snap.capacity_misses, 0);
+
+        // Looping over 4 pages with 2 frames: every miss is a capacity miss
+        for &pid in &pids {
+            bpm.fetch_page_read(pid).unwrap();
+        }
+        let snap = bpm.stats().snapshot();
+        assert_eq!(snap.compulsory_misses, 4);
+        assert_eq!(snap.capacity_misses, 4);
+        assert_eq!(snap.conflict_misses, 0);
+
+        // Explicitly deleted, then refetched: neither new nor evicted
+        bpm.delete_page(pids[3]).unwrap();
+        bpm.fetch_page_read(pids[3]).unwrap();
+        let snap = bpm.stats().snapshot();
+        assert_eq!(snap.conflict_misses, 1);
+        assert_eq!(
+            snap.compulsory_misses + snap.capacity_misses + snap.conflict_misses,
+            snap.cache_misses
+        );
+    }
+
+    // ========================================================================
+    // Frame generation
+    // ========================================================================
+
+    #[test]
+    fn test_frame_generation_changes_on_reuse() {
+        let (bpm, _dir) = create_test_bpm(1);
+
+        let (pid0, fid) = {
+            let guard = bpm.new_page().unwrap();
+            (guard.page_id(), guard.frame_id())
+        };
+        let gen = bpm.frame_generation(fid).unwrap();
+
+        // Plain pins/unpins keep the generation
+        for _ in 0..3 {
+            let guard = bpm.fetch_page_read(pid0).unwrap();
+            assert_eq!(guard.frame_id(), fid);
+        }
+        assert_eq!(bpm.frame_generation(fid), Some(gen));
+
+        // Eviction + reuse of the only frame bumps it
+        let guard = bpm.new_page().unwrap();
+        assert_eq!(guard.frame_id(), fid);
+        assert_ne!(bpm.frame_generation(fid), Some(gen));
+        assert_eq!(
+            bpm.snapshot().frames()[fid.0].generation,
+            bpm.frame_generation(fid).unwrap()
+        );
+
+        assert_eq!(bpm.frame_generation(FrameId::new(1)), None);
+    }
+
+    // ========================================================================
+    // Double-write flush
+    // ========================================================================
+
+    #[test]
+    fn test_fetch_detects_torn_sector() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut dm = DiskManager::create(&path).unwrap();
+        dm.enable_sector_checksums().unwrap();
+        let bpm = BufferPoolManager::new(4, dm);
+
+        let pid = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[2000] = 0x77;
+            guard.page_id()
+        };
+        bpm.flush_all_pages().unwrap();
+        drop(bpm);
+
+        // Only sector 3 of the page reaches the disk
+        let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        let offset = SUPERBLOCK_SIZE + (pid.0 as usize * PAGE_SIZE + 3 * Page::SECTOR_SIZE) as u64;
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(&[0xEE; Page::SECTOR_SIZE]).unwrap();
+
+        let bpm = BufferPoolManager::new(4, DiskManager::open(&path).unwrap());
+        match bpm.fetch_page_read(pid) {
+            Err(Error::TornPage { page_id, sector }) => {
+                assert_eq!(page_id, pid);
+                assert_eq!(sector, 3);
+            }
+            other => panic!("expected TornPage, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(bpm.get_pin_count(pid), None);
+    }
+
+    #[test]
+    fn test_flush_all_through_double_write() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut dm = DiskManager::create(&path).unwrap();
+        dm.enable_double_write(8).unwrap();
+        let bpm = BufferPoolManager::new(4, dm);
+
+        for i in 0..3u8 {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[0] = i + 1;
+        }
+        bpm.flush_all_pages().unwrap();
+
+        assert_eq!(bpm.snapshot().dirty_count(), 0);
+        assert_eq!(bpm.stats().snapshot().pages_written, 3);
+        drop(bpm);
+
+        let mut dm = DiskManager::open(&path).unwrap();
+        for i in 0..3u8 {
+            assert_eq!(
+                dm.read_page(PageId::new(i as u32)).unwrap().as_slice()[0],
+                i + 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_flush_batch_skips_reused_frames() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut dm = DiskManager::create(&path).unwrap();
+        dm.enable_double_write(8).unwrap();
+        let bpm = BufferPoolManager::new(1, dm);
+        let on_disk = |pid| bpm.disk_manager.lock().read_page(pid).unwrap().as_slice()[100];
+
+        let (a, frame_id) = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[100] = 0xAA;
+            (guard.page_id(), guard.frame_id())
+        };
+        bpm.flush_page(a).unwrap();
+        let stale = [(a, frame_id)];
+
+        // Evicting `a` hands its frame to `b` before the snapshot is flushed
+        let b = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[100] = 0xBB;
+            assert_eq!(guard.frame_id(), frame_id);
+            guard.page_id()
+        };
+
+        let (flushed, failed) = bpm.flush_batch(&stale);
+        assert_eq!((flushed, failed.len()), (0, 0));
+        assert_eq!(on_disk(a), 0xAA);
+        assert_eq!(bpm.snapshot().dirty_count(), 1);
+
+        // A current snapshot writes `b`, and its flush pin is released
+        let (flushed, failed) = bpm.flush_batch(&[(b, frame_id)]);
+        assert_eq!((flushed, failed.len()), (1, 0));
+        assert_eq!(on_disk(b), 0xBB);
+        assert_eq!(bpm.get_pin_count(b), Some(0));
+        assert!(bpm.fetch_page_read(a).is_ok());
+    }
+
+    #[test]
+    fn test_torn_eviction_recovered_from_double_write() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut dm = DiskManager::create(&path).unwrap();
+        dm.enable_double_write(8).unwrap();
+        let bpm = BufferPoolManager::new(1, dm);
+
+        let pid = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[100] = 0xAB;
+            guard.as_mut_slice()[4000] = 0xCD;
+            guard.page_id()
+        };
+        // Evicting the dirty page writes it back through the region
+        let _other = bpm.new_page().unwrap();
+        assert!(!bpm.contains_page(pid));
+        let offset = bpm.disk_manager.lock().page_offset(pid);
+        drop(_other);
+        drop(bpm);
+
+        // The crash tore the write-back: its second half is garbage
+        let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(offset + (PAGE_SIZE / 2) as u64))
+            .unwrap();
+        file.write_all(&[0xEE; PAGE_SIZE / 2]).unwrap();
+        drop(file);
+
+        let bpm = BufferPoolManager::new(1, DiskManager::open(&path).unwrap());
+        let guard = bpm.fetch_page_read(pid).unwrap();
+        assert_eq!(guard.as_slice()[100], 0xAB);
+        assert_eq!(guard.as_slice()[4000], 0xCD);
+    }
+
+    #[test]
+    fn test_dirty_ratio_tracks_flushes() {
+        let (bpm, _dir) = create_test_bpm(4);
+        assert_eq!(bpm.dirty_ratio(), 0.0);
+        assert!(!bpm.flush_in_progress());
+
+        let pids: Vec<PageId> = (0..2).map(|_| bpm.new_page().unwrap().page_id()).collect();
+        bpm.flush_all_pages().unwrap();
+        assert_eq!(bpm.dirty_ratio(), 0.0);
+
+        bpm.fetch_page_write(pids[0]).unwrap().as_mut_slice()[100] = 1;
+        assert_eq!(bpm.dirty_ratio(), 0.25);
+        bpm.fetch_page_write(pids[1]).unwrap().as_mut_slice()[100] = 2;
+        assert_eq!(bpm.dirty_ratio(), 0.5);
+
+        bpm.flush_all_pages().unwrap();
+        assert_eq!(bpm.dirty_ratio(), 0.0);
+        assert!(!bpm.flush_in_progress());
+    }
+
+    #[test]
+    fn test_flush_all_reports_partial_failure() {
+        let (bpm, _dir) = create_test_bpm(4);
+
+        let good: Vec<PageId> = (0..2u8)
+            .map(|i| {
+                let mut guard = bpm.new_page().unwrap();
+                guard.as_mut_slice()[0] = i + 1;
+                guard.page_id()
+            })
+            .collect();
+
+        // Inject a write failure: a dirty frame for a page that was never
+        // allocated on disk
+        let bad = PageId::new(1000);
+        {
+            let mut guard = bpm.fetch_page_write_new(bad).unwrap();
+            guard.as_mut_slice()[0] = 0xFF;
+        }
+
+        match bpm.flush_all_pages() {
+            Err(Error::PartialFlush { flushed, failed }) => {
+                assert_eq!(flushed, 2);
+                assert_eq!(failed.len(), 1);
+                assert_eq!(failed[0].0, bad);
+                assert!(matches!(failed[0].1, Error::PageNotFound(1000)));
+            }
+            other => panic!("expected PartialFlush, got {:?}", other),
+        }
+
+        // The good pages were written and are clean; the bad one stays dirty
+        assert_eq!(bpm.snapshot().dirty_count(), 1);
+        let mut dm = bpm.disk_manager.lock();
+        for (i, &pid) in good.iter().enumerate() {
+            assert_eq!(dm.read_page(pid).unwrap().as_slice()[0], i as u8 + 1);
+        }
+    }
+
+    #[test]
+    fn test_reference_records_access_without_pinning() {
+        let (bpm, _dir) = create_test_bpm(2);
+        let a = bpm.new_page().unwrap().page_id();
+        let b = bpm.new_page().unwrap().page_id();
+        let before = bpm.stats().snapshot();
+        let replacer_before = bpm.replacer_debug();
+
+        bpm.reference(a);
+        bpm.reference(PageId::new(50)); // not resident: no-op
+
+        assert_eq!(bpm.get_pin_count(a), Some(0));
+        assert_eq!(bpm.stats().snapshot(), before);
+        assert!(!bpm.contains_page(PageId::new(50)));
+        // FIFO ignores re-accesses, so the order is unchanged and `a` is
+        // still the next victim
+        assert_eq!(bpm.replacer_debug(), replacer_before);
+        let _c = bpm.new_page().unwrap();
+        assert!(!bpm.contains_page(a));
+        assert!(bpm.contains_page(b));
+    }
+
+    #[test]
+    fn test_write_guard_flush_keeps_latch_and_pin() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let bpm = BufferPoolManager::new(4, DiskManager::create(&path).unwrap());
+
+        let mut guard = bpm.new_page().unwrap();
+        let pid = guard.page_id();
+        guard.as_mut_slice()[100..104].copy_from_slice(b"ckpt");
+        guard.flush().unwrap();
+
+        // Still held and pinned
+        assert!(!guard.is_dropped());
+        assert_eq!(bpm.get_pin_count(pid), Some(1));
+
+        // The file holds the flushed bytes with a valid checksum
+        let on_disk = read_page_from_file(&path, pid);
+        assert_eq!(&on_disk.as_slice()[100..104], b"ckpt");
+        assert!(on_disk.verify_checksum());
+        assert_eq!(bpm.stats().snapshot().pages_written, 1);
+
+        // Changes after the flush aren't on disk until the next flush
+        guard.as_mut_slice()[100] = b'X';
+        drop(guard);
+        assert_eq!(read_page_from_file(&path, pid).as_slice()[100], b'c');
+        bpm.flush_page(pid).unwrap();
+        assert_eq!(read_page_from_file(&path, pid).as_slice()[100], b'X');
+    }
+
+    // ========================================================================
+    // Write coalescing
+    // ========================================================================
+
+    #[test]
+    fn test_write_coalesce_window_skips_hot_dirty_page() {
+        let (bpm, _dir) = create_test_bpm(2);
+        bpm.set_write_coalesce_window(Some(Duration::from_secs(3600)));
+
+        // Oldest in FIFO order, dirty, and just written
+        let hot = bpm.new_page().unwrap().page_id();
+        // Newer but clean
+        let cold = bpm.new_page().unwrap().page_id();
+        bpm.flush_page(cold).unwrap();
+
+        let _third = bpm.new_page().unwrap();
+        assert!(bpm.contains_page(hot));
+        assert!(!bpm.contains_page(cold));
+        assert_eq!(bpm.stats().snapshot().pages_written, 1); // only the cold flush
+    }
+
+    #[test]
+    fn test_write_coalesce_window_falls_back_when_all_hot() {
+        let (bpm, _dir) = create_test_bpm(2);
+        bpm.set_write_coalesce_window(Some(Duration::from_secs(3600)));
+
+        let first = bpm.new_page().unwrap().page_id();
+        let second = bpm.new_page().unwrap().page_id();
+
+        // Every candidate is hot, so the replacer's own choice is evicted
+        let _third = bpm.new_page().unwrap();
+        assert!(!bpm.contains_page(first));
+        assert!(bpm.contains_page(second));
+    }
+
+    #[test]
+    fn test_write_coalesce_window_disabled() {
+        let (bpm, _dir) = create_test_bpm(2);
+        assert_eq!(bpm.write_coalesce_window(), None);
+
+        let hot = bpm.new_page().unwrap().page_id();
+        let cold = bpm.new_page().unwrap().page_id();
+        bpm.flush_page(cold).unwrap();
+
+        let _third = bpm.new_page().unwrap();
+        assert!(!bpm.contains_page(hot));
+        assert!(bpm.contains_page(cold));
+    }
+
+    #[test]
+    fn test_prefer_clean_victims() {
+        let (bpm, _dir) = create_test_bpm(3);
+        assert!(!bpm.prefer_clean_victims());
+        bpm.set_prefer_clean_victims(true);
+
+        // Oldest and next-oldest are dirty; the newest is clean
+        let dirty_a = bpm.new_page().unwrap().page_id();
+        let dirty_b = bpm.new_page().unwrap().page_id();
+        let clean = bpm.new_page().unwrap().page_id();
+        bpm.flush_page(clean).unwrap();
+        let written = bpm.stats().snapshot().pages_written;
+
+        let _fourth = bpm.new_page().unwrap();
+        assert!(!bpm.contains_page(clean));
+        assert!(bpm.contains_page(dirty_a));
+        assert!(bpm.contains_page(dirty_b));
+        assert_eq!(bpm.stats().snapshot().pages_written, written);
+
+        // With only dirty candidates left, FIFO order decides
+        let _fifth = bpm.new_page().unwrap();
+        assert!(!bpm.contains_page(dirty_a));
+        assert!(bpm.contains_page(dirty_b));
+    }
+
+    #[test]
+    fn test_min_free_reserve() {
+        let (bpm, _dir) = create_test_bpm(4);
+        assert!(matches!(
+            bpm.set_min_free_reserve(4),
+            Err(Error::InvalidConfig(_))
+        ));
+
+        // Fill the pool with pinned pages: nothing can be evicted yet
+        let mut guards: Vec<_> = (0..4).map(|_| bpm.new_page().unwrap()).collect();
+        let pids: Vec<PageId> = guards.iter().map(|g| g.page_id()).collect();
+        bpm.set_min_free_reserve(2).unwrap();
+        assert_eq!(bpm.min_free_reserve(), 2);
+        assert_eq!(bpm.free_frame_count(), 0);
+
+        // Each unpin tops the reserve up as far as it can
+        drop(guards.remove(0));
+        assert_eq!(bpm.free_frame_count(), 1);
+        assert!(!bpm.contains_page(pids[0]));
+        drop(guards.remove(0));
+        assert_eq!(bpm.free_frame_count(), 2);
+        drop(guards);
+        assert_eq!(bpm.free_frame_count(), 2);
+        assert_eq!(bpm.page_count(), 2);
+
+        // A miss dips into the reserve until the page is unpinned
+        let guard = bpm.fetch_page_read(pids[0]).unwrap();
+        assert_eq!(bpm.free_frame_count(), 1);
+        drop(guard);
+        assert_eq!(bpm.free_frame_count(), 2);
+        assert!(bpm.contains_page(pids[0]));
+
+        bpm.set_min_free_reserve(0).unwrap();
+        bpm.fetch_page_read(pids[1]).unwrap();
+        assert_eq!(bpm.free_frame_count(), 1);
+    }
+
+    #[test]
+    fn test_release_clean_pages() {
+        let (bpm, _dir) = create_test_bpm(8);
+        let pids: Vec<PageId> = (0..6)
+            .map(|i| {
+                let mut guard = bpm.new_page().unwrap();
+                guard.as_mut_slice()[100] = i;
+                guard.page_id()
+            })
+            .collect();
+        bpm.flush_all_pages().unwrap();
+
+        // pids[4] is dirty again and pids[5] is pinned
+        bpm.fetch_page_write(pids[4]).unwrap().as_mut_slice()[100] = 44;
+        let pinned = bpm.fetch_page_read(pids[5]).unwrap();
+
+        assert_eq!(bpm.release_clean_pages(), 4);
+        assert_eq!(bpm.free_frame_count(), 6);
+        assert!(pids[..4].iter().all(|&pid| !bpm.contains_page(pid)));
+        assert!(bpm.contains_page(pids[4]));
+        assert!(bpm.contains_page(pids[5]));
+        assert_eq!(bpm.stats().snapshot().pages_written, 6);
+        drop(pinned);
+        assert_eq!(bpm.release_clean_pages(), 1);
+
+        // Released pages read back from disk, and freed frames are reusable
+        for (i, &pid) in pids.iter().enumerate() {
+            let expected = if i == 4 { 44 } else { i as u8 };
+            assert_eq!(bpm.fetch_page_read(pid).unwrap().as_slice()[100], expected);
+        }
+        assert!(bpm.new_page().unwrap().is_zeroed());
+    }
+
+    #[test]
+    fn test_victim_cache_saves_reads() {
+        fn cycle_reads(bpm: &BufferPoolManager, pids: &[PageId]) -> crate::buffer::StatsSnapshot {
+            bpm.stats().reset();
+            for _ in 0..5 {
+                for &pid in pids {
+                    bpm.fetch_page_read(pid).unwrap();
+                }
+            }
+            bpm.stats().snapshot()
+        }
+
+        // Cycling over 6 pages thrashes a 4-frame FIFO pool
+        let dir = tempdir().unwrap();
+        let mut dm = DiskManager::create(dir.path().join("test.db")).unwrap();
+        dm.preallocate(6).unwrap();
+        let pids: Vec<PageId> = (0..6).map(PageId::new).collect();
+
+        let bpm = BufferPoolManager::new(4, dm);
+        let plain = cycle_reads(&bpm, &pids);
+        assert_eq!(plain.pages_read, 30);
+        assert_eq!(plain.victim_hits, 0);
+        drop(bpm);
+
+        let dm = DiskManager::open(dir.path().join("test.db")).unwrap();
+        let bpm = BufferPoolManager::new(4, dm).with_victim_cache(2);
+        let tiered = cycle_reads(&bpm, &pids);
+        assert_eq!(tiered.cache_misses, 30);
+        assert_eq!(tiered.pages_read, 6);
+        assert_eq!(tiered.victim_hits, 24);
+    }
+
+    #[test]
+    fn test_victim_cache_copy_is_current() {
+        let (bpm, _dir) = create_test_bpm(1);
+        let bpm = bpm.with_victim_cache(4);
+
+        let p0 = bpm.new_page().unwrap().page_id();
+        {
+            let mut guard = bpm.fetch_page_write(p0).unwrap();
+            guard.as_mut_slice()[100] = 7;
+        }
+        // Evicting p0 flushes it and keeps a copy of the latest contents
+        let p1 = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[100] = 9;
+            guard.page_id()
+        };
+        assert_eq!(bpm.fetch_page_read(p0).unwrap().as_slice()[100], 7);
+        assert_eq!(bpm.fetch_page_read(p1).unwrap().as_slice()[100], 9);
+        let stats = bpm.stats().snapshot();
+        assert_eq!(stats.victim_hits, 2);
+        assert_eq!(stats.pages_read, 0);
+        assert_eq!(stats.pages_written, 2);
+    }
+
+    #[test]
+    fn test_with_disk_manager() {
+        let (bpm, _dir) = create_test_bpm(4);
+        assert_eq!(bpm.with_disk_manager(|dm| dm.page_count()), 0);
+
+        let pids: Vec<PageId> = (0..3).map(|_| bpm.new_page().unwrap().page_id()).collect();
+        bpm.flush_all_pages().unwrap();
+
+        // Every page allocated through the pool is on disk
+        assert_eq!(bpm.page_count(), 3);
+        assert_eq!(bpm.with_disk_manager(|dm| dm.page_count()), 3);
+        let on_disk = bpm.with_disk_manager(|dm| dm.read_page(pids[2]).unwrap());
+        assert!(on_disk.as_slice().iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_append_page_bypasses_pool() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let bpm = BufferPoolManager::new(4, DiskManager::create(&path).unwrap());
+        let before = bpm.stats().snapshot();
+
+        let pids: Vec<PageId> = bpm.with_disk_manager(|dm| {
+            (0..1000u32)
+                .map(|i| {
+                    let mut page = Page::new();
+                    page.as_mut_slice()[100..104].copy_from_slice(&i.to_le_bytes());
+                    dm.append_page(&page).unwrap()
+                })
+                .collect()
+        });
+        assert_eq!(pids.first(), Some(&PageId::new(0)));
+        assert_eq!(pids.last(), Some(&PageId::new(999)));
+        assert_eq!(bpm.stats().snapshot(), before);
+        assert_eq!(bpm.page_count(), 0);
+        drop(bpm);
+
+        let mut dm = DiskManager::open(&path).unwrap();
+        assert_eq!(dm.page_count(), 1000);
+        for (i, &pid) in pids.iter().enumerate() {
+            let page = dm.read_page(pid).unwrap();
+            assert_eq!(&page.as_slice()[100..104], &(i as u32).to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_resize_down() {
+        let (bpm, _dir) = create_test_bpm(4);
+        let pids: Vec<PageId> = (0..3).map(|_| bpm.new_page().unwrap().page_id()).collect();
+        bpm.flush_all_pages().unwrap();
+        bpm.stats().reset();
+
+        let pinned = bpm.fetch_page_read(pids[0]).unwrap();
+
+        // One free frame is retired, then the oldest unpinned page evicted
+        assert_eq!(bpm.resize_down(2).unwrap(), 2);
+        assert_eq!(bpm.pool_size(), 2);
+        assert_eq!(bpm.free_frame_count(), 0);
+        assert_eq!(bpm.stats().snapshot().evictions, 1);
+        assert_eq!(bpm.stats().snapshot().pages_written, 0);
+        assert!(bpm.contains_page(pids[0]));
+        assert!(!bpm.contains_page(pids[1]));
+        assert!(bpm.contains_page(pids[2]));
+
+        // Pinned frames are not dropped
+        let pinned2 = bpm.fetch_page_read(pids[2]).unwrap();
+        assert_eq!(bpm.resize_down(1).unwrap(), 2);
+        assert_eq!(bpm.page_count(), 2);
+        assert!(matches!(bpm.resize_down(0), Err(Error::InvalidConfig(_))));
+
+        // The shrunk pool keeps working within its remaining frames
+        drop(pinned2);
+        assert_eq!(bpm.resize_down(1).unwrap(), 1);
+        drop(pinned);
+        assert_eq!(bpm.fetch_page_read(pids[1]).unwrap().page_id(), pids[1]);
+        assert_eq!(bpm.page_count(), 1);
+    }
+
+    #[test]
+    fn test_lossy_read_skips_write_latch() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let bpm = BufferPoolManager::new(4, DiskManager::create(&path).unwrap()).with_lossy_reads();
+
+        let pid = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[100..102].copy_from_slice(b"v1");
+            guard.page_id()
+        };
+        bpm.flush_page(pid).unwrap();
+
+        let fresh = bpm.fetch_page_read_lossy(pid).unwrap();
+        assert!(!fresh.is_stale());
+        drop(fresh);
+
+        let mut writer = bpm.fetch_page_write(pid).unwrap();
+        writer.as_mut_slice()[100..102].copy_from_slice(b"v2");
+
+        // Called on the writer's own thread, so blocking would deadlock
+        let stale = bpm.fetch_page_read_lossy(pid).unwrap();
+        assert!(stale.is_stale());
+        assert_eq!(&stale.as_slice()[100..102], b"v1");
+        assert_eq!(bpm.get_pin_count(pid), Some(1));
+
+        drop(writer);
+        let fresh = bpm.fetch_page_read_lossy(pid).unwrap();
+        assert!(!fresh.is_stale());
+        assert_eq!(&fresh.as_slice()[100..102], b"v2");
+    }
+
+    #[test]
+    fn test_clear_page() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let bpm = BufferPoolManager::new(4, DiskManager::create(&path).unwrap());
+
+        let pid = {
+            let mut guard = bpm.new_page_typed(PageType::Data).unwrap();
+            guard.as_mut_slice()[100..104].copy_from_slice(b"data");
+            guard.update_checksum();
+            guard.page_id()
+        };
+        bpm.flush_page(pid).unwrap();
+        let reads = bpm.stats().snapshot().pages_read;
+
+        {
+            let _pinned = bpm.fetch_page_read(pid).unwrap();
+            assert!(matches!(bpm.clear_page(pid), Err(Error::PageNotPinned(_))));
+        }
+
+        bpm.clear_page(pid).unwrap();
+        let page = bpm.fetch_page_read(pid).unwrap();
+        assert!(page.verify_checksum());
+        assert!(page.as_slice()[PageHeader::SIZE..].iter().all(|&b| b == 0));
+        drop(page);
+        assert_eq!(bpm.stats().snapshot().pages_read, reads);
+
+        // The cleared page is dirty and reaches disk on flush
+        bpm.flush_page(pid).unwrap();
+        let on_disk = read_page_from_file(&path, pid);
+        assert!(on_disk.verify_checksum());
+        assert!(on_disk.as_slice()[PageHeader::SIZE..]
+            .iter()
+            .all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_export_metrics_csv() {
+        let (bpm, _dir) = create_test_bpm(4);
+        let mut out = Vec::new();
+        let pid = bpm.new_page().unwrap().page_id();
+        bpm.export_metrics_csv(&mut out, true).unwrap();
+        bpm.flush_all_pages().unwrap();
+        drop(bpm.fetch_page_read(pid).unwrap());
+        bpm.export_metrics_csv(&mut out, false).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "timestamp_us,resident_pages,dirty_ratio,cache_hits,cache_misses,evictions,\
+             pages_read,pages_written,compulsory_misses,capacity_misses,conflict_misses,\
+             victim_hits"
+        );
+
+        let columns = lines[0].split(',').count();
+        let rows: Vec<Vec<&str>> = lines[1..].iter().map(|l| l.split(',').collect()).collect();
+        assert!(rows.iter().all(|r| r.len() == columns));
+        assert!(rows[1][0].parse::<u64>().unwrap() >= rows[0][0].parse::<u64>().unwrap());
+        assert_eq!(rows[0][1..4], ["1", "0.2500", "0"]);
+        assert_eq!(rows[1][1..4], ["1", "0.0000", "1"]);
+    }
+
+    #[test]
+    fn test_last_access_advances_and_clears_on_eviction() {
+        let (bpm, _dir) = create_test_bpm(1);
+        let pid = bpm.new_page().unwrap().page_id();
+        let created = bpm.last_access(pid).unwrap();
+
+        std::thread::sleep(Duration::from_millis(2));
+        drop(bpm.fetch_page_read(pid).unwrap());
+        let hit = bpm.last_access(pid).unwrap();
+        assert!(hit > created);
+
+        // Evict it; the frame's timestamp goes with the page
+        let frame_id = bpm.page_table.read()[&pid];
+        let other = bpm.new_page().unwrap().page_id();
+        assert!(!bpm.contains_page(pid));
+        assert_eq!(bpm.last_access(pid), None);
+        assert_eq!(bpm.page_table.read()[&other], frame_id);
+        assert!(bpm.frames[frame_id.0].last_access().unwrap() >= hit);
+
+        // Reloading starts a fresh timestamp
+        std::thread::sleep(Duration::from_millis(2));
+        drop(bpm.fetch_page_read(pid).unwrap());
+        assert!(bpm.last_access(pid).unwrap() > hit);
+    }
+
+    #[test]
+    fn test_delete_page_flushing_keeps_last_write() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let (kept, lost) = {
+            let bpm = BufferPoolManager::new(4, DiskManager::create(&path).unwrap());
+            let kept = bpm.new_page().unwrap().page_id();
+            let lost = bpm.new_page().unwrap().page_id();
+            bpm.flush_all_pages().unwrap();
+
+            bpm.fetch_page_write(kept).unwrap().as_mut_slice()[100] = 1;
+            bpm.fetch_page_write(lost).unwrap().as_mut_slice()[100] = 2;
+            bpm.delete_page_flushing(kept).unwrap();
+            bpm.delete_page(lost).unwrap();
+            assert!(!bpm.contains_page(kept));
+            assert!(!bpm.contains_page(lost));
+            (kept, lost)
+        };
+
+        let bpm = BufferPoolManager::new(4, DiskManager::open(&path).unwrap());
+        assert_eq!(bpm.fetch_page_read(kept).unwrap().as_slice()[100], 1);
+        assert_eq!(bpm.fetch_page_read(lost).unwrap().as_slice()[100], 0);
+    }
+
+    #[test]
+    fn test_read_page_copy() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let bpm = BufferPoolManager::new(4, DiskManager::create(&path).unwrap());
+
+        let pid = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[100..104].copy_from_slice(b"copy");
+            guard.update_checksum();
+            guard.page_id()
+        };
+        bpm.flush_page(pid).unwrap();
+
+        let copy = bpm.read_page_copy(pid).unwrap();
+        assert_eq!(bpm.get_pin_count(pid), Some(0));
+        assert_eq!(copy.as_slice(), read_page_from_file(&path, pid).as_slice());
+        assert_eq!(&copy.as_slice()[100..104], b"copy");
+
+        assert!(matches!(
+            bpm.read_page_copy(PageId::new(99)),
+            Err(Error::PageNotFound(99))
+        ));
+    }
+
+    #[test]
+    fn test_new_page_typed() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let pid = {
+            let bpm = BufferPoolManager::new(4, DiskManager::create(&path).unwrap());
+            let guard = bpm.new_page_typed(PageType::BTreeLeaf).unwrap();
+            assert_eq!(guard.header().page_type, PageType::BTreeLeaf);
+            assert_eq!(guard.header().lsn, Lsn::ZERO);
+            assert!(guard.verify_checksum());
+            let pid = guard.page_id();
+            drop(guard);
+            bpm.flush_all_pages().unwrap();
+            pid
+        };
+
+        let bpm = BufferPoolManager::new(4, DiskManager::open(&path).unwrap());
+        let guard = bpm.fetch_page_read(pid).unwrap();
+        assert_eq!(guard.header().page_type, PageType::BTreeLeaf);
+        assert!(guard.verify_checksum());
+    }
+
+    // ========================================================================
+    // Tracing
+    // ========================================================================
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_cache_miss_emits_trace_event() {
+        use std::sync::Arc;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Records each event's name and fields.
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(&'static str, Fields)>>);
+
+        #[derive(Default)]
+        struct Fields(Vec<(String, String)>);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .push((field.name().to_string(), format!("{:?}", value)));
+            }
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                self.0.lock().push((event.metadata().name(), fields));
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let (bpm, _dir) = create_test_bpm(4);
+        let pid = bpm.new_page().unwrap().page_id();
+        bpm.flush_page(pid).unwrap();
+        // Free the frame so the next fetch misses
+        bpm.delete_page(pid).unwrap();
+
+        let recorder = Arc::new(Recorder::default());
+        tracing::subscriber::with_default(recorder.clone(), || {
+            bpm.fetch_page_read(pid).unwrap();
+        });
+
+        let events = recorder.0.lock();
+        let (_, fields) = events
+            .iter()
+            .find(|(name, _)| *name == "cache_miss")
+            .expect("cache_miss event");
+        assert!(fields
+            .0
+            .contains(&("page_id".to_string(), pid.0.to_string())));
+        assert!(fields.0.iter().any(|(name, _)| name == "frame_id"));
+        assert!(events.iter().any(|(name, _)| *name == "disk_read"));
+    }
+
+    // ========================================================================
+    // Checked unpin
+    // ========================================================================
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "unpin of unpinned")]
+    fn test_double_unpin_asserts_in_debug() {
+        let (bpm, _dir) = create_test_bpm(2);
+        let guard = bpm.new_page().unwrap();
+        let frame_id = guard.frame_id();
+        drop(guard);
+
+        let _ = bpm.unpin_page_internal(frame_id, false);
     }
 
-    fn handle_cache_hit(&self, frame_id: FrameId, page_id: PageId) {
-        let frame = &self.frames[frame_id.0];
-        frame.pin();
+    #[test]
+    fn test_double_unpin_is_error() {
+        let (bpm, _dir) = create_test_bpm(2);
+        let guard = bpm.new_page().unwrap();
+        let (pid, frame_id) = (guard.page_id(), guard.frame_id());
+        drop(guard);
 
-        {
-            let mut replacer = self.replacer.lock();
-            replacer.record_access(frame_id, page_id);
-            replacer.set_evictable(frame_id, false);
-        }
+        // What release builds return, minus the debug assertion
+        assert!(matches!(
+            bpm.unpin_frame(frame_id, false),
+            Err(Error::PageNotPinned(p)) if p == pid.0
+        ));
+        assert_eq!(bpm.get_pin_count(pid), Some(0));
 
-        self.stats.cache_hits.fetch_add(1, Ordering::Relaxed);
+        // The pool keeps working
+        assert_eq!(bpm.fetch_page_read(pid).unwrap().page_id(), pid);
     }
 
-    fn handle_cache_miss(&self, page_id: PageId) -> Result<FrameId> {
-        self.stats.cache_misses.fetch_add(1, Ordering::Relaxed);
+    // ========================================================================
+    // Deferred replacer updates
+    // ========================================================================
 
-        let frame_id = self.get_free_frame()?;
+    #[test]
+    fn test_pinned_frame_listed_evictable_is_skipped() {
+        let (bpm, _dir) = create_test_bpm(1);
 
-        let page_data = {
-            let mut dm = self.disk_manager.lock();
-            dm.read_page(page_id)?
-        };
+        let guard = bpm.new_page().unwrap();
+        let pid = guard.page_id();
 
-        self.stats.pages_read.fetch_add(1, Ordering::Relaxed);
+        // What a deferred hit leaves behind: pinned, but listed as evictable
+        bpm.replacer.lock().set_evictable(guard.frame_id(), true);
 
-        let frame = &self.frames[frame_id.0];
+        assert!(matches!(bpm.new_page(), Err(Error::NoFreeFrames)));
+        assert!(bpm.contains_page(pid));
+        assert_eq!(bpm.get_pin_count(pid), Some(1));
 
-        {
-            let mut page = frame.page_mut();
-            page.as_mut_slice().copy_from_slice(page_data.as_slice());
-        }
+        // The frame is still tracked and can be evicted once unpinned
+        drop(guard);
+        assert!(bpm.new_page().is_ok());
+        assert!(!bpm.contains_page(pid));
+    }
 
-        frame.set_page_id(Some(page_id));
-        frame.pin();
+    #[test]
+    fn test_eviction_correct_with_deferred_hits() {
+        let (bpm, _dir) = create_test_bpm(3);
+        let a = bpm.new_page().unwrap().page_id();
+        let b = bpm.new_page().unwrap().page_id();
+        let c = bpm.new_page().unwrap().page_id();
 
-        {
-            let mut pt = self.page_table.write();
-            pt.insert(page_id, frame_id);
-        }
+        // A hit pins `a` without telling the replacer
+        let held = bpm.fetch_page_read(a).unwrap();
+        assert_eq!(bpm.replacer_debug(), "FIFO [2*, 1*, 0*] evictable=3");
 
-        {
-            let mut replacer = self.replacer.lock();
-            replacer.record_access(frame_id, page_id);
-            replacer.set_evictable(frame_id, false);
-        }
+        // `a` is first in FIFO order but pinned, so `b` goes
+        let d = bpm.new_page().unwrap().page_id();
+        assert!(bpm.contains_page(a));
+        assert!(!bpm.contains_page(b));
 
-        Ok(frame_id)
+        // Once released, `a` keeps its FIFO position and is next
+        drop(held);
+        let _e = bpm.new_page().unwrap();
+        assert!(!bpm.contains_page(a));
+        assert!(bpm.contains_page(c));
+        assert!(bpm.contains_page(d));
+        assert_eq!(bpm.get_pin_count(c), Some(0));
     }
 
-    // ========================================================================
-    // Internal: Frame allocation and eviction
-    // ========================================================================
+    #[test]
+    fn test_deferred_accesses_tracked_per_frame() {
+        let (bpm, _dir) = create_test_bpm(3);
+        let a = bpm.new_page().unwrap().page_id();
+        let b = bpm.new_page().unwrap().page_id();
+        let _c = bpm.new_page().unwrap().page_id();
 
-    fn get_free_frame(&self) -> Result<FrameId> {
-        {
-            let mut fl = self.free_list.lock();
-            if let Some(frame_id) = fl.pop() {
-                return Ok(frame_id);
-            }
+        // Repeated hits queue each frame once
+        for _ in 0..10 {
+            drop(bpm.fetch_page_read(a).unwrap());
+            drop(bpm.fetch_page_read(b).unwrap());
         }
-        self.evict_page()
-    }
+        assert_eq!(bpm.deferred.lock().len(), 2);
 
-    fn evict_page(&self) -> Result<FrameId> {
-        let frame_id = {
-            let mut replacer = self.replacer.lock();
-            replacer.evict().ok_or(Error::NoFreeFrames)?
-        };
+        // Eviction drains the queue, and the frame still pinned by a hit
+        // no longer counts as evictable
+        let held = bpm.fetch_page_read(a).unwrap();
+        let _d = bpm.new_page().unwrap();
+        assert!(bpm.deferred.lock().is_empty());
+        assert!(bpm.contains_page(a));
+        assert_eq!(bpm.replacer.lock().size(), 1);
 
-        self.stats.evictions.fetch_add(1, Ordering::Relaxed);
+        drop(held);
+        assert_eq!(bpm.replacer.lock().size(), 2);
+    }
 
-        let frame = &self.frames[frame_id.0];
-        let old_page_id = frame.page_id();
+    #[test]
+    fn test_cache_hits_skip_replacer_lock() {
+        let (bpm, _dir) = create_test_bpm(4);
+        let pids: Vec<PageId> = (0..4).map(|_| bpm.new_page().unwrap().page_id()).collect();
 
-        if frame.is_dirty() {
-            if let Some(pid) = old_page_id {
-                self.flush_frame(frame_id, pid)?;
+        // Warm-up: the first hit and release of each page is lock-free too,
+        // since the frames are already listed as evictable
+        let before = bpm.replacer_locks.load(Ordering::Relaxed);
+        for _ in 0..100 {
+            for &pid in &pids {
+                let _guard = bpm.fetch_page_read(pid).unwrap();
             }
         }
+        assert_eq!(bpm.replacer_locks.load(Ordering::Relaxed), before);
+        assert_eq!(bpm.stats().snapshot().cache_hits, 400);
 
-        if let Some(pid) = old_page_id {
-            let mut pt = self.page_table.write();
-            pt.remove(&pid);
-        }
+        // A miss still goes through the replacer (evict, load, release)
+        let cold = bpm.allocate_page_id().unwrap();
+        drop(bpm.fetch_page_read(cold).unwrap());
+        assert_eq!(bpm.replacer_locks.load(Ordering::Relaxed), before + 3);
+    }
 
-        frame.clear_dirty();
-        frame.set_page_id(None);
+    // ========================================================================
+    // Read-ahead
+    // ========================================================================
 
-        Ok(frame_id)
+    /// Create `n` pages on disk, none of them resident in the pool.
+    fn create_cold_pages(bpm: &BufferPoolManager, n: usize) -> Vec<PageId> {
+        let pids: Vec<PageId> = (0..n).map(|_| bpm.new_page().unwrap().page_id()).collect();
+        bpm.flush_all_pages().unwrap();
+        for &pid in &pids {
+            bpm.delete_page(pid).unwrap();
+        }
+        bpm.stats().reset();
+        pids
     }
 
-    fn flush_frame(&self, frame_id: FrameId, page_id: PageId) -> Result<()> {
-        let frame = &self.frames[frame_id.0];
+    #[test]
+    fn test_latency_sampling_separates_hits_and_misses() {
+        let (bpm, _dir) = create_test_bpm(4);
+        let pids = create_cold_pages(&bpm, 4);
 
-        if frame.is_dirty() {
-            let page = frame.page();
-            {
-                let mut dm = self.disk_manager.lock();
-                dm.write_page(page_id, &page)?;
-            }
-            drop(page);
+        // Disabled by default
+        assert_eq!(bpm.latency_sampling(), None);
+        bpm.fetch_page_read(pids[0]).unwrap();
+        assert_eq!(bpm.latency_percentiles().miss.samples, 0);
 
-            frame.clear_dirty();
-            self.stats.pages_written.fetch_add(1, Ordering::Relaxed);
+        bpm.set_latency_sampling(Some(1));
+        for &pid in &pids[1..] {
+            bpm.fetch_page_read(pid).unwrap();
+        }
+        for _ in 0..10 {
+            for &pid in &pids {
+                bpm.fetch_page_read(pid).unwrap();
+            }
         }
 
-        Ok(())
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
+        let latency = bpm.latency_percentiles();
+        assert_eq!(latency.miss.samples, 3);
+        assert_eq!(latency.hit.samples, 40);
+        // A disk read outweighs a page table lookup
+        assert!(latency.miss.p50 > latency.hit.p50);
 
-    fn create_test_bpm(pool_size: usize) -> (BufferPoolManager, tempfile::TempDir) {
-        let dir = tempdir().unwrap();
-        let path = dir.path().join("test.db");
-        let dm = DiskManager::create(&path).unwrap();
-        (BufferPoolManager::new(pool_size, dm), dir)
+        // One in N: only every other fetch is timed
+        bpm.set_latency_sampling(Some(2));
+        for _ in 0..10 {
+            bpm.fetch_page_read(pids[0]).unwrap();
+        }
+        assert_eq!(bpm.latency_percentiles().hit.samples, 5);
     }
 
-    // ========================================================================
-    // Core functionality tests
-    // ========================================================================
-
     #[test]
-    fn test_new_page_and_fetch() {
+    fn test_readahead_sequential() {
         let (bpm, _dir) = create_test_bpm(10);
-        let data = b"Hello, world!";
-
-        // Create and write
-        let pid = {
-            let mut guard = bpm.new_page().unwrap();
-            assert_eq!(guard.page_id(), PageId::new(0));
-            guard.as_mut_slice()[..data.len()].copy_from_slice(data);
-            guard.page_id()
-        };
+        let pids = create_cold_pages(&bpm, 8);
+        bpm.set_readahead(3);
 
-        // Read back
-        {
-            let guard = bpm.fetch_page_read(pid).unwrap();
-            assert_eq!(&guard.as_slice()[..data.len()], data);
+        for &pid in &pids {
+            bpm.fetch_page_read(pid).unwrap();
         }
 
-        // Delete
-        bpm.delete_page(pid).unwrap();
-        assert!(!bpm.contains_page(pid));
+        // Only the first two reads miss; the rest were prefetched
+        let snap = bpm.stats().snapshot();
+        assert_eq!(snap.cache_misses, 2);
+        assert_eq!(snap.cache_hits, 6);
     }
 
     #[test]
-    fn test_eviction_persists_data() {
-        let (bpm, _dir) = create_test_bpm(1); // Only 1 frame!
+    fn test_readahead_random_does_not_prefetch() {
+        let (bpm, _dir) = create_test_bpm(10);
+        let pids = create_cold_pages(&bpm, 8);
+        bpm.set_readahead(3);
 
-        // Create page 0, write data
-        let pid0 = {
-            let mut guard = bpm.new_page().unwrap();
-            guard.as_mut_slice()[0] = 0x42;
-            guard.page_id()
-        };
+        for i in [5, 2, 7, 0] {
+            bpm.fetch_page_read(pids[i]).unwrap();
+        }
 
-        // Create page 1 (evicts page 0)
-        let _pid1 = bpm.new_page().unwrap().page_id();
+        assert_eq!(bpm.page_count(), 4);
+        assert_eq!(bpm.stats().snapshot().cache_misses, 4);
+    }
 
-        assert_eq!(bpm.stats().snapshot().evictions, 1);
+    #[test]
+    fn test_readahead_disabled_by_default() {
+        let (bpm, _dir) = create_test_bpm(10);
+        let pids = create_cold_pages(&bpm, 4);
+        assert_eq!(bpm.readahead(), 0);
 
-        // Fetch page 0 - should load from disk with data intact
-        {
-            let guard = bpm.fetch_page_read(pid0).unwrap();
-            assert_eq!(guard.as_slice()[0], 0x42);
-        }
+        bpm.fetch_page_read(pids[0]).unwrap();
+        bpm.fetch_page_read(pids[1]).unwrap();
+
+        assert_eq!(bpm.page_count(), 2);
     }
 
+    // ========================================================================
+    // Snapshot
+    // ========================================================================
+
     #[test]
-    fn test_no_free_frames_when_all_pinned() {
-        let (bpm, _dir) = create_test_bpm(2);
+    fn test_snapshot_consistent_and_stable() {
+        let (bpm, _dir) = create_test_bpm(4);
 
-        let _guard1 = bpm.new_page().unwrap();
-        let _guard2 = bpm.new_page().unwrap();
+        let pids: Vec<PageId> = (0..3).map(|_| bpm.new_page().unwrap().page_id()).collect();
+        let _held = bpm.fetch_page_read(pids[1]).unwrap();
 
-        // All frames pinned
-        assert!(bpm.new_page().is_err());
+        let snap = bpm.snapshot();
+        assert_eq!(snap.frames().len(), 4);
+        assert_eq!(snap.page_count(), bpm.page_count());
+        assert_eq!(snap.pinned_count(), 1);
+        assert_eq!(snap.dirty_count(), 3);
+        for &pid in &pids {
+            let frame = snap.frame_for(pid).expect("resident page has a frame");
+            assert_eq!(frame.page_id, Some(pid));
+        }
+        assert_eq!(snap.frame_for(pids[1]).unwrap().pin_count, 1);
+
+        // Mutating the pool afterwards does not affect the snapshot
+        let before = snap.clone();
+        bpm.flush_all_pages().unwrap();
+        bpm.delete_page(pids[0]).unwrap();
+        let _new = bpm.new_page().unwrap();
+        assert_eq!(snap, before);
+        assert!(snap.frame_for(pids[0]).is_some());
+        assert_ne!(bpm.snapshot(), snap);
     }
 
     // ========================================================================
@@ -556,6 +3786,85 @@ mod tests {
         let _guard2 = bpm.fetch_page_write(pid).unwrap();
     }
 
+    #[test]
+    fn test_read_guard_clone_ref() {
+        let (bpm, _dir) = create_test_bpm(2);
+
+        let pid = {
+            let mut guard = bpm.new_page().unwrap();
+            guard.as_mut_slice()[100] = 0x5A;
+            guard.page_id()
+        };
+
+        let guard = bpm.fetch_page_read(pid).unwrap();
+        let hits = bpm.stats().snapshot().cache_hits;
+        let clone = guard.clone_ref();
+        assert_eq!(bpm.get_pin_count(pid), Some(2));
+        assert_eq!(bpm.stats().snapshot().cache_hits, hits);
+        assert_eq!(clone.page_id(), pid);
+        assert_eq!(guard.as_slice()[100], 0x5A);
+        assert_eq!(clone.as_slice()[100], 0x5A);
+
+        drop(guard);
+        assert_eq!(bpm.get_pin_count(pid), Some(1));
+        assert_eq!(clone.as_slice()[100], 0x5A);
+        drop(clone);
+        assert_eq!(bpm.get_pin_count(pid), Some(0));
+    }
+
+    #[test]
+    fn test_read_guard_btree_node_view() {
+        let (bpm, _dir) = create_test_bpm(2);
+
+        let data_pid = bpm.new_page_typed(PageType::Data).unwrap().page_id();
+        let leaf_pid = {
+            let mut guard = bpm.new_page().unwrap();
+            init_btree_leaf(&mut guard);
+            btree_set_key_count(&mut guard, 3);
+            btree_set_sibling(&mut guard, data_pid);
+            guard.page_id()
+        };
+
+        assert!(bpm
+            .fetch_page_read(data_pid)
+            .unwrap()
+            .btree_node()
+            .is_none());
+
+        let guard = bpm.fetch_page_read(leaf_pid).unwrap();
+        let node = guard.btree_node().unwrap();
+        assert!(node.is_leaf());
+        assert_eq!(node.key_count(), 3);
+        assert_eq!(node.sibling(), data_pid);
+        assert_eq!(node.entries().len(), PAGE_SIZE - BTREE_NODE_HEADER_SIZE);
+    }
+
+    #[test]
+    fn test_guard_header_accessors() {
+        let (bpm, _dir) = create_test_bpm(1);
+
+        let pid = {
+            let mut guard = bpm.new_page().unwrap();
+            assert_eq!(guard.page_type(), PageType::Invalid);
+            let mut header = PageHeader::new(PageType::Data);
+            header.lsn = Lsn::new(5);
+            guard.set_header(&header);
+            assert_eq!(guard.lsn(), Lsn::new(5));
+            guard.set_lsn(Lsn::new(9));
+            assert_eq!(guard.header().page_type, PageType::Data);
+            guard.page_id()
+        };
+
+        // Evict the page so the read comes back from disk
+        bpm.new_page().unwrap();
+        assert!(!bpm.contains_page(pid));
+
+        let guard = bpm.fetch_page_read(pid).unwrap();
+        assert_eq!(guard.page_type(), PageType::Data);
+        assert_eq!(guard.lsn(), Lsn::new(9));
+        assert_eq!(guard.header().lsn, Lsn::new(9));
+    }
+
     #[test]
     fn test_pin_count_with_checked_methods() {
         let (bpm, _dir) = create_test_bpm(2);
@@ -622,6 +3931,260 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_writer_not_starved_by_readers() {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Barrier;
+        use std::thread;
+
+        let (bpm, _dir) = create_test_bpm(4);
+        let pid = bpm.new_page().unwrap().page_id();
+        let stop = AtomicBool::new(false);
+        let started = Barrier::new(5);
+
+        thread::scope(|s| {
+            // Overlapping readers keep the latch read-held continuously
+            for _ in 0..4 {
+                s.spawn(|| {
+                    started.wait();
+                    while !stop.load(Ordering::Relaxed) {
+                        let _guard = bpm.fetch_page_read(pid).unwrap();
+                        thread::sleep(Duration::from_micros(200));
+                    }
+                });
+            }
+
+            started.wait();
+            thread::sleep(Duration::from_millis(20));
+            let start = Instant::now();
+            bpm.fetch_page_write(pid).unwrap().as_mut_slice()[100] = 1;
+            let waited = start.elapsed();
+            stop.store(true, Ordering::Relaxed);
+            assert!(
+                waited < Duration::from_secs(1),
+                "writer waited {:?}",
+                waited
+            );
+        });
+        assert_eq!(bpm.fetch_page_read(pid).unwrap().as_slice()[100], 1);
+    }
+
+    #[test]
+    fn test_hits_stay_correct_under_concurrent_eviction() {
+        use std::thread;
+
+        // 8 pages through 5 frames: hits and evictions interleave constantly,
+        // and 4 threads pinning one page each never exhaust the pool
+        let (bpm, _dir) = create_test_bpm(5);
+        let pids: Vec<PageId> = (0..8u32)
+            .map(|i| {
+                let mut guard = bpm.new_page().unwrap();
+                guard.as_mut_slice()[100..104].copy_from_slice(&i.to_le_bytes());
+                guard.page_id()
+            })
+            .collect();
+        bpm.stats().reset();
+
+        thread::scope(|s| {
+            for t in 0..4usize {
+                let (bpm, pids) = (&bpm, &pids);
+                s.spawn(move || {
+                    for n in 0..500 {
+                        // Mostly revisit a thread-local hot page, sometimes roam
+                        let i = if n % 3 == 0 { (n * 7 + t) % 8 } else { t * 2 };
+                        let guard = bpm.fetch_page_read(pids[i]).unwrap();
+                        let stamp = &guard.as_slice()[100..104];
+                        assert_eq!(stamp, (i as u32).to_le_bytes());
+                        assert_eq!(guard.page_id(), pids[i]);
+                    }
+                });
+            }
+        });
+
+        let stats = bpm.stats().snapshot();
+        assert!(stats.cache_hits > 0);
+        assert!(stats.evictions > 0);
+        assert_eq!(stats.cache_hits + stats.cache_misses, 2000);
+    }
+
+    #[test]
+    fn test_upgradable_guard_coexists_with_readers() {
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::Duration;
+
+        let (bpm, _dir) = create_test_bpm(4);
+        let pid = bpm.new_page().unwrap().page_id();
+
+        let reader = bpm.fetch_page_read(pid).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let (go_tx, go_rx) = mpsc::channel();
+
+        thread::scope(|s| {
+            let bpm = &bpm;
+            s.spawn(move || {
+                // Taken while a reader holds the page
+                let upgradable = bpm.fetch_page_upgradable(pid).unwrap();
+                tx.send("acquired").unwrap();
+                // A pending upgrade blocks new readers, so wait for reader2
+                go_rx.recv().unwrap();
+                let mut guard = upgradable.upgrade();
+                tx.send("upgraded").unwrap();
+                guard.as_mut_slice()[0] = 0x7E;
+            });
+
+            assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), "acquired");
+            // More readers can still join alongside the upgradable guard
+            let reader2 = bpm.fetch_page_read(pid).unwrap();
+            assert_eq!(bpm.get_pin_count(pid), Some(3));
+            go_tx.send(()).unwrap();
+
+            // The upgrade must wait for both readers
+            assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+            drop(reader);
+            assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+            drop(reader2);
+            assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), "upgraded");
+        });
+
+        assert_eq!(bpm.get_pin_count(pid), Some(0));
+        assert_eq!(bpm.fetch_page_read(pid).unwrap().as_slice()[0], 0x7E);
+        assert!(bpm.snapshot().frame_for(pid).unwrap().is_dirty);
+    }
+
+    #[test]
+    fn test_upgradable_guard_downgrade_and_drop() {
+        let (bpm, _dir) = create_test_bpm(4);
+        let pid = bpm.new_page().unwrap().page_id();
+        bpm.flush_all_pages().unwrap();
+
+        // Dropping without upgrading leaves the page clean and unpinned
+        drop(bpm.fetch_page_upgradable(pid).unwrap());
+        assert_eq!(bpm.get_pin_count(pid), Some(0));
+        assert_eq!(bpm.snapshot().dirty_count(), 0);
+
+        let upgradable = bpm.fetch_page_upgradable(pid).unwrap();
+        let read = upgradable.downgrade();
+        assert_eq!(bpm.get_pin_count(pid), Some(1));
+
+        // After downgrading, another upgradable guard can be taken
+        let other = bpm.fetch_page_upgradable(pid).unwrap();
+        assert_eq!(bpm.get_pin_count(pid), Some(2));
+        drop(read);
+        drop(other);
+        assert_eq!(bpm.get_pin_count(pid), Some(0));
+    }
+
+    #[test]
+    fn test_fetch_pages_write_overlapping_sets_no_deadlock() {
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::Duration;
+
+        let (bpm, _dir) = create_test_bpm(8);
+        let pids: Vec<PageId> = (0..4).map(|_| bpm.new_page().unwrap().page_id()).collect();
+
+        let (tx, rx) = mpsc::channel();
+        thread::scope(|s| {
+            let sets = [
+                vec![pids[0], pids[1], pids[2]],
+                vec![pids[2], pids[1], pids[0]],
+                vec![pids[3], pids[1]],
+            ];
+            for set in sets {
+                let bpm = &bpm;
+                let tx = tx.clone();
+                s.spawn(move || {
+                    for _ in 0..200 {
+                        let mut guards = bpm.fetch_pages_write(&set).unwrap();
+                        for (guard, &pid) in guards.iter_mut().zip(&set) {
+                            assert_eq!(guard.page_id(), pid);
+                            guard.as_mut_slice()[0] = guard.as_slice()[0].wrapping_add(1);
+                        }
+                    }
+                    tx.send(()).unwrap();
+                });
+            }
+
+            for _ in 0..3 {
+                rx.recv_timeout(Duration::from_secs(10))
+                    .expect("fetch_pages_write deadlocked");
+            }
+        });
+
+        // 200 increments per set touching the page
+        assert_eq!(bpm.fetch_page_read(pids[0]).unwrap().as_slice()[0], 144); // 400 % 256
+        assert_eq!(bpm.fetch_page_read(pids[1]).unwrap().as_slice()[0], 88); // 600 % 256
+        assert_eq!(bpm.fetch_page_read(pids[3]).unwrap().as_slice()[0], 200);
+        for &pid in &pids {
+            assert_eq!(bpm.get_pin_count(pid), Some(0));
+        }
+    }
+
+    #[test]
+    fn test_read_snapshot_is_consistent() {
+        use std::sync::atomic::AtomicBool;
+        use std::thread;
+
+        let (bpm, _dir) = create_test_bpm(8);
+        let pids: Vec<PageId> = (0..3).map(|_| bpm.new_page().unwrap().page_id()).collect();
+        let done = AtomicBool::new(false);
+
+        thread::scope(|s| {
+            // Bump every page to the same counter, taking the latches in
+            // reverse order of the snapshot request
+            s.spawn(|| {
+                let order: Vec<PageId> = pids.iter().rev().copied().collect();
+                for value in 1..=2000u32 {
+                    for mut guard in bpm.fetch_pages_write(&order).unwrap() {
+                        guard.as_mut_slice()[100..104].copy_from_slice(&value.to_le_bytes());
+                    }
+                }
+                done.store(true, Ordering::Release);
+            });
+
+            let mut snapshots = 0;
+            while !done.load(Ordering::Acquire) || snapshots == 0 {
+                let pages = bpm
+                    .read_snapshot(&[pids[2], pids[0], pids[1], pids[0]])
+                    .unwrap();
+                let values: Vec<&[u8]> = pages.iter().map(|p| &p.as_slice()[100..104]).collect();
+                assert!(values.iter().all(|v| *v == values[0]), "torn snapshot");
+                snapshots += 1;
+            }
+        });
+
+        for &pid in &pids {
+            assert_eq!(bpm.get_pin_count(pid), Some(0));
+        }
+        let last = bpm.read_snapshot(&pids).unwrap();
+        assert!(last
+            .iter()
+            .all(|p| p.as_slice()[100..104] == 2000u32.to_le_bytes()));
+    }
+
+    #[test]
+    fn test_fetch_pages_write_all_or_nothing() {
+        let (bpm, _dir) = create_test_bpm(8);
+        let a = bpm.new_page().unwrap().page_id();
+        let b = bpm.new_page().unwrap().page_id();
+
+        // The missing page fails after `a` and `b` were latched
+        let missing = PageId::new(100);
+        assert!(matches!(
+            bpm.fetch_pages_write(&[b, missing, a]),
+            Err(Error::PageNotFound(100))
+        ));
+        assert_eq!(bpm.get_pin_count(a), Some(0));
+        assert_eq!(bpm.get_pin_count(b), Some(0));
+
+        assert!(matches!(
+            bpm.fetch_pages_write(&[a, b, a]),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert_eq!(bpm.get_pin_count(a), Some(0));
+    }
+
     /// BusTub: EvictableTest - pinned pages cannot be evicted
     #[test]
     fn test_evictable_under_contention() {
@@ -4,10 +4,14 @@
 //! - Which page is loaded (if any)
 //! - Pin count for reference counting
 //! - Dirty flag for write-back tracking
+//! - Generation counter for detecting frame reuse
+//! - Last-write timestamp for write coalescing
+//! - Deferred replacer bookkeeping for the cache-hit fast path
+//! - Whether the page data is known to be all zeros
 
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 
-use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard};
 
 use crate::common::PageId;
 use crate::storage::page::Page;
@@ -23,6 +27,18 @@ use crate::storage::page::Page;
 /// - `page_id`: `Mutex` for safe updates
 /// - `pin_count`: `AtomicU32` for lock-free reference counting
 /// - `is_dirty`: `AtomicBool` for lock-free dirty tracking
+/// - `generation`: `AtomicU64` bumped whenever the frame is reassigned
+/// - `last_write`: `AtomicU64` timestamp of the last write
+/// - `last_access`: `AtomicU64` timestamp of the last pin
+/// - `pending_access`, `replacer_evictable`: `AtomicBool` replacer bookkeeping
+/// - `zeroed`: `AtomicBool` set while the page data is known to be zeros
+///
+/// # Layout
+/// `Page` is aligned to 4096 bytes, so every `Frame` is too and its size is
+/// a multiple of 4096. In the pool's `Vec<Frame>`, one frame's atomics can
+/// therefore never share a cache line with a neighbor's, and threads working
+/// on different pages don't contend on a line (no false sharing). Keep this
+/// in mind before moving the page data out of the frame.
 pub struct Frame {
     /// The page data, protected by RwLock.
     page: RwLock<Page>,
@@ -35,6 +51,25 @@ pub struct Frame {
 
     /// Whether the page has been modified since loading.
     is_dirty: AtomicBool,
+
+    /// Incremented on every page assignment and reset.
+    generation: AtomicU64,
+
+    /// When the loaded page was last written (0 = not since loading).
+    last_write: AtomicU64,
+
+    /// When the loaded page was last pinned (0 = no page loaded).
+    last_access: AtomicU64,
+
+    /// An access not yet reported to the replacer.
+    pending_access: AtomicBool,
+
+    /// Whether the replacer was last told this frame is evictable.
+    replacer_evictable: AtomicBool,
+
+    /// The page data is all zeros. Cleared whenever a lock that can write
+    /// the page is handed out.
+    zeroed: AtomicBool,
 }
 
 impl Frame {
@@ -45,6 +80,12 @@ impl Frame {
             page_id: Mutex::new(None),
             pin_count: AtomicU32::new(0),
             is_dirty: AtomicBool::new(false),
+            generation: AtomicU64::new(0),
+            last_write: AtomicU64::new(0),
+            last_access: AtomicU64::new(0),
+            pending_access: AtomicBool::new(false),
+            replacer_evictable: AtomicBool::new(false),
+            zeroed: AtomicBool::new(true),
         }
     }
 
@@ -58,10 +99,59 @@ impl Frame {
         self.page.read()
     }
 
+    /// Try to acquire a read lock on the page without blocking.
+    ///
+    /// Returns `None` if a writer holds (or is waiting for) the lock.
+    #[inline]
+    pub fn try_page(&self) -> Option<RwLockReadGuard<'_, Page>> {
+        self.page.try_read()
+    }
+
     /// Acquire write lock on the page.
     #[inline]
     pub fn page_mut(&self) -> RwLockWriteGuard<'_, Page> {
-        self.page.write()
+        let page = self.page.write();
+        self.zeroed.store(false, Ordering::Relaxed);
+        page
+    }
+
+    /// Acquire an upgradable read lock on the page.
+    ///
+    /// Coexists with plain readers but excludes writers and other upgradable
+    /// readers, so it can later be upgraded to a write lock without racing.
+    #[inline]
+    pub fn page_upgradable(&self) -> RwLockUpgradableReadGuard<'_, Page> {
+        let page = self.page.upgradable_read();
+        self.zeroed.store(false, Ordering::Relaxed);
+        page
+    }
+
+    /// Make sure the page data is all zeros.
+    ///
+    /// Skips the 4KB fill when the frame is fresh or was reset and nothing
+    /// has taken a write lock since. Returns whether a fill was needed.
+    pub fn zero_page(&self) -> bool {
+        let mut page = self.page.write();
+        if self.zeroed.load(Ordering::Relaxed) {
+            return false;
+        }
+        page.reset();
+        self.zeroed.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Zero the page data, handing its physical memory back to the OS
+    /// where possible.
+    ///
+    /// On Linux with 4KB OS pages this uses `madvise(MADV_DONTNEED)`: the
+    /// data reads back as zeros and memory is only faulted back in when the
+    /// frame is next written. Elsewhere the page is simply zero-filled.
+    pub fn release_page_memory(&self) {
+        let mut page = self.page.write();
+        if !discard_page_memory(&mut page) {
+            page.reset();
+        }
+        self.zeroed.store(true, Ordering::Relaxed);
     }
 
     // ========================================================================
@@ -75,9 +165,115 @@ impl Frame {
     }
 
     /// Set the page ID.
+    ///
+    /// Assigning a page (`Some`) starts a new generation and clears the
+    /// last-write timestamp. Any change clears the last-access timestamp.
     #[inline]
     pub fn set_page_id(&self, page_id: Option<PageId>) {
-        *self.page_id.lock() = page_id;
+        let mut current = self.page_id.lock();
+        self.last_access.store(0, Ordering::Relaxed);
+        if page_id.is_some() {
+            self.generation.fetch_add(1, Ordering::Relaxed);
+            self.last_write.store(0, Ordering::Relaxed);
+        }
+        *current = page_id;
+    }
+
+    // ========================================================================
+    // Generation (Atomic)
+    // ========================================================================
+
+    /// Get the frame's generation.
+    ///
+    /// The generation changes whenever the frame is assigned a page or reset,
+    /// and is stable across pins and unpins. Callers that cache a `FrameId`
+    /// can compare generations to detect that the frame was reused.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    // ========================================================================
+    // Last-write timestamp (Atomic)
+    // ========================================================================
+
+    /// Record a write at `timestamp`.
+    ///
+    /// The unit is up to the caller (the buffer pool uses microseconds since
+    /// it was created); 0 is reserved for "not written".
+    #[inline]
+    pub fn record_write(&self, timestamp: u64) {
+        self.last_write.store(timestamp, Ordering::Relaxed);
+    }
+
+    /// Timestamp of the last write to the loaded page, or None.
+    #[inline]
+    pub fn last_write(&self) -> Option<u64> {
+        match self.last_write.load(Ordering::Relaxed) {
+            0 => None,
+            ts => Some(ts),
+        }
+    }
+
+    // ========================================================================
+    // Last-access timestamp (Atomic)
+    // ========================================================================
+
+    /// Record an access (pin) at `timestamp`.
+    ///
+    /// Same unit as [`record_write`](Self::record_write); 0 is reserved for
+    /// "not accessed". Kept here so time-aware replacement policies can read
+    /// it instead of keeping their own per-frame map.
+    #[inline]
+    pub fn record_access_time(&self, timestamp: u64) {
+        self.last_access.store(timestamp, Ordering::Relaxed);
+    }
+
+    /// Timestamp of the last access to the loaded page, or None.
+    #[inline]
+    pub fn last_access(&self) -> Option<u64> {
+        match self.last_access.load(Ordering::Relaxed) {
+            0 => None,
+            ts => Some(ts),
+        }
+    }
+
+    // ========================================================================
+    // Deferred replacer bookkeeping (Atomic)
+    // ========================================================================
+
+    /// Note an access to report to the replacer later, returning whether
+    /// none was pending before.
+    #[inline]
+    pub fn defer_access(&self) -> bool {
+        !self.pending_access.swap(true, Ordering::Relaxed)
+    }
+
+    /// Whether an access is waiting to be reported to the replacer.
+    #[inline]
+    pub fn has_pending_access(&self) -> bool {
+        self.pending_access.load(Ordering::Relaxed)
+    }
+
+    /// Clear the pending-access flag, returning whether it was set.
+    #[inline]
+    pub fn take_pending_access(&self) -> bool {
+        self.pending_access.swap(false, Ordering::Relaxed)
+    }
+
+    /// Whether the replacer was last told this frame is evictable.
+    ///
+    /// Only meaningful to the buffer pool, which updates it while holding
+    /// the replacer lock.
+    #[inline]
+    pub fn replacer_evictable(&self) -> bool {
+        self.replacer_evictable.load(Ordering::Relaxed)
+    }
+
+    /// Record what the replacer was told about this frame's evictability.
+    #[inline]
+    pub fn set_replacer_evictable(&self, evictable: bool) {
+        self.replacer_evictable.store(evictable, Ordering::Relaxed);
     }
 
     // ========================================================================
@@ -90,6 +286,19 @@ impl Frame {
         self.pin_count.fetch_add(1, Ordering::Relaxed) + 1
     }
 
+    /// Increment the pin count unless it would exceed `max`.
+    ///
+    /// Returns the new pin count, or None if the frame already has `max` pins.
+    #[inline]
+    pub fn try_pin(&self, max: u32) -> Option<u32> {
+        self.pin_count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                (count < max).then_some(count + 1)
+            })
+            .ok()
+            .map(|old| old + 1)
+    }
+
     /// Decrement the pin count. Returns the new pin count.
     ///
     /// # Panics
@@ -101,6 +310,20 @@ impl Frame {
         old - 1
     }
 
+    /// Decrement the pin count unless it is already 0.
+    ///
+    /// Returns the new pin count, or None on underflow (the count is left
+    /// at 0).
+    #[inline]
+    pub fn try_unpin(&self) -> Option<u32> {
+        self.pin_count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                count.checked_sub(1)
+            })
+            .ok()
+            .map(|old| old - 1)
+    }
+
     /// Get the current pin count.
     #[inline]
     pub fn pin_count(&self) -> u32 {
@@ -155,13 +378,41 @@ impl Frame {
     ///
     /// Called after eviction to prepare for reuse.
     pub fn reset(&self) {
-        self.page_mut().reset();
+        self.zero_page();
         self.set_page_id(None);
         self.pin_count.store(0, Ordering::Relaxed);
         self.is_dirty.store(false, Ordering::Relaxed);
+        self.last_write.store(0, Ordering::Relaxed);
+        self.last_access.store(0, Ordering::Relaxed);
+        self.pending_access.store(false, Ordering::Relaxed);
+        self.replacer_evictable.store(false, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Relaxed);
     }
 }
 
+/// Release a page's memory with `madvise`. Returns false if it wasn't
+/// released (the data is then unchanged).
+#[cfg(target_os = "linux")]
+fn discard_page_memory(page: &mut Page) -> bool {
+    // SAFETY: sysconf has no preconditions.
+    let os_page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    // With larger OS pages the advice would cover neighboring frames
+    if os_page_size != Page::size() as libc::c_long {
+        return false;
+    }
+    let data = page.as_mut_slice();
+    // SAFETY: `data` is exactly one OS page (Page is 4KB and 4KB-aligned),
+    // exclusively borrowed, in private anonymous memory. MADV_DONTNEED makes
+    // it read back as zeros, which is a valid Page.
+    let ret = unsafe { libc::madvise(data.as_mut_ptr().cast(), data.len(), libc::MADV_DONTNEED) };
+    ret == 0
+}
+
+#[cfg(not(target_os = "linux"))]
+fn discard_page_memory(_page: &mut Page) -> bool {
+    false
+}
+
 impl Default for Frame {
     fn default() -> Self {
         Self::new()
@@ -172,6 +423,29 @@ impl Default for Frame {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_frames_do_not_share_cache_lines() {
+        // Page alignment implies cache-line alignment
+        assert!(std::mem::align_of::<Frame>() >= 64);
+        assert_eq!(std::mem::size_of::<Frame>() % 64, 0);
+
+        let frames: Vec<Frame> = (0..4).map(|_| Frame::new()).collect();
+        for pair in frames.windows(2) {
+            let a = &pair[0] as *const Frame as usize;
+            let b = &pair[1] as *const Frame as usize;
+            assert_eq!(a % 64, 0);
+            assert!(b - a >= std::mem::size_of::<Frame>());
+        }
+
+        // Behavior is unchanged
+        let frame = &frames[1];
+        frame.pin();
+        frame.mark_dirty();
+        assert_eq!(frames[0].pin_count(), 0);
+        assert!(!frames[2].is_dirty());
+        assert_eq!(frame.try_unpin(), Some(0));
+    }
+
     #[test]
     fn test_frame_new() {
         let frame = Frame::new();
@@ -199,6 +473,29 @@ mod tests {
         assert!(!frame.is_pinned());
     }
 
+    #[test]
+    fn test_frame_try_pin() {
+        let frame = Frame::new();
+
+        assert_eq!(frame.try_pin(2), Some(1));
+        assert_eq!(frame.try_pin(2), Some(2));
+        assert_eq!(frame.try_pin(2), None);
+        assert_eq!(frame.pin_count(), 2);
+
+        frame.unpin();
+        assert_eq!(frame.try_pin(2), Some(2));
+    }
+
+    #[test]
+    fn test_frame_try_unpin() {
+        let frame = Frame::new();
+        frame.pin();
+
+        assert_eq!(frame.try_unpin(), Some(0));
+        assert_eq!(frame.try_unpin(), None);
+        assert_eq!(frame.pin_count(), 0);
+    }
+
     #[test]
     #[should_panic(expected = "pin count underflow")]
     fn test_frame_unpin_underflow() {
@@ -277,6 +574,116 @@ mod tests {
         assert_eq!(frame.page().as_slice()[100], 0);
     }
 
+    #[test]
+    fn test_frame_zero_page_skips_clean_frames() {
+        let frame = Frame::new();
+        assert!(!frame.zero_page());
+
+        frame.page_mut().as_mut_slice()[100] = 0xFF;
+        assert!(frame.zero_page());
+        assert_eq!(frame.page().as_slice()[100], 0);
+        assert!(!frame.zero_page());
+
+        drop(frame.page_upgradable());
+        assert!(frame.zero_page());
+
+        drop(frame.page_mut());
+        frame.reset();
+        assert!(!frame.zero_page());
+    }
+
+    #[test]
+    fn test_frame_generation() {
+        let frame = Frame::new();
+        assert_eq!(frame.generation(), 0);
+
+        frame.set_page_id(Some(PageId::new(1)));
+        let gen = frame.generation();
+        assert_eq!(gen, 1);
+
+        // Pins and unpins don't change the generation
+        frame.pin();
+        frame.unpin();
+        frame.mark_dirty();
+        assert_eq!(frame.generation(), gen);
+
+        // Reassignment and reset do
+        frame.set_page_id(Some(PageId::new(2)));
+        assert!(frame.generation() > gen);
+        let gen = frame.generation();
+        frame.reset();
+        assert!(frame.generation() > gen);
+    }
+
+    #[test]
+    fn test_frame_last_write() {
+        let frame = Frame::new();
+        frame.set_page_id(Some(PageId::new(1)));
+        assert_eq!(frame.last_write(), None);
+
+        frame.record_write(42);
+        assert_eq!(frame.last_write(), Some(42));
+
+        // A new page starts unwritten
+        frame.set_page_id(Some(PageId::new(2)));
+        assert_eq!(frame.last_write(), None);
+
+        frame.record_write(7);
+        frame.reset();
+        assert_eq!(frame.last_write(), None);
+    }
+
+    #[test]
+    fn test_frame_last_access() {
+        let frame = Frame::new();
+        frame.set_page_id(Some(PageId::new(1)));
+        assert_eq!(frame.last_access(), None);
+
+        frame.record_access_time(10);
+        frame.record_access_time(25);
+        assert_eq!(frame.last_access(), Some(25));
+
+        // Unmapping (eviction) and reset both clear it
+        frame.set_page_id(None);
+        assert_eq!(frame.last_access(), None);
+
+        frame.record_access_time(7);
+        frame.reset();
+        assert_eq!(frame.last_access(), None);
+    }
+
+    #[test]
+    fn test_frame_release_page_memory() {
+        let frame = Frame::new();
+        frame.page_mut().as_mut_slice()[123] = 0xAB;
+        frame.release_page_memory();
+        assert!(frame.page().is_zeroed());
+        // Already zero, so no fill needed
+        assert!(!frame.zero_page());
+
+        // The memory is usable again afterwards
+        frame.page_mut().as_mut_slice()[123] = 0xCD;
+        assert_eq!(frame.page().as_slice()[123], 0xCD);
+    }
+
+    #[test]
+    fn test_frame_pending_access() {
+        let frame = Frame::new();
+        assert!(!frame.take_pending_access());
+
+        assert!(frame.defer_access());
+        assert!(!frame.defer_access());
+        assert!(frame.has_pending_access());
+        assert!(frame.take_pending_access());
+        assert!(!frame.take_pending_access());
+
+        frame.defer_access();
+        frame.set_replacer_evictable(true);
+        frame.reset();
+        assert!(!frame.take_pending_access());
+        assert!(!frame.replacer_evictable());
+    }
+
     #[test]
     fn test_frame_concurrent_reads() {
         use std::sync::Arc;
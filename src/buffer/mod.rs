@@ -6,17 +6,26 @@
 //! # Components
 //! - [`BufferPoolManager`] - The main page cache
 //! - [`Frame`] - A slot in the buffer pool holding a page + metadata
-//! - [`PageReadGuard`] / [`PageWriteGuard`] - RAII guards for page access
+//! - [`PageReadGuard`] / [`PageWriteGuard`] / [`PageUpgradableGuard`] - RAII
+//!   guards for page access
+//! - [`LossyReadGuard`] - Non-blocking read that may return a stale copy
 //! - [`BufferPoolStats`] - Performance statistics
+//! - [`PoolSnapshot`] - Point-in-time copy of pool metadata
+//! - [`op_log`] - Operation recording and deterministic replay
 //! - [`replacer`] - Eviction policy implementations
 
 mod buffer_pool_manager;
 mod frame;
+pub mod op_log;
 mod page_guard;
 pub mod replacer;
+mod snapshot;
 mod stats;
 
 pub use buffer_pool_manager::BufferPoolManager;
 pub use frame::Frame;
-pub use page_guard::{PageReadGuard, PageWriteGuard};
-pub use stats::{BufferPoolStats, StatsSnapshot};
\ No newline at end of file
+pub use op_log::{replay, Divergence, LoggedOp, PoolOp};
+pub use page_guard::{LossyReadGuard, PageReadGuard, PageUpgradableGuard, PageWriteGuard};
+pub use snapshot::{FrameSnapshot, PoolSnapshot};
+pub(crate) use stats::LatencyReservoir;
+pub use stats::{AccessLatency, BufferPoolStats, LatencyPercentiles, StatsSnapshot};
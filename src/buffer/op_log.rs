@@ -0,0 +1,211 @@
+//! Operation log for deterministic replay.
+//!
+//! A pool built with [`BufferPoolManager::with_op_log`] records each public
+//! page operation it serves, with whether it succeeded. [`replay`] runs a
+//! captured log against another pool, one operation at a time, so a
+//! sequence that triggered a bug can be reproduced single-threaded.
+//!
+//! The log grows by one entry per operation until drained with
+//! [`BufferPoolManager::take_op_log`]; drained chunks replay in sequence.
+//!
+//! Only the API sequence is logged, not page contents or guard lifetimes:
+//! replay drops each guard immediately. Composite calls (e.g.
+//! `read_page_copy`, `fetch_pages_write`) are logged as the fetches they
+//! make.
+
+use std::fmt;
+
+use crate::common::{Error, PageId, Result};
+
+use super::BufferPoolManager;
+
+/// A logged buffer pool operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolOp {
+    /// `fetch_page_read` (or `fetch_page_read_lossy`)
+    FetchRead(PageId),
+    /// `fetch_page_write`
+    FetchWrite(PageId),
+    /// `fetch_page_upgradable`
+    FetchUpgradable(PageId),
+    /// `fetch_page_write_no_read`
+    FetchWriteNoRead(PageId),
+    /// `fetch_or_create`
+    FetchOrCreate(PageId),
+    /// `allocate_page_id`
+    AllocatePageId,
+    /// `new_page` (or `new_page_typed`)
+    NewPage,
+    /// `delete_page`
+    DeletePage(PageId),
+    /// `delete_page_flushing`
+    DeletePageFlushing(PageId),
+    /// `clear_page`
+    ClearPage(PageId),
+    /// `flush_page`
+    FlushPage(PageId),
+    /// `flush_all_pages`
+    FlushAll,
+}
+
+/// One entry in the operation log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggedOp {
+    /// The operation.
+    pub op: PoolOp,
+    /// `Ok`, or the error's message.
+    pub result: std::result::Result<(), String>,
+}
+
+impl LoggedOp {
+    pub(crate) fn new<T>(op: PoolOp, result: &Result<T>) -> Self {
+        Self {
+            op,
+            result: result.as_ref().map(|_| ()).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// The first operation whose replayed outcome differs from the logged one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Position of the operation in the log.
+    pub index: usize,
+    /// The operation.
+    pub op: PoolOp,
+    /// Outcome during replay.
+    pub got: std::result::Result<(), String>,
+    /// Outcome in the log.
+    pub logged: std::result::Result<(), String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "replay diverged at op {} ({:?}): got {:?}, logged {:?}",
+            self.index, self.op, self.got, self.logged
+        )
+    }
+}
+
+/// Re-execute `ops` against `bpm`, in order.
+///
+/// `bpm` should be fresh, over a fresh file, and the same size as the pool
+/// that recorded the log. Returns the first divergence, or `None` if every
+/// operation had its logged outcome.
+///
+/// # Errors
+/// `Error::InvalidConfig` if `bpm` already has pages resident.
+pub fn replay(ops: &[LoggedOp], bpm: &BufferPoolManager) -> Result<Option<Divergence>> {
+    if bpm.page_count() != 0 {
+        return Err(Error::InvalidConfig(format!(
+            "replay needs an empty pool, found {} resident pages",
+            bpm.page_count()
+        )));
+    }
+
+    for (index, logged) in ops.iter().enumerate() {
+        let got = LoggedOp::new(logged.op, &apply(logged.op, bpm));
+        if got.result != logged.result {
+            return Ok(Some(Divergence {
+                index,
+                op: logged.op,
+                got: got.result,
+                logged: logged.result.clone(),
+            }));
+        }
+    }
+    Ok(None)
+}
+
+fn apply(op: PoolOp, bpm: &BufferPoolManager) -> Result<()> {
+    match op {
+        PoolOp::FetchRead(pid) => bpm.fetch_page_read(pid).map(drop),
+        PoolOp::FetchWrite(pid) => bpm.fetch_page_write(pid).map(drop),
+        PoolOp::FetchUpgradable(pid) => bpm.fetch_page_upgradable(pid).map(drop),
+        PoolOp::FetchWriteNoRead(pid) => bpm.fetch_page_write_no_read(pid).map(drop),
+        PoolOp::FetchOrCreate(pid) => bpm.fetch_or_create(pid).map(drop),
+        PoolOp::AllocatePageId => bpm.allocate_page_id().map(drop),
+        PoolOp::NewPage => bpm.new_page().map(drop),
+        PoolOp::DeletePage(pid) => bpm.delete_page(pid),
+        PoolOp::DeletePageFlushing(pid) => bpm.delete_page_flushing(pid),
+        PoolOp::ClearPage(pid) => bpm.clear_page(pid),
+        PoolOp::FlushPage(pid) => bpm.flush_page(pid),
+        PoolOp::FlushAll => bpm.flush_all_pages(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::DiskManager;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_replay_reaches_same_resident_state() {
+        let dir = tempdir().unwrap();
+        let recorded =
+            BufferPoolManager::new(3, DiskManager::create(dir.path().join("a.db")).unwrap())
+                .with_op_log();
+
+        let pids: Vec<PageId> = (0..5)
+            .map(|_| recorded.new_page().unwrap().page_id())
+            .collect();
+        recorded.flush_page(pids[4]).unwrap();
+        drop(recorded.fetch_page_read(pids[0]).unwrap());
+        drop(recorded.fetch_page_write(pids[1]).unwrap());
+        recorded.delete_page(pids[4]).unwrap();
+        drop(recorded.fetch_or_create(PageId::new(5)).unwrap());
+        assert!(recorded.fetch_page_read(PageId::new(99)).is_err());
+        recorded.flush_all_pages().unwrap();
+        drop(recorded.fetch_page_upgradable(pids[2]).unwrap());
+        drop(recorded.fetch_page_write_no_read(pids[3]).unwrap());
+
+        let log = recorded.op_log().unwrap();
+        assert_eq!(log.len(), 14);
+        assert_eq!(log[0].op, PoolOp::NewPage);
+        assert!(log[10].result.is_err());
+
+        // Draining hands over the entries and starts an empty log
+        assert_eq!(recorded.take_op_log(), Some(log.clone()));
+        assert_eq!(recorded.op_log(), Some(Vec::new()));
+
+        let fresh =
+            BufferPoolManager::new(3, DiskManager::create(dir.path().join("b.db")).unwrap());
+        assert_eq!(fresh.op_log(), None);
+        assert_eq!(fresh.take_op_log(), None);
+        assert_eq!(replay(&log, &fresh).unwrap(), None);
+
+        let state = |bpm: &BufferPoolManager| {
+            let snapshot = bpm.snapshot();
+            let mut pages: Vec<(PageId, bool)> = snapshot
+                .frames()
+                .iter()
+                .filter_map(|f| f.page_id.map(|pid| (pid, f.is_dirty)))
+                .collect();
+            pages.sort();
+            pages
+        };
+        assert_eq!(state(&fresh), state(&recorded));
+        assert_eq!(fresh.stats().snapshot(), recorded.stats().snapshot());
+    }
+
+    #[test]
+    fn test_replay_reports_divergence() {
+        let dir = tempdir().unwrap();
+        let bpm = BufferPoolManager::new(2, DiskManager::create(dir.path().join("a.db")).unwrap());
+        let log = [LoggedOp {
+            op: PoolOp::FetchRead(PageId::new(0)),
+            result: Ok(()),
+        }];
+        let divergence = replay(&log, &bpm).unwrap().unwrap();
+        assert_eq!(divergence.index, 0);
+        assert!(divergence.got.is_err());
+        assert!(divergence.to_string().contains("op 0"));
+
+        // A pool with pages already resident can't reproduce the log
+        let _pid = bpm.new_page().unwrap().page_id();
+        assert!(matches!(replay(&log, &bpm), Err(Error::InvalidConfig(_))));
+    }
+}
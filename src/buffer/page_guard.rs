@@ -3,16 +3,19 @@
 //! These guards provide safe access to pages in the buffer pool:
 //! - [`PageReadGuard`] - Shared read access (multiple allowed)
 //! - [`PageWriteGuard`] - Exclusive write access (auto-marks dirty)
+//! - [`PageUpgradableGuard`] - Shared access with the right to upgrade
+//! - [`LossyReadGuard`] - A latched read, or a possibly stale copy
 //!
-//! Both guards auto-unpin the page when dropped. The `drop_guard()` method
+//! All guards auto-unpin the page when dropped. The `drop_guard()` method
 //! allows explicit early release and is safe to call multiple times.
 
 use std::ops::{Deref, DerefMut};
 
-use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
+use parking_lot::{RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard};
 
-use crate::common::{FrameId, PageId};
-use crate::storage::page::Page;
+use crate::common::trace::trace_event;
+use crate::common::{FrameId, Lsn, PageId, Result};
+use crate::storage::page::{BTreeNodePage, Page, PageHeader, PageType};
 
 use super::buffer_pool_manager::BufferPoolManager;
 
@@ -78,6 +81,48 @@ impl<'a> PageReadGuard<'a> {
         self.dropped
     }
 
+    /// Get a second read guard for the same page without a full fetch.
+    ///
+    /// Bumps the pin count and shares the read latch; the page table and
+    /// replacer are not consulted and no cache hit is counted. The latch is
+    /// taken recursively, so a writer waiting on the page can't deadlock
+    /// the clone against `self`.
+    ///
+    /// # Panics
+    /// Panics if called after `drop_guard()`.
+    pub fn clone_ref(&self) -> PageReadGuard<'a> {
+        let held = self
+            .lock
+            .as_ref()
+            .expect("PageReadGuard used after drop_guard()");
+        let lock = RwLockReadGuard::rwlock(held).read_recursive();
+        self.bpm.repin_held(self.frame_id);
+        PageReadGuard::new(self.bpm, self.frame_id, self.page_id, lock)
+    }
+
+    /// Decode the page header.
+    #[inline]
+    pub fn header(&self) -> PageHeader {
+        self.deref().header()
+    }
+
+    /// The page type, read without decoding the rest of the header.
+    #[inline]
+    pub fn page_type(&self) -> PageType {
+        self.deref().page_type()
+    }
+
+    /// The page LSN, read without decoding the rest of the header.
+    #[inline]
+    pub fn lsn(&self) -> Lsn {
+        self.deref().lsn()
+    }
+
+    /// View the page as a B-tree node, or `None` if it isn't one.
+    pub fn btree_node(&self) -> Option<BTreeNodePage<'_>> {
+        BTreeNodePage::new(self)
+    }
+
     /// Explicitly drop the guard, releasing the lock and unpinning the page.
     ///
     /// Safe to call multiple times - subsequent calls are no-ops.
@@ -86,7 +131,7 @@ impl<'a> PageReadGuard<'a> {
         if !self.dropped {
             self.dropped = true;
             self.lock.take(); // Release the lock first
-            self.bpm.unpin_page_internal(self.frame_id, false);
+            unpin(self.bpm, self.frame_id, self.page_id, false);
         }
     }
 }
@@ -170,6 +215,53 @@ impl<'a> PageWriteGuard<'a> {
         self.dropped
     }
 
+    /// Decode the page header.
+    #[inline]
+    pub fn header(&self) -> PageHeader {
+        self.deref().header()
+    }
+
+    /// The page type, read without decoding the rest of the header.
+    #[inline]
+    pub fn page_type(&self) -> PageType {
+        self.deref().page_type()
+    }
+
+    /// The page LSN, read without decoding the rest of the header.
+    #[inline]
+    pub fn lsn(&self) -> Lsn {
+        self.deref().lsn()
+    }
+
+    /// Overwrite the page header.
+    #[inline]
+    pub fn set_header(&mut self, header: &PageHeader) {
+        self.deref_mut().set_header(header);
+    }
+
+    /// Overwrite the page LSN, leaving the rest of the header alone.
+    #[inline]
+    pub fn set_lsn(&mut self, lsn: Lsn) {
+        self.deref_mut().set_lsn(lsn);
+    }
+
+    /// Write the page to disk now, keeping the latch and the pin.
+    ///
+    /// The checksum is updated first, so the on-disk copy verifies. Later
+    /// modifications through this guard are written as usual once the page
+    /// is flushed or evicted.
+    ///
+    /// # Panics
+    /// Panics if called after `drop_guard()`.
+    pub fn flush(&mut self) -> Result<()> {
+        let page = self
+            .lock
+            .as_mut()
+            .expect("PageWriteGuard used after drop_guard()");
+        page.update_checksum();
+        self.bpm.write_through(self.page_id, page)
+    }
+
     /// Explicitly drop the guard, releasing the lock and unpinning the page.
     ///
     /// Safe to call multiple times - subsequent calls are no-ops.
@@ -178,7 +270,7 @@ impl<'a> PageWriteGuard<'a> {
         if !self.dropped {
             self.dropped = true;
             self.lock.take(); // Release the lock first
-            self.bpm.unpin_page_internal(self.frame_id, true); // Always dirty
+            unpin(self.bpm, self.frame_id, self.page_id, true); // Always dirty
         }
     }
 }
@@ -207,4 +299,178 @@ impl Drop for PageWriteGuard<'_> {
     fn drop(&mut self) {
         self.drop_guard();
     }
-}
\ No newline at end of file
+}
+/// Guard for read access with the right to upgrade to exclusive access.
+///
+/// Coexists with [`PageReadGuard`]s for the same page, but only one
+/// upgradable or write guard can exist at a time, so [`upgrade`](Self::upgrade)
+/// never races another writer. The page stays pinned across upgrade and
+/// downgrade, and is unpinned (not dirtied) if the guard is dropped as is.
+///
+/// # Example
+/// ```ignore
+/// let guard = bpm.fetch_page_upgradable(page_id)?;
+/// if needs_split(&guard) {
+///     let mut guard = guard.upgrade(); // waits for readers to drain
+///     guard.as_mut_slice()[0] = 0xFF;
+/// }
+/// ```
+pub struct PageUpgradableGuard<'a> {
+    /// Reference back to BPM for unpin on drop.
+    bpm: &'a BufferPoolManager,
+    /// Frame holding this page.
+    frame_id: FrameId,
+    /// Page ID for convenience.
+    page_id: PageId,
+    /// Lock guard providing access to page data.
+    /// Option allows take() for explicit drop and conversion.
+    lock: Option<RwLockUpgradableReadGuard<'a, Page>>,
+    /// Whether this guard has been dropped.
+    dropped: bool,
+}
+
+impl<'a> PageUpgradableGuard<'a> {
+    /// Create a new upgradable guard.
+    pub(crate) fn new(
+        bpm: &'a BufferPoolManager,
+        frame_id: FrameId,
+        page_id: PageId,
+        lock: RwLockUpgradableReadGuard<'a, Page>,
+    ) -> Self {
+        Self {
+            bpm,
+            frame_id,
+            page_id,
+            lock: Some(lock),
+            dropped: false,
+        }
+    }
+
+    /// Get the page ID.
+    #[inline]
+    pub fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    /// Get the frame ID.
+    #[inline]
+    pub fn frame_id(&self) -> FrameId {
+        self.frame_id
+    }
+
+    /// Check if this guard has been dropped.
+    #[inline]
+    pub fn is_dropped(&self) -> bool {
+        self.dropped
+    }
+
+    /// Upgrade to exclusive access, blocking until plain readers drop.
+    ///
+    /// The pin is carried over to the returned guard.
+    ///
+    /// # Panics
+    /// Panics if called after `drop_guard()`.
+    pub fn upgrade(mut self) -> PageWriteGuard<'a> {
+        let lock = self
+            .lock
+            .take()
+            .expect("PageUpgradableGuard used after drop_guard()");
+        self.dropped = true;
+
+        let lock = RwLockUpgradableReadGuard::upgrade(lock);
+        self.bpm.preserve_for_snapshot(self.page_id, &lock);
+        PageWriteGuard::new(self.bpm, self.frame_id, self.page_id, lock)
+    }
+
+    /// Give up the write intent, keeping shared access.
+    ///
+    /// The pin is carried over to the returned guard.
+    ///
+    /// # Panics
+    /// Panics if called after `drop_guard()`.
+    pub fn downgrade(mut self) -> PageReadGuard<'a> {
+        let lock = self
+            .lock
+            .take()
+            .expect("PageUpgradableGuard used after drop_guard()");
+        self.dropped = true;
+
+        let lock = RwLockUpgradableReadGuard::downgrade(lock);
+        PageReadGuard::new(self.bpm, self.frame_id, self.page_id, lock)
+    }
+
+    /// Explicitly drop the guard, releasing the lock and unpinning the page.
+    ///
+    /// Safe to call multiple times - subsequent calls are no-ops.
+    pub fn drop_guard(&mut self) {
+        if !self.dropped {
+            self.dropped = true;
+            self.lock.take(); // Release the lock first
+            unpin(self.bpm, self.frame_id, self.page_id, false);
+        }
+    }
+}
+
+impl Deref for PageUpgradableGuard<'_> {
+    type Target = Page;
+
+    #[inline]
+    fn deref(&self) -> &Page {
+        self.lock
+            .as_ref()
+            .expect("PageUpgradableGuard used after drop_guard()")
+    }
+}
+
+impl Drop for PageUpgradableGuard<'_> {
+    fn drop(&mut self) {
+        self.drop_guard();
+    }
+}
+
+/// Unpin a guard's frame on release.
+///
+/// An unpin error is a bookkeeping bug with nowhere to go from a drop, so
+/// it is reported as an `unpin_error` event rather than returned.
+fn unpin(bpm: &BufferPoolManager, frame_id: FrameId, page_id: PageId, is_dirty: bool) {
+    if let Err(e) = bpm.unpin_page_internal(frame_id, is_dirty) {
+        trace_event!(
+            ERROR,
+            "unpin_error",
+            page_id = page_id.0,
+            frame_id = frame_id.0,
+            error = e.to_string()
+        );
+    }
+}
+
+/// Result of [`BufferPoolManager::fetch_page_read_lossy`].
+///
+/// Either a normal read guard, or, when the page's write latch was held, an
+/// owned copy of the page as last flushed. Derefs to the page either way.
+pub enum LossyReadGuard<'a> {
+    /// The read latch was free; the page is current.
+    Fresh(PageReadGuard<'a>),
+    /// The page was write-latched; this is its last flushed contents.
+    Stale(Box<Page>),
+}
+
+impl LossyReadGuard<'_> {
+    /// Check if this read may miss changes made since the last flush.
+    #[inline]
+    pub fn is_stale(&self) -> bool {
+        matches!(self, LossyReadGuard::Stale(_))
+    }
+}
+
+impl Deref for LossyReadGuard<'_> {
+    type Target = Page;
+
+    #[inline]
+    fn deref(&self) -> &Page {
+        match self {
+            LossyReadGuard::Fresh(guard) => guard,
+            LossyReadGuard::Stale(page) => page,
+        }
+    }
+}
@@ -5,12 +5,19 @@
 
 use std::collections::{HashSet, VecDeque};
 
+use super::Replacer;
 use crate::common::{FrameId, PageId};
 
 /// FIFO replacement policy.
 ///
 /// Evicts pages in the order they were first brought into the buffer pool.
 /// Once a frame is in the queue, re-accessing it does NOT move it to the back.
+///
+/// # Determinism
+/// The eviction order depends only on the sequence of calls: the victim is
+/// always the evictable frame that entered the queue earliest, so there are
+/// no ties. The hash sets are used for membership tests only and are never
+/// iterated, so their ordering can't leak into the result.
 pub struct FifoReplacer {
     /// Queue of frame IDs in access order (front = oldest).
     queue: VecDeque<FrameId>,
@@ -65,9 +72,16 @@ impl FifoReplacer {
     /// **Important**: This implementation does NOT remove non-evictable frames from
     /// the queue. It finds the first evictable frame and only removes that one.
     pub fn evict(&mut self) -> Option<FrameId> {
-        // Find the position of the first evictable frame
-        let position = self.queue.iter()
-            .position(|&fid| self.evictable.contains(&fid))?;
+        self.evict_if(|_| true)
+    }
+
+    /// Evict the oldest evictable frame that also satisfies `accept`.
+    ///
+    /// Frames that are rejected keep their queue position and evictable
+    /// flag, so a caller can veto candidates (e.g. frames it knows are pinned)
+    /// without disturbing the FIFO order.
+    pub fn evict_if(&mut self, accept: impl FnMut(FrameId) -> bool) -> Option<FrameId> {
+        let position = self.position_if(accept)?;
 
         // Remove only that frame
         let frame_id = self.queue.remove(position)?;
@@ -77,6 +91,36 @@ impl FifoReplacer {
         Some(frame_id)
     }
 
+    /// Report the frame [`evict_if`](Self::evict_if) would evict, without
+    /// evicting it.
+    pub fn peek_if(&self, accept: impl FnMut(FrameId) -> bool) -> Option<FrameId> {
+        self.position_if(accept)
+            .map(|position| self.queue[position])
+    }
+
+    /// Position of the first evictable frame in the queue that satisfies
+    /// `accept`.
+    fn position_if(&self, mut accept: impl FnMut(FrameId) -> bool) -> Option<usize> {
+        self.queue
+            .iter()
+            .position(|&fid| self.evictable.contains(&fid) && accept(fid))
+    }
+
+    /// Like [`evict_if`](Self::evict_if), but skip frames for which
+    /// `is_dirty` holds unless no acceptable clean frame exists.
+    ///
+    /// The replacer doesn't track dirtiness itself; the caller supplies it.
+    /// Evicting a clean frame needs no write-back, so this trades a little
+    /// FIFO accuracy for lower eviction latency.
+    pub fn evict_preferring_clean(
+        &mut self,
+        mut accept: impl FnMut(FrameId) -> bool,
+        mut is_dirty: impl FnMut(FrameId) -> bool,
+    ) -> Option<FrameId> {
+        self.evict_if(|fid| accept(fid) && !is_dirty(fid))
+            .or_else(|| self.evict_if(accept))
+    }
+
     /// Remove a frame from the replacer entirely.
     ///
     /// Called when a page is deleted from the buffer pool.
@@ -95,6 +139,59 @@ impl FifoReplacer {
     pub fn size(&self) -> usize {
         self.evictable.len()
     }
+
+    /// Describe the replacer's internal ordering for debugging.
+    ///
+    /// Lists the queue from oldest to newest; evictable frames are marked
+    /// with `*`. For example `FIFO [0*, 1, 2*] evictable=2`.
+    pub fn debug_state(&self) -> String {
+        let entries: Vec<String> = self
+            .queue
+            .iter()
+            .map(|fid| {
+                if self.evictable.contains(fid) {
+                    format!("{}*", fid.0)
+                } else {
+                    fid.0.to_string()
+                }
+            })
+            .collect();
+        format!("FIFO [{}] evictable={}", entries.join(", "), self.size())
+    }
+}
+
+impl Replacer for FifoReplacer {
+    fn record_access(&mut self, frame_id: FrameId, page_id: PageId) {
+        FifoReplacer::record_access(self, frame_id, page_id);
+    }
+
+    fn set_evictable(&mut self, frame_id: FrameId, evictable: bool) {
+        FifoReplacer::set_evictable(self, frame_id, evictable);
+    }
+
+    fn evict_if(&mut self, accept: &mut dyn FnMut(FrameId) -> bool) -> Option<FrameId> {
+        FifoReplacer::evict_if(self, accept)
+    }
+
+    fn peek_if(&self, accept: &mut dyn FnMut(FrameId) -> bool) -> Option<FrameId> {
+        FifoReplacer::peek_if(self, accept)
+    }
+
+    fn remove(&mut self, frame_id: FrameId) {
+        FifoReplacer::remove(self, frame_id);
+    }
+
+    fn size(&self) -> usize {
+        FifoReplacer::size(self)
+    }
+
+    fn evict(&mut self) -> Option<FrameId> {
+        FifoReplacer::evict(self)
+    }
+
+    fn debug_state(&self) -> String {
+        FifoReplacer::debug_state(self)
+    }
 }
 
 impl Default for FifoReplacer {
@@ -148,6 +245,64 @@ mod tests {
         assert_eq!(replacer.evict(), None);
     }
 
+    #[test]
+    fn test_evict_preferring_clean() {
+        let mut replacer = FifoReplacer::new();
+        for i in 0..3 {
+            replacer.record_access(FrameId::new(i), PageId::new(100 + i as u32));
+            replacer.set_evictable(FrameId::new(i), true);
+        }
+
+        // Frames 0 and 2 are dirty: the clean frame 1 goes first
+        let dirty = |fid: FrameId| fid.0 != 1;
+        assert_eq!(
+            replacer.evict_preferring_clean(|_| true, dirty),
+            Some(FrameId::new(1))
+        );
+        // Only dirty frames left, so FIFO order resumes
+        assert_eq!(
+            replacer.evict_preferring_clean(|_| true, dirty),
+            Some(FrameId::new(0))
+        );
+        assert_eq!(
+            replacer.evict_preferring_clean(|_| true, dirty),
+            Some(FrameId::new(2))
+        );
+        assert_eq!(replacer.evict_preferring_clean(|_| true, dirty), None);
+    }
+
+    #[test]
+    fn test_fifo_evict_if_keeps_rejected_in_place() {
+        let mut replacer = FifoReplacer::new();
+        for i in 0..3 {
+            replacer.record_access(FrameId::new(i), PageId::new(100 + i as u32));
+            replacer.set_evictable(FrameId::new(i), true);
+        }
+
+        // Veto frame 0: frame 1 goes, frame 0 stays at the front
+        assert_eq!(replacer.evict_if(|fid| fid.0 != 0), Some(FrameId::new(1)));
+        assert_eq!(replacer.evict_if(|_| false), None);
+        assert_eq!(replacer.size(), 2);
+        assert_eq!(replacer.evict(), Some(FrameId::new(0)));
+        assert_eq!(replacer.evict(), Some(FrameId::new(2)));
+    }
+
+    #[test]
+    fn test_fifo_peek_if_does_not_evict() {
+        let mut replacer = FifoReplacer::new();
+        for i in 0..3 {
+            replacer.record_access(FrameId::new(i), PageId::new(i as u32));
+            replacer.set_evictable(FrameId::new(i), true);
+        }
+        let before = replacer.debug_state();
+
+        assert_eq!(replacer.peek_if(|_| true), Some(FrameId::new(0)));
+        assert_eq!(replacer.peek_if(|fid| fid.0 != 0), Some(FrameId::new(1)));
+        assert_eq!(replacer.peek_if(|_| false), None);
+        assert_eq!(replacer.debug_state(), before);
+        assert_eq!(replacer.evict(), Some(FrameId::new(0)));
+    }
+
     #[test]
     fn test_fifo_remove() {
         let mut replacer = FifoReplacer::new();
@@ -212,6 +367,60 @@ mod tests {
         assert_eq!(replacer.evict(), Some(FrameId::new(0)));
     }
 
+    #[test]
+    fn test_fifo_debug_state() {
+        let mut replacer = FifoReplacer::new();
+        assert_eq!(replacer.debug_state(), "FIFO [] evictable=0");
+
+        replacer.record_access(FrameId::new(2), PageId::new(100));
+        replacer.record_access(FrameId::new(0), PageId::new(101));
+        replacer.record_access(FrameId::new(1), PageId::new(102));
+        replacer.set_evictable(FrameId::new(2), true);
+        replacer.set_evictable(FrameId::new(1), true);
+        assert_eq!(replacer.debug_state(), "FIFO [2*, 0, 1*] evictable=2");
+
+        replacer.evict();
+        assert_eq!(replacer.debug_state(), "FIFO [0, 1*] evictable=1");
+    }
+
+    /// Shared access/evictable sequence; returns every frame evicted, in order.
+    fn run_eviction_fixture(replacer: &mut FifoReplacer) -> Vec<FrameId> {
+        let mut evicted = Vec::new();
+        for i in [3, 1, 4, 0, 5, 2] {
+            replacer.record_access(FrameId::new(i), PageId::new(100 + i as u32));
+            replacer.set_evictable(FrameId::new(i), true);
+        }
+        // Re-access and pin some frames; FIFO ignores the re-access
+        replacer.record_access(FrameId::new(3), PageId::new(103));
+        replacer.set_evictable(FrameId::new(1), false);
+        replacer.set_evictable(FrameId::new(0), false);
+
+        evicted.extend(replacer.evict());
+        evicted.extend(replacer.evict());
+
+        // Bring back a frame and unpin the others
+        replacer.record_access(FrameId::new(3), PageId::new(200));
+        replacer.set_evictable(FrameId::new(3), true);
+        replacer.set_evictable(FrameId::new(1), true);
+        replacer.set_evictable(FrameId::new(0), true);
+
+        while let Some(frame_id) = replacer.evict() {
+            evicted.push(frame_id);
+        }
+        evicted
+    }
+
+    #[test]
+    fn test_eviction_sequence_is_deterministic() {
+        let expected: Vec<FrameId> = [3, 4, 1, 0, 5, 2, 3].map(FrameId::new).to_vec();
+
+        for _ in 0..10 {
+            let mut replacer = FifoReplacer::new();
+            assert_eq!(run_eviction_fixture(&mut replacer), expected);
+            assert_eq!(replacer.size(), 0);
+        }
+    }
+
     #[test]
     fn test_evict_only_removes_evicted_frame() {
         let mut replacer = FifoReplacer::new();
@@ -5,10 +5,83 @@
 //!
 //! Future implementations (Week 3-4):
 //! - LRU (Least Recently Used)
-//! - CLOCK (Second Chance)
+//! - CLOCK (Second Chance), with `ClockReplacer::with_sweep_limit` bounding
+//!   how many reference bits the hand clears per eviction
 //! - LRU-K (K-distance based)
 //! - 2Q (Two-Queue)
+//! - A configurable grace period: `record_access` takes a flag marking a
+//!   page as freshly loaded, and CLOCK/LRU skip it for its first sweep so a
+//!   scan flooding the pool can't evict it before its first re-reference
+//!
+//! Every policy implements [`Replacer`], and the buffer pool holds a boxed
+//! `dyn Replacer` (see `BufferPoolManager::with_replacer`). Once the
+//! built-in policies are selected by a `ReplacerKind`, a
+//! `ReplacerKind::Custom` variant holding a factory
+//! (`Arc<dyn Fn() -> Box<dyn Replacer + Send> + Send + Sync>`) lets
+//! experimental policies go through the same `with_replacer`/`set_replacer`
+//! plumbing and comparison harness as the built-in ones.
+//!
+//! Every policy must evict in an order determined only by the sequence of
+//! calls, so tests and trace replays are reproducible. Policies with ties
+//! break them by a documented rule rather than hash iteration order, e.g.
+//! LRU-K frames with fewer than K accesses by earliest first access, and
+//! CLOCK by hand position. Each policy pins its exact sequence on the
+//! eviction fixture used in `fifo.rs`'s tests.
 
 mod fifo;
 
-pub use fifo::FifoReplacer;
\ No newline at end of file
+pub use fifo::FifoReplacer;
+
+use crate::common::{FrameId, PageId};
+
+/// An eviction policy.
+///
+/// The buffer pool reports accesses and pin changes, and asks for a victim
+/// when it needs a frame. Implementations only track frame IDs; they never
+/// touch page data or take pool locks.
+pub trait Replacer {
+    /// Record that a frame was accessed while holding `page_id`.
+    fn record_access(&mut self, frame_id: FrameId, page_id: PageId);
+
+    /// Set whether a frame may be evicted (its pin count reached 0).
+    fn set_evictable(&mut self, frame_id: FrameId, evictable: bool);
+
+    /// Evict the policy's preferred evictable frame that also satisfies
+    /// `accept`.
+    ///
+    /// Rejected frames must keep their position and evictable flag, so the
+    /// pool can veto candidates (e.g. frames a cache hit has just pinned)
+    /// without disturbing the policy's order.
+    fn evict_if(&mut self, accept: &mut dyn FnMut(FrameId) -> bool) -> Option<FrameId>;
+
+    /// Report the frame [`evict_if`](Self::evict_if) would evict, without
+    /// changing any state.
+    fn peek_if(&self, accept: &mut dyn FnMut(FrameId) -> bool) -> Option<FrameId>;
+
+    /// Remove a frame from the replacer entirely.
+    fn remove(&mut self, frame_id: FrameId);
+
+    /// Number of evictable frames.
+    fn size(&self) -> usize;
+
+    /// Evict the policy's preferred evictable frame.
+    fn evict(&mut self) -> Option<FrameId> {
+        self.evict_if(&mut |_| true)
+    }
+
+    /// Like [`evict_if`](Self::evict_if), but skip frames for which
+    /// `is_dirty` holds unless no acceptable clean frame exists.
+    fn evict_preferring_clean(
+        &mut self,
+        accept: &mut dyn FnMut(FrameId) -> bool,
+        is_dirty: &mut dyn FnMut(FrameId) -> bool,
+    ) -> Option<FrameId> {
+        self.evict_if(&mut |fid| accept(fid) && !is_dirty(fid))
+            .or_else(|| self.evict_if(accept))
+    }
+
+    /// Describe the replacer's internal state for debugging.
+    fn debug_state(&self) -> String {
+        format!("evictable={}", self.size())
+    }
+}
@@ -0,0 +1,89 @@
+//! Point-in-time view of buffer pool metadata.
+//!
+//! A [`PoolSnapshot`] is captured by [`BufferPoolManager::snapshot`] under a
+//! brief page table lock and can then be inspected freely (e.g. by a
+//! monitoring thread) without touching any pool locks.
+//!
+//! [`BufferPoolManager::snapshot`]: super::BufferPoolManager::snapshot
+
+use crate::common::{FrameId, PageId};
+
+/// State of a single frame at snapshot time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameSnapshot {
+    /// The frame this entry describes.
+    pub frame_id: FrameId,
+    /// Page resident in the frame, or None if the frame was free.
+    pub page_id: Option<PageId>,
+    /// Pin count at snapshot time.
+    pub pin_count: u32,
+    /// Whether the frame held unflushed modifications.
+    pub is_dirty: bool,
+    /// Frame generation (see [`Frame::generation`](super::Frame::generation)).
+    pub generation: u64,
+}
+
+/// A consistent, read-only copy of buffer pool metadata.
+///
+/// Holds one entry per frame, indexed by `FrameId`. The resident page set is
+/// derived from the frames, so the two can never disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolSnapshot {
+    frames: Vec<FrameSnapshot>,
+}
+
+impl PoolSnapshot {
+    pub(crate) fn new(frames: Vec<FrameSnapshot>) -> Self {
+        Self { frames }
+    }
+
+    /// Per-frame state, indexed by `FrameId`.
+    pub fn frames(&self) -> &[FrameSnapshot] {
+        &self.frames
+    }
+
+    /// Iterate over the pages that were resident.
+    pub fn resident_pages(&self) -> impl Iterator<Item = PageId> + '_ {
+        self.frames.iter().filter_map(|f| f.page_id)
+    }
+
+    /// Find the frame that held `page_id`, if it was resident.
+    pub fn frame_for(&self, page_id: PageId) -> Option<&FrameSnapshot> {
+        self.frames.iter().find(|f| f.page_id == Some(page_id))
+    }
+
+    /// Number of resident pages.
+    pub fn page_count(&self) -> usize {
+        self.resident_pages().count()
+    }
+
+    /// Number of frames with a non-zero pin count.
+    pub fn pinned_count(&self) -> usize {
+        self.frames.iter().filter(|f| f.pin_count > 0).count()
+    }
+
+    /// Number of frames holding dirty pages.
+    pub fn dirty_count(&self) -> usize {
+        self.frames.iter().filter(|f| f.is_dirty).count()
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_snapshot_serde_roundtrip() {
+        let frame = FrameSnapshot {
+            frame_id: FrameId::new(2),
+            page_id: Some(PageId::new(9)),
+            pin_count: 1,
+            is_dirty: true,
+            generation: 4,
+        };
+        let json = serde_json::to_string(&frame).unwrap();
+        assert!(json.contains("\"page_id\":9"));
+        assert_eq!(serde_json::from_str::<FrameSnapshot>(&json).unwrap(), frame);
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 /// Statistics tracked by the buffer pool.
 ///
@@ -39,6 +40,18 @@ pub struct BufferPoolStats {
 
     /// Number of pages written to disk.
     pub pages_written: AtomicU64,
+
+    /// Misses on a page never seen before by this pool.
+    pub compulsory_misses: AtomicU64,
+
+    /// Misses on a page that was previously evicted to make room.
+    pub capacity_misses: AtomicU64,
+
+    /// All other misses (e.g. the page was explicitly deleted from the pool).
+    pub conflict_misses: AtomicU64,
+
+    /// Misses served from the victim cache instead of disk.
+    pub victim_hits: AtomicU64,
 }
 
 impl BufferPoolStats {
@@ -50,6 +63,10 @@ impl BufferPoolStats {
             evictions: AtomicU64::new(0),
             pages_read: AtomicU64::new(0),
             pages_written: AtomicU64::new(0),
+            compulsory_misses: AtomicU64::new(0),
+            capacity_misses: AtomicU64::new(0),
+            conflict_misses: AtomicU64::new(0),
+            victim_hits: AtomicU64::new(0),
         }
     }
 
@@ -76,6 +93,10 @@ impl BufferPoolStats {
             evictions: self.evictions.load(Ordering::Relaxed),
             pages_read: self.pages_read.load(Ordering::Relaxed),
             pages_written: self.pages_written.load(Ordering::Relaxed),
+            compulsory_misses: self.compulsory_misses.load(Ordering::Relaxed),
+            capacity_misses: self.capacity_misses.load(Ordering::Relaxed),
+            conflict_misses: self.conflict_misses.load(Ordering::Relaxed),
+            victim_hits: self.victim_hits.load(Ordering::Relaxed),
         }
     }
 
@@ -86,6 +107,10 @@ impl BufferPoolStats {
         self.evictions.store(0, Ordering::Relaxed);
         self.pages_read.store(0, Ordering::Relaxed);
         self.pages_written.store(0, Ordering::Relaxed);
+        self.compulsory_misses.store(0, Ordering::Relaxed);
+        self.capacity_misses.store(0, Ordering::Relaxed);
+        self.conflict_misses.store(0, Ordering::Relaxed);
+        self.victim_hits.store(0, Ordering::Relaxed);
     }
 }
 
@@ -110,12 +135,17 @@ impl Default for BufferPoolStats {
 /// println!("{}", snapshot);  // Can print safely
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StatsSnapshot {
     pub cache_hits: u64,
     pub cache_misses: u64,
     pub evictions: u64,
     pub pages_read: u64,
     pub pages_written: u64,
+    pub compulsory_misses: u64,
+    pub capacity_misses: u64,
+    pub conflict_misses: u64,
+    pub victim_hits: u64,
 }
 
 impl StatsSnapshot {
@@ -128,6 +158,71 @@ impl StatsSnapshot {
             self.cache_hits as f64 / total as f64
         }
     }
+
+    /// Average several snapshots field by field, rounding to the nearest
+    /// count. An empty slice gives all zeros.
+    ///
+    /// The result's `hit_rate()` is the pooled rate over all runs, so runs
+    /// with more accesses weigh more than in a plain mean of hit rates.
+    pub fn mean(snapshots: &[StatsSnapshot]) -> StatsSnapshot {
+        let n = snapshots.len() as u64;
+        let avg = |field: fn(&StatsSnapshot) -> u64| {
+            if n == 0 {
+                return 0;
+            }
+            let sum: u64 = snapshots.iter().map(field).sum();
+            (sum + n / 2) / n
+        };
+        StatsSnapshot {
+            cache_hits: avg(|s| s.cache_hits),
+            cache_misses: avg(|s| s.cache_misses),
+            evictions: avg(|s| s.evictions),
+            pages_read: avg(|s| s.pages_read),
+            pages_written: avg(|s| s.pages_written),
+            compulsory_misses: avg(|s| s.compulsory_misses),
+            capacity_misses: avg(|s| s.capacity_misses),
+            conflict_misses: avg(|s| s.conflict_misses),
+            victim_hits: avg(|s| s.victim_hits),
+        }
+    }
+
+    /// Population standard deviation of the snapshots' hit rates.
+    ///
+    /// Returns 0.0 for fewer than two snapshots.
+    pub fn hit_rate_stddev(snapshots: &[StatsSnapshot]) -> f64 {
+        if snapshots.len() < 2 {
+            return 0.0;
+        }
+        let n = snapshots.len() as f64;
+        let mean = snapshots.iter().map(Self::hit_rate).sum::<f64>() / n;
+        let variance = snapshots
+            .iter()
+            .map(|s| (s.hit_rate() - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        variance.sqrt()
+    }
+
+    /// Column names matching [`csv_fields`](Self::csv_fields).
+    pub const CSV_HEADER: &'static str = "cache_hits,cache_misses,evictions,pages_read,\
+pages_written,compulsory_misses,capacity_misses,conflict_misses,victim_hits";
+
+    /// The counters as comma-separated values, in [`CSV_HEADER`](Self::CSV_HEADER)
+    /// order, without a trailing newline.
+    pub fn csv_fields(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.cache_hits,
+            self.cache_misses,
+            self.evictions,
+            self.pages_read,
+            self.pages_written,
+            self.compulsory_misses,
+            self.capacity_misses,
+            self.conflict_misses,
+            self.victim_hits,
+        )
+    }
 }
 
 impl fmt::Display for StatsSnapshot {
@@ -143,6 +238,86 @@ impl fmt::Display for StatsSnapshot {
     }
 }
 
+/// Median and 99th percentile of a set of latency samples.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub p50: Duration,
+    pub p99: Duration,
+    /// Number of samples the percentiles were computed from (0 = no data).
+    pub samples: usize,
+}
+
+/// Sampled fetch latencies, split by cache hits and misses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccessLatency {
+    pub hit: LatencyPercentiles,
+    pub miss: LatencyPercentiles,
+}
+
+/// Fixed-size uniform sample of latencies (reservoir sampling).
+///
+/// Keeps at most [`capacity`](Self::CAPACITY) samples; once full, each new
+/// sample replaces a random one with probability `capacity / seen`, so the
+/// reservoir stays a uniform sample of everything recorded.
+#[derive(Debug)]
+pub(crate) struct LatencyReservoir {
+    samples: Vec<Duration>,
+    /// Samples offered so far.
+    seen: u64,
+    /// xorshift64 state for picking replacement slots.
+    rng: u64,
+}
+
+impl LatencyReservoir {
+    pub(crate) const CAPACITY: usize = 1024;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            samples: Vec::new(),
+            seen: 0,
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub(crate) fn record(&mut self, latency: Duration) {
+        self.seen += 1;
+        if self.samples.len() < Self::CAPACITY {
+            self.samples.push(latency);
+            return;
+        }
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let slot = self.rng % self.seen;
+        if (slot as usize) < Self::CAPACITY {
+            self.samples[slot as usize] = latency;
+        }
+    }
+
+    /// Nearest-rank p50 and p99 of the current samples.
+    pub(crate) fn percentiles(&self) -> LatencyPercentiles {
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        let rank = |p: f64| {
+            let idx = (p * sorted.len() as f64).ceil() as usize;
+            sorted
+                .get(idx.saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        LatencyPercentiles {
+            p50: rank(0.50),
+            p99: rank(0.99),
+            samples: sorted.len(),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.samples.clear();
+        self.seen = 0;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,6 +352,36 @@ mod tests {
         assert_eq!(snapshot.hit_rate(), 0.7);
     }
 
+    #[test]
+    fn test_snapshot_mean_and_stddev() {
+        let run = |cache_hits, cache_misses, evictions| StatsSnapshot {
+            cache_hits,
+            cache_misses,
+            evictions,
+            pages_read: cache_misses,
+            pages_written: 0,
+            compulsory_misses: 0,
+            capacity_misses: 0,
+            conflict_misses: 0,
+            victim_hits: 0,
+        };
+        let runs = [run(60, 40, 10), run(80, 20, 5), run(70, 30, 6)];
+
+        let mean = StatsSnapshot::mean(&runs);
+        assert_eq!(mean.cache_hits, 70);
+        assert_eq!(mean.cache_misses, 30);
+        assert_eq!(mean.pages_read, 30);
+        assert_eq!(mean.evictions, 7);
+        assert!((mean.hit_rate() - 0.7).abs() < 1e-9);
+
+        // Hit rates 0.6, 0.8, 0.7 deviate from 0.7 by 0.1, 0.1, 0
+        let expected = (0.02f64 / 3.0).sqrt();
+        assert!((StatsSnapshot::hit_rate_stddev(&runs) - expected).abs() < 1e-9);
+
+        assert_eq!(StatsSnapshot::mean(&[]).cache_hits, 0);
+        assert_eq!(StatsSnapshot::hit_rate_stddev(&runs[..1]), 0.0);
+    }
+
     #[test]
     fn test_stats_reset() {
         let stats = BufferPoolStats::new();
@@ -202,4 +407,41 @@ mod tests {
         assert!(display.contains("misses: 20"));
         assert!(display.contains("80.00%"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_latency_reservoir_percentiles() {
+        let mut reservoir = LatencyReservoir::new();
+        assert_eq!(reservoir.percentiles(), LatencyPercentiles::default());
+
+        for us in 1..=100 {
+            reservoir.record(Duration::from_micros(us));
+        }
+        let p = reservoir.percentiles();
+        assert_eq!(p.p50, Duration::from_micros(50));
+        assert_eq!(p.p99, Duration::from_micros(99));
+        assert_eq!(p.samples, 100);
+
+        // Stays bounded once full
+        for _ in 0..10_000 {
+            reservoir.record(Duration::from_micros(1));
+        }
+        assert_eq!(reservoir.percentiles().samples, LatencyReservoir::CAPACITY);
+        assert_eq!(reservoir.percentiles().p50, Duration::from_micros(1));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_stats_snapshot_serde_roundtrip() {
+        let stats = BufferPoolStats::new();
+        stats.cache_hits.fetch_add(80, Ordering::Relaxed);
+        stats.pages_written.fetch_add(3, Ordering::Relaxed);
+
+        let snapshot = stats.snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.contains("\"cache_hits\":80"));
+        assert_eq!(
+            serde_json::from_str::<StatsSnapshot>(&json).unwrap(),
+            snapshot
+        );
+    }
+}
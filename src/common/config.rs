@@ -1,5 +1,7 @@
 //! Configuration constants for InterchangeDB.
 
+use super::{Error, Result};
+
 /// Size of a page in bytes (4KB).
 ///
 /// This value is chosen to match:
@@ -22,6 +24,35 @@ pub const MAX_PAGES: u64 = (u32::MAX as u64) + 1;
 /// Maximum theoretical database size in bytes.
 pub const MAX_DB_SIZE_BYTES: u64 = MAX_PAGES * PAGE_SIZE as u64;
 
+/// Smallest supported page size (one disk sector).
+pub const MIN_PAGE_SIZE: usize = 512;
+
+/// Check a buffer pool configuration before building anything from it.
+///
+/// # Errors
+/// Returns `Error::InvalidConfig` if:
+/// - `page_size` is not a power of two, or is below [`MIN_PAGE_SIZE`]
+/// - `pool_size` is 0
+/// - `pool_size * page_size` overflows `usize`
+pub fn validate(pool_size: usize, page_size: usize) -> Result<()> {
+    if !page_size.is_power_of_two() || page_size < MIN_PAGE_SIZE {
+        return Err(Error::InvalidConfig(format!(
+            "page_size {} must be a power of two >= {}",
+            page_size, MIN_PAGE_SIZE
+        )));
+    }
+    if pool_size == 0 {
+        return Err(Error::InvalidConfig("pool_size must be > 0".to_string()));
+    }
+    if pool_size.checked_mul(page_size).is_none() {
+        return Err(Error::InvalidConfig(format!(
+            "pool_size {} * page_size {} overflows usize",
+            pool_size, page_size
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -32,6 +63,27 @@ mod tests {
         assert_eq!(PAGE_SIZE, 4096);
     }
 
+    #[test]
+    fn test_validate() {
+        assert!(validate(64, PAGE_SIZE).is_ok());
+        assert!(validate(1, MIN_PAGE_SIZE).is_ok());
+
+        for (pool_size, page_size) in [
+            (64, 3000),
+            (64, 256),
+            (64, 0),
+            (0, PAGE_SIZE),
+            (usize::MAX / 2, PAGE_SIZE),
+        ] {
+            assert!(
+                matches!(validate(pool_size, page_size), Err(Error::InvalidConfig(_))),
+                "validate({}, {})",
+                pool_size,
+                page_size
+            );
+        }
+    }
+
     #[test]
     fn test_max_db_size() {
         // 16TB = 16 * 1024^4 bytes
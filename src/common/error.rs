@@ -2,6 +2,8 @@
 
 use std::fmt;
 
+use super::PageId;
+
 /// Convenient Result type alias.
 ///
 /// Instead of writing `Result<T, Error>` everywhere, we can write `Result<T>`.
@@ -38,6 +40,79 @@ pub enum Error {
     ///
     /// This indicates a bug - unpinning should match pinning.
     PageNotPinned(u32),
+
+    /// Pinning the page would exceed the configured maximum pin count.
+    ///
+    /// Usually indicates a pin leak (e.g. a runaway cursor).
+    PinLimitExceeded(u32),
+
+    /// Some dirty pages could not be written during a bulk flush.
+    ///
+    /// Every page not listed in `failed` was written and is clean.
+    PartialFlush {
+        /// Number of pages written successfully.
+        flushed: usize,
+        /// Pages that failed to flush, with the error for each.
+        failed: Vec<(PageId, Error)>,
+    },
+
+    /// A page read hit end of file before a full page was read.
+    ///
+    /// The file is shorter than its page count implies, usually because it
+    /// was truncated outside the database.
+    ShortRead {
+        /// Page being read.
+        page_id: PageId,
+        /// Bytes actually read.
+        got: usize,
+        /// Bytes expected (the page size).
+        expected: usize,
+    },
+
+    /// A page read back doesn't match the sector checksums stored for it.
+    ///
+    /// Only checked when sector checksums are enabled. Usually means the
+    /// last write of the page was torn by a crash.
+    TornPage {
+        /// Page being read.
+        page_id: PageId,
+        /// First sector (512-byte unit) that doesn't match.
+        sector: usize,
+    },
+
+    /// A page header is malformed or has the wrong type for how the page
+    /// is being read (e.g. too few bytes, or a B-tree page opened as an LSM
+    /// manifest).
+    InvalidPageHeader(String),
+
+    /// The database file uses an on-disk format newer than this build
+    /// supports.
+    UnsupportedFormat {
+        /// Format version found in the file.
+        found: u16,
+        /// Newest format version this build can read.
+        supported: u16,
+    },
+
+    /// The database file is locked by another open `DiskManager`.
+    ///
+    /// Usually another process has the same database open.
+    DatabaseLocked,
+
+    /// A blocking wait gave up after the configured lock timeout.
+    ///
+    /// Usually means every pin is held and nothing is releasing one, as in
+    /// a deadlock between threads that each hold pins.
+    LockTimeout,
+
+    /// A configuration value is out of range (e.g. a zero pool size).
+    InvalidConfig(String),
+
+    /// An internal invariant was violated (poisoned lock, impossible state).
+    ///
+    /// Returned instead of panicking so a bug in one component doesn't
+    /// crash the whole process.
+    Internal(String),
 }
 
 impl fmt::Display for Error {
@@ -49,6 +124,40 @@ impl fmt::Display for Error {
             Error::InvalidPageId(pid) => write!(f, "Invalid page ID: {}", pid),
             Error::BufferPoolFull => write!(f, "Buffer pool is full"),
             Error::PageNotPinned(pid) => write!(f, "Page {} is not pinned", pid),
+            Error::PinLimitExceeded(pid) => {
+                write!(f, "Page {} exceeded the maximum pin count", pid)
+            }
+            Error::PartialFlush { flushed, failed } => {
+                write!(f, "Flushed {} pages but {} failed", flushed, failed.len())?;
+                if let Some((pid, err)) = failed.first() {
+                    write!(f, " (first: {}: {})", pid, err)?;
+                }
+                Ok(())
+            }
+            Error::ShortRead {
+                page_id,
+                got,
+                expected,
+            } => write!(
+                f,
+                "Short read of {}: got {} of {} bytes (file truncated?)",
+                page_id, got, expected
+            ),
+            Error::TornPage { page_id, sector } => {
+                write!(f, "Torn write detected in {} (sector {})", page_id, sector)
+            }
+            Error::InvalidPageHeader(msg) => write!(f, "Invalid page header: {}", msg),
+            Error::UnsupportedFormat { found, supported } => write!(
+                f,
+                "Unsupported format version {} (newest supported: {})",
+                found, supported
+            ),
+            Error::DatabaseLocked => {
+                write!(f, "Database file is locked by another process")
+            }
+            Error::LockTimeout => write!(f, "Timed out waiting for a lock"),
+            Error::InvalidConfig(msg) => write!(f, "Invalid configuration: {}", msg),
+            Error::Internal(msg) => write!(f, "Internal error: {}", msg),
         }
     }
 }
@@ -57,6 +166,9 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Error::Io(e) => Some(e),
+            Error::PartialFlush { failed, .. } => failed
+                .first()
+                .map(|(_, e)| e as &(dyn std::error::Error + 'static)),
             _ => None,
         }
     }
@@ -68,6 +180,12 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl<T> From<std::sync::PoisonError<T>> for Error {
+    fn from(err: std::sync::PoisonError<T>) -> Self {
+        Error::Internal(format!("poisoned lock: {}", err))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -82,6 +200,27 @@ mod tests {
             format!("{}", err),
             "No free frames available in buffer pool"
         );
+
+        let err = Error::PartialFlush {
+            flushed: 3,
+            failed: vec![(PageId::new(7), Error::PageNotFound(7))],
+        };
+        assert_eq!(
+            format!("{}", err),
+            "Flushed 3 pages but 1 failed (first: Page(7): Page 7 not found)"
+        );
+
+        let err = Error::TornPage {
+            page_id: PageId::new(3),
+            sector: 5,
+        };
+        assert_eq!(
+            format!("{}", err),
+            "Torn write detected in Page(3) (sector 5)"
+        );
+
+        let err = Error::LockTimeout;
+        assert_eq!(format!("{}", err), "Timed out waiting for a lock");
     }
 
     #[test]
@@ -95,6 +234,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_poison_error_conversion() {
+        use std::sync::{Arc, Mutex};
+
+        let lock = Arc::new(Mutex::new(0));
+        let lock_clone = Arc::clone(&lock);
+        let _ = std::thread::spawn(move || {
+            let _guard = lock_clone.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+
+        let err: Error = lock.lock().unwrap_err().into();
+        match err {
+            Error::Internal(msg) => assert!(msg.contains("poisoned")),
+            _ => panic!("Expected Internal error"),
+        }
+    }
+
     #[test]
     fn test_result_type_alias() {
         fn might_fail() -> Result<u32> {
@@ -17,6 +17,8 @@ use std::fmt;
 /// // Can use directly as index: frames[frame_id.0]
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct FrameId(pub usize);
 
 impl FrameId {
@@ -53,4 +55,15 @@ mod tests {
     fn test_frame_id_display() {
         assert_eq!(format!("{}", FrameId::new(42)), "Frame(42)");
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_frame_id_serde_roundtrip() {
+        let json = serde_json::to_string(&FrameId::new(7)).unwrap();
+        assert_eq!(json, "7");
+        assert_eq!(
+            serde_json::from_str::<FrameId>(&json).unwrap(),
+            FrameId::new(7)
+        );
+    }
+}
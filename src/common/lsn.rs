@@ -0,0 +1,80 @@
+//! Log sequence number type.
+
+use std::fmt;
+
+/// Position of a record in the write-ahead log.
+///
+/// Every page header stores the LSN of the last change applied to it, so
+/// recovery can tell which logged changes a page already reflects. LSNs
+/// increase monotonically, so ordering LSNs orders log records.
+///
+/// # Example
+/// ```
+/// use interchangedb::Lsn;
+///
+/// let lsn = Lsn::new(7);
+/// assert!(lsn.is_valid());
+/// assert!(Lsn::ZERO < lsn);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Lsn(pub u64);
+
+impl Lsn {
+    /// LSN of a page that has never been logged. Orders before every
+    /// logged record.
+    pub const ZERO: Lsn = Lsn(0);
+
+    /// Invalid/sentinel LSN.
+    ///
+    /// Used to represent "no LSN", e.g. an unset position.
+    pub const INVALID: Lsn = Lsn(u64::MAX);
+
+    /// Create a new Lsn.
+    #[inline]
+    pub fn new(lsn: u64) -> Self {
+        Lsn(lsn)
+    }
+
+    /// Check if this LSN is valid (not the sentinel value).
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        *self != Self::INVALID
+    }
+}
+
+impl fmt::Display for Lsn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::INVALID {
+            write!(f, "Lsn(INVALID)")
+        } else {
+            write!(f, "Lsn({})", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lsn_invalid() {
+        assert!(!Lsn::INVALID.is_valid());
+        assert!(Lsn::ZERO.is_valid());
+        assert_eq!(Lsn::default(), Lsn::ZERO);
+    }
+
+    #[test]
+    fn test_lsn_ordering() {
+        assert!(Lsn::ZERO < Lsn::new(1));
+        assert!(Lsn::new(5) > Lsn::new(3));
+        assert!(Lsn::new(u64::MAX - 1) < Lsn::INVALID);
+    }
+
+    #[test]
+    fn test_lsn_display() {
+        assert_eq!(format!("{}", Lsn::new(42)), "Lsn(42)");
+        assert_eq!(format!("{}", Lsn::INVALID), "Lsn(INVALID)");
+    }
+}
@@ -3,13 +3,18 @@
 //! This module contains fundamental primitives used throughout the codebase:
 //! - Configuration constants
 //! - Error types
-//! - Identifiers (PageId, FrameId)
+//! - Identifiers (PageId, FrameId, Lsn, RecordId)
 
 pub mod config;
 pub mod error;
 mod frame_id;
+mod lsn;
 mod page_id;
+mod record_id;
+pub(crate) mod trace;
 
 pub use error::{Error, Result};
 pub use frame_id::FrameId;
-pub use page_id::PageId;
\ No newline at end of file
+pub use lsn::Lsn;
+pub use page_id::PageId;
+pub use record_id::RecordId;
@@ -18,6 +18,8 @@ use std::fmt;
 /// assert_eq!(page_id.0, 42);
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct PageId(pub u32);
 
 impl PageId {
@@ -77,4 +79,15 @@ mod tests {
         assert_eq!(format!("{}", PageId::new(42)), "Page(42)");
         assert_eq!(format!("{}", PageId::INVALID), "Page(INVALID)");
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_page_id_serde_roundtrip() {
+        let json = serde_json::to_string(&PageId::new(42)).unwrap();
+        assert_eq!(json, "42");
+        assert_eq!(
+            serde_json::from_str::<PageId>(&json).unwrap(),
+            PageId::new(42)
+        );
+    }
+}
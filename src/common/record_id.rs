@@ -0,0 +1,104 @@
+//! Record identifier type.
+
+use std::fmt;
+
+use super::PageId;
+
+/// Identifies a record: the page holding it and its slot on that page.
+///
+/// Records order by page, then slot, which is their physical order in a
+/// table heap.
+///
+/// # Example
+/// ```
+/// use interchangedb::{PageId, RecordId};
+///
+/// let rid = RecordId::new(PageId::new(3), 7);
+/// assert!(rid.is_valid());
+/// assert_eq!(rid.to_string(), "Record(3:7)");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordId {
+    /// Page holding the record.
+    pub page_id: PageId,
+    /// Slot of the record within the page.
+    pub slot: u16,
+}
+
+impl RecordId {
+    /// Invalid/sentinel record ID.
+    ///
+    /// Used to represent "no record" or uninitialized state.
+    pub const INVALID: RecordId = RecordId {
+        page_id: PageId::INVALID,
+        slot: u16::MAX,
+    };
+
+    /// Create a new RecordId.
+    #[inline]
+    pub fn new(page_id: PageId, slot: u16) -> Self {
+        RecordId { page_id, slot }
+    }
+
+    /// Check if this record ID is valid (on a valid page).
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.page_id.is_valid()
+    }
+}
+
+impl fmt::Display for RecordId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_valid() {
+            write!(f, "Record(INVALID)")
+        } else {
+            write!(f, "Record({}:{})", self.page_id.0, self.slot)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_id_new() {
+        let rid = RecordId::new(PageId::new(4), 2);
+        assert_eq!(rid.page_id, PageId::new(4));
+        assert_eq!(rid.slot, 2);
+        assert!(rid.is_valid());
+    }
+
+    #[test]
+    fn test_record_id_invalid() {
+        assert!(!RecordId::INVALID.is_valid());
+        assert!(!RecordId::new(PageId::INVALID, 0).is_valid());
+    }
+
+    #[test]
+    fn test_record_id_ordering() {
+        let rid = |page, slot| RecordId::new(PageId::new(page), slot);
+        assert!(rid(1, 9) < rid(2, 0));
+        assert!(rid(2, 1) < rid(2, 3));
+        assert!(rid(u32::MAX - 1, u16::MAX) < RecordId::INVALID);
+    }
+
+    #[test]
+    fn test_record_id_display() {
+        assert_eq!(
+            format!("{}", RecordId::new(PageId::new(42), 7)),
+            "Record(42:7)"
+        );
+        assert_eq!(format!("{}", RecordId::INVALID), "Record(INVALID)");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_record_id_serde_roundtrip() {
+        let rid = RecordId::new(PageId::new(42), 7);
+        let json = serde_json::to_string(&rid).unwrap();
+        assert_eq!(json, r#"{"page_id":42,"slot":7}"#);
+        assert_eq!(serde_json::from_str::<RecordId>(&json).unwrap(), rid);
+    }
+}
@@ -0,0 +1,41 @@
+//! Optional instrumentation through the `tracing` crate.
+//!
+//! With the `tracing` feature enabled, [`trace_event!`] emits a
+//! `tracing` event; without it the macro expands to nothing, so the
+//! instrumented paths cost nothing in default builds.
+//!
+//! # Events
+//! | Name          | Level | Fields                         | Emitted by          |
+//! |---------------|-------|--------------------------------|---------------------|
+//! | `cache_hit`   | TRACE | `page_id`, `frame_id`          | `BufferPoolManager` |
+//! | `cache_miss`  | TRACE | `page_id`, `frame_id`          | `BufferPoolManager` |
+//! | `eviction`    | DEBUG | `page_id`, `frame_id`          | `BufferPoolManager` |
+//! | `flush`       | DEBUG | `page_id`, `frame_id`          | `BufferPoolManager` |
+//! | `disk_read`   | TRACE | `page_id`                      | `DiskManager`       |
+//! | `disk_write`  | TRACE | `page_id`                      | `DiskManager`       |
+//! | `unpin_error` | ERROR | `page_id`, `frame_id`, `error` | Page guards on drop |
+//!
+//! `flush` events from [`PageWriteGuard::flush`] carry only `page_id`.
+//!
+//! [`PageWriteGuard::flush`]: crate::buffer::PageWriteGuard::flush
+
+/// Emit a named `tracing` event at the given level, e.g.
+/// `trace_event!(TRACE, "cache_hit", page_id = page_id.0)`.
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($level:ident, $name:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        tracing::event!(name: $name, tracing::Level::$level $(, $key = $value)*)
+    };
+}
+
+/// Disabled: the field expressions are type-checked but never evaluated.
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($level:ident, $name:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        if false {
+            $(let _ = &$value;)*
+        }
+    };
+}
+
+pub(crate) use trace_event;
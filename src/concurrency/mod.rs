@@ -4,5 +4,8 @@
 //! - Transaction manager
 //! - MVCC (Multi-Version Concurrency Control)
 //! - Snapshot isolation
+//! - First-committer-wins: taking a write guard on a slot modified by
+//!   another transaction since this one's snapshot fails with
+//!   `Error::WriteConflict`, so the caller aborts and retries
 
 // TODO: Week 10-11 - Implement MVCC
@@ -4,5 +4,26 @@
 //! - SQL parsing
 //! - Query planning
 //! - Executor operators
+//! - `TableHeap` iterator as a std `Iterator` (record-count `size_hint`,
+//!   `DoubleEndedIterator` for reverse scans over prev-page links)
+//! - `executors`: Volcano-style `Executor` trait (`next() -> Option<Tuple>`)
+//!   with `SeqScanExecutor`, `FilterExecutor`, `ProjectionExecutor`
+//! - Predicate pushdown into `SeqScanExecutor`, skipping pages ruled out by
+//!   a per-page min/max zone map (`storage::zone_map::ZoneMap`)
+//! - `AggregationExecutor` (COUNT/SUM/MIN/MAX, GROUP BY) with a hash table
+//!   that spills to buffer pool pages past a memory budget
+//! - `IndexScanExecutor`: `BPlusTree::range` → `RecordId`s → `TableHeap` fetch
+//! - Catalog: a `TableHeap` of serialized table descriptors (name, schema,
+//!   first data page, index root) rooted at a well-known metadata page, with
+//!   `Database::create_table(name, schema)` / `open_table(name)`
+//! - `Database::stats()` returning a `DatabaseStats` snapshot that bundles
+//!   the buffer pool's `StatsSnapshot` with WAL `LogStats` and per-index
+//!   metrics (e.g. B-tree depth, LSM run count), with `Display`, serde JSON,
+//!   and Prometheus text output
+//! - `Table`: a `TableHeap` plus secondary `BPlusTree` indexes (key column →
+//!   `RecordId`), kept in step by `insert(tuple)` and `delete(rid)`
+//! - `Database::vacuum()`: flush the pool, `DiskManager::compact`, then
+//!   rebuild the free-space map and rewrite catalog/index root page IDs
+//!   from the compaction mapping
 
 // TODO: Week 12-14 - Implement query layer
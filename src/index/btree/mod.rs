@@ -5,5 +5,15 @@
 //! - Search, insert, delete operations
 //! - Iterator for range scans
 //! - Integration with buffer pool
+//! - Bottom-up bulk load from sorted input (`BPlusTree::bulk_load`) with a
+//!   target leaf fill factor
+//! - `unique` option on `BPlusTree::new`: reject duplicate keys in unique
+//!   mode, store multiple values per key otherwise
+//! - Variable-length byte-slice keys: length-prefixed entries, splits by
+//!   byte size rather than entry count
+//! - `BPlusTree` generic over a [`KeyComparator`](super::KeyComparator)
+//!   supplied at construction and used for all search/insert decisions
+//! - Implement [`Index`](super::Index) for `BPlusTree` (insert/get/delete and
+//!   `range` over the leaf chain) so it is interchangeable with the LSM tree
 
 // TODO: Week 5-7 - Implement B-tree
@@ -0,0 +1,110 @@
+//! Key ordering for index structures.
+//!
+//! Indexes order keys through a [`KeyComparator`] rather than the key type's
+//! own `Ord`, so the same structure can serve ascending, descending, or
+//! multi-column orderings.
+
+use std::cmp::Ordering;
+
+/// Defines the ordering an index uses for its keys.
+///
+/// # Example
+/// ```
+/// use interchangedb::index::{Descending, KeyComparator, Natural};
+/// use std::cmp::Ordering;
+///
+/// let desc = Descending(Natural);
+/// assert_eq!(desc.compare(&1u64, &2u64), Ordering::Greater);
+/// ```
+pub trait KeyComparator<K: ?Sized> {
+    /// Compare two keys.
+    fn compare(&self, a: &K, b: &K) -> Ordering;
+}
+
+/// Orders keys by their own `Ord` implementation.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Natural;
+
+impl<K: Ord + ?Sized> KeyComparator<K> for Natural {
+    #[inline]
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Reverses the ordering of the wrapped comparator.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Descending<C>(pub C);
+
+impl<K: ?Sized, C: KeyComparator<K>> KeyComparator<K> for Descending<C> {
+    #[inline]
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        self.0.compare(b, a)
+    }
+}
+
+/// Orders byte-string keys ignoring ASCII case.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AsciiCaseInsensitive;
+
+impl KeyComparator<[u8]> for AsciiCaseInsensitive {
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        let a = a.iter().map(u8::to_ascii_lowercase);
+        let b = b.iter().map(u8::to_ascii_lowercase);
+        a.cmp(b)
+    }
+}
+
+/// Composite (two-column) keys: compare by the first column, then the second.
+impl<A, B, CA, CB> KeyComparator<(A, B)> for (CA, CB)
+where
+    CA: KeyComparator<A>,
+    CB: KeyComparator<B>,
+{
+    fn compare(&self, a: &(A, B), b: &(A, B)) -> Ordering {
+        self.0
+            .compare(&a.0, &b.0)
+            .then_with(|| self.1.compare(&a.1, &b.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_by<K: Clone, C: KeyComparator<K>>(keys: &[K], cmp: &C) -> Vec<K> {
+        let mut keys = keys.to_vec();
+        keys.sort_by(|a, b| cmp.compare(a, b));
+        keys
+    }
+
+    #[test]
+    fn test_natural_order() {
+        assert_eq!(sorted_by(&[3u64, 1, 2], &Natural), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_descending_order() {
+        let cmp = Descending(Natural);
+        assert_eq!(sorted_by(&[3u64, 1, 5, 2], &cmp), vec![5, 3, 2, 1]);
+        assert_eq!(cmp.compare(&7u64, &7u64), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_case_insensitive_order() {
+        let cmp = AsciiCaseInsensitive;
+        assert_eq!(cmp.compare(b"Apple".as_slice(), b"apple"), Ordering::Equal);
+        assert_eq!(cmp.compare(b"apple".as_slice(), b"BANANA"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_composite_order() {
+        // ORDER BY dept ASC, salary DESC
+        let cmp = (Natural, Descending(Natural));
+        let rows = [(2u32, 100u64), (1, 50), (2, 300), (1, 70)];
+        assert_eq!(
+            sorted_by(&rows, &cmp),
+            vec![(1, 70), (1, 50), (2, 300), (2, 100)]
+        );
+    }
+}
@@ -0,0 +1,663 @@
+//! The LSM tree: memtable, manifest, and sorted runs.
+//!
+//! # Manifest Page Layout
+//! ```text
+//! Offset  Size  Field
+//! ------  ----  -----
+//! 0       13    PageHeader (page_type = LsmManifest)
+//! 13      4     run count
+//! 17      8×n   runs, oldest first: first page ID u32, entry count u32
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::buffer::BufferPoolManager;
+use crate::common::config::PAGE_SIZE;
+use crate::common::{Error, PageId, Result};
+use crate::index::Index;
+use crate::storage::page::{Page, PageHeader, PageType};
+
+use super::run::SortedRun;
+
+const OFFSET_RUN_COUNT: usize = PageHeader::SIZE;
+const OFFSET_RUNS: usize = OFFSET_RUN_COUNT + 4;
+const RUN_ENTRY_SIZE: usize = 8;
+
+/// Maximum number of runs the manifest page can describe.
+const MAX_RUNS: usize = (PAGE_SIZE - OFFSET_RUNS) / RUN_ENTRY_SIZE;
+
+/// Default number of memtable entries before an automatic flush.
+const DEFAULT_MEMTABLE_CAPACITY: usize = 1024;
+
+/// Default run count above which a flush triggers compaction.
+const DEFAULT_COMPACTION_THRESHOLD: usize = 4;
+
+/// A log-structured merge tree mapping `u64` keys to `u64` values.
+///
+/// Writes go to an in-memory memtable; when it reaches capacity it is
+/// flushed to an immutable [`SortedRun`] in buffer pool pages and recorded in
+/// the manifest page. The manifest page ID is all that's needed to
+/// [`open`](Self::open) the tree again.
+///
+/// # Compaction
+/// Every run is a place a lookup may have to search, so runs are merged
+/// size-tiered style: once a flush leaves more than the compaction threshold
+/// of runs, all of them are [`compact`](Self::compact)ed into one.
+///
+/// # Durability
+/// There is no WAL yet: memtable contents not yet [`flush`](Self::flush)ed
+/// are lost if the tree is dropped.
+///
+/// # Example
+/// ```ignore
+/// let mut lsm = Lsm::create(&bpm)?;
+/// lsm.put(1, 100)?;
+/// lsm.flush()?;
+/// let manifest = lsm.manifest_page_id();
+/// // ... later, after reopening the database ...
+/// let lsm = Lsm::open(&bpm, manifest)?;
+/// assert_eq!(lsm.get(1)?, Some(100));
+/// ```
+pub struct Lsm<'a> {
+    bpm: &'a BufferPoolManager,
+    /// Page listing the runs.
+    manifest: PageId,
+    /// Unflushed writes; `None` is a tombstone.
+    memtable: BTreeMap<u64, Option<u64>>,
+    /// Flushed runs, oldest first.
+    runs: Vec<SortedRun>,
+    /// Memtable size that triggers an automatic flush.
+    memtable_capacity: usize,
+    /// Run count above which a flush triggers compaction.
+    compaction_threshold: usize,
+}
+
+impl<'a> Lsm<'a> {
+    /// Create an empty tree with a new manifest page.
+    pub fn create(bpm: &'a BufferPoolManager) -> Result<Self> {
+        let manifest = {
+            let mut guard = bpm.new_page()?;
+            encode_manifest(&mut guard, &[]);
+            guard.page_id()
+        };
+
+        Ok(Self {
+            bpm,
+            manifest,
+            memtable: BTreeMap::new(),
+            runs: Vec::new(),
+            memtable_capacity: DEFAULT_MEMTABLE_CAPACITY,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
+        })
+    }
+
+    /// Open an existing tree from its manifest page.
+    ///
+    /// # Errors
+    /// - `Error::InvalidPageHeader` if the page is not an LSM manifest
+    /// - Any error from reading the manifest or run pages
+    pub fn open(bpm: &'a BufferPoolManager, manifest: PageId) -> Result<Self> {
+        let descriptors = {
+            let guard = bpm.fetch_page_read(manifest)?;
+            if guard.header().page_type != PageType::LsmManifest {
+                return Err(Error::InvalidPageHeader(format!(
+                    "{} is not an LSM manifest page",
+                    manifest
+                )));
+            }
+            decode_manifest(&guard)
+        };
+
+        let runs = descriptors
+            .into_iter()
+            .map(|(first_page, entry_count)| SortedRun::load(bpm, first_page, entry_count))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            bpm,
+            manifest,
+            memtable: BTreeMap::new(),
+            runs,
+            memtable_capacity: DEFAULT_MEMTABLE_CAPACITY,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
+        })
+    }
+
+    /// Set the memtable size that triggers an automatic flush.
+    pub fn with_memtable_capacity(mut self, entries: usize) -> Self {
+        self.memtable_capacity = entries.max(1);
+        self
+    }
+
+    /// Set the run count above which a flush triggers compaction.
+    pub fn with_compaction_threshold(mut self, runs: usize) -> Self {
+        self.compaction_threshold = runs.max(1);
+        self
+    }
+
+    /// The manifest page ID, needed to reopen the tree.
+    pub fn manifest_page_id(&self) -> PageId {
+        self.manifest
+    }
+
+    /// Insert or overwrite a key.
+    pub fn put(&mut self, key: u64, value: u64) -> Result<()> {
+        self.write(key, Some(value))
+    }
+
+    /// Delete a key by writing a tombstone.
+    pub fn delete(&mut self, key: u64) -> Result<()> {
+        self.write(key, None)
+    }
+
+    /// Look up a key: memtable first, then runs newest to oldest.
+    pub fn get(&self, key: u64) -> Result<Option<u64>> {
+        if let Some(&value) = self.memtable.get(&key) {
+            return Ok(value);
+        }
+        for run in self.runs.iter().rev() {
+            if let Some(value) = run.get(self.bpm, key)? {
+                return Ok(value);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Collect live entries with `start <= key < end`, in key order.
+    ///
+    /// Runs are merged oldest to newest and then the memtable, so the newest
+    /// version of each key wins and tombstoned keys are omitted.
+    pub fn range(&self, start: u64, end: u64) -> Result<Vec<(u64, u64)>> {
+        let mut merged = BTreeMap::new();
+        for run in &self.runs {
+            merged.extend(run.scan_range(self.bpm, start, end)?);
+        }
+        if start < end {
+            merged.extend(self.memtable.range(start..end).map(|(&k, &v)| (k, v)));
+        }
+
+        Ok(merged
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|v| (key, v)))
+            .collect())
+    }
+
+    /// Write the memtable to a new sorted run and record it in the manifest.
+    ///
+    /// No-op if the memtable is empty.
+    ///
+    /// # Errors
+    /// - `Error::Internal` if the manifest has no room for another run
+    /// - Any error from allocating or writing pages
+    pub fn flush(&mut self) -> Result<()> {
+        if self.memtable.is_empty() {
+            return Ok(());
+        }
+        if self.runs.len() >= MAX_RUNS {
+            return Err(Error::Internal("LSM manifest is full".to_string()));
+        }
+
+        let entries: Vec<(u64, Option<u64>)> =
+            self.memtable.iter().map(|(&k, &v)| (k, v)).collect();
+        let run = SortedRun::write(self.bpm, &entries)?;
+        self.runs.push(run);
+        if let Err(e) = self.write_manifest(&self.runs) {
+            // The manifest never recorded the run, so drop it and give its
+            // pages back rather than leak them.
+            if let Some(run) = self.runs.pop() {
+                let _ = run.free(self.bpm);
+            }
+            return Err(e);
+        }
+
+        self.memtable.clear();
+
+        if self.runs.len() > self.compaction_threshold {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Merge all runs into a single run.
+    ///
+    /// Newer entries shadow older ones, and since the result is the oldest
+    /// (and only) run, tombstones are dropped entirely. The manifest is
+    /// updated before the old runs' pages are freed; if that fails, the
+    /// tree keeps its old runs and the merged run is freed. No-op with
+    /// fewer than two runs.
+    ///
+    /// # Errors
+    /// Any error from reading, writing, or freeing pages.
+    pub fn compact(&mut self) -> Result<()> {
+        if self.runs.len() < 2 {
+            return Ok(());
+        }
+
+        let mut merged = BTreeMap::new();
+        for run in &self.runs {
+            merged.extend(run.scan(self.bpm)?);
+        }
+        let live: Vec<(u64, Option<u64>)> = merged
+            .into_iter()
+            .filter(|(_, value)| value.is_some())
+            .collect();
+
+        let new_runs = if live.is_empty() {
+            Vec::new()
+        } else {
+            vec![SortedRun::write(self.bpm, &live)?]
+        };
+        if let Err(e) = self.write_manifest(&new_runs) {
+            for run in new_runs {
+                let _ = run.free(self.bpm);
+            }
+            return Err(e);
+        }
+        let old_runs = std::mem::replace(&mut self.runs, new_runs);
+
+        for run in old_runs {
+            run.free(self.bpm)?;
+        }
+        Ok(())
+    }
+
+    /// Number of flushed runs.
+    pub fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Number of pages the tree occupies: the manifest plus every run page.
+    pub fn page_count(&self) -> usize {
+        1 + self
+            .runs
+            .iter()
+            .map(|run| run.page_ids().count())
+            .sum::<usize>()
+    }
+
+    /// Number of unflushed memtable entries (including tombstones).
+    pub fn memtable_len(&self) -> usize {
+        self.memtable.len()
+    }
+
+    fn write(&mut self, key: u64, value: Option<u64>) -> Result<()> {
+        self.memtable.insert(key, value);
+        if self.memtable.len() >= self.memtable_capacity {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn write_manifest(&self, runs: &[SortedRun]) -> Result<()> {
+        let descriptors: Vec<(PageId, u32)> = runs
+            .iter()
+            .map(|run| (run.first_page(), run.entry_count()))
+            .collect();
+        let mut guard = self.bpm.fetch_page_write(self.manifest)?;
+        encode_manifest(&mut guard, &descriptors);
+        Ok(())
+    }
+}
+
+impl Index for Lsm<'_> {
+    fn insert(&mut self, key: u64, value: u64) -> Result<()> {
+        self.put(key, value)
+    }
+
+    fn get(&self, key: u64) -> Result<Option<u64>> {
+        Lsm::get(self, key)
+    }
+
+    fn delete(&mut self, key: u64) -> Result<bool> {
+        let existed = Lsm::get(self, key)?.is_some();
+        if existed {
+            Lsm::delete(self, key)?;
+        }
+        Ok(existed)
+    }
+
+    fn range(&self, start: u64, end: u64) -> Result<Box<dyn Iterator<Item = (u64, u64)> + '_>> {
+        Ok(Box::new(Lsm::range(self, start, end)?.into_iter()))
+    }
+}
+
+fn encode_manifest(page: &mut Page, runs: &[(PageId, u32)]) {
+    page.reset();
+    page.set_header(&PageHeader::new(PageType::LsmManifest));
+
+    let data = page.as_mut_slice();
+    data[OFFSET_RUN_COUNT..OFFSET_RUN_COUNT + 4]
+        .copy_from_slice(&(runs.len() as u32).to_le_bytes());
+    for (i, &(first_page, entry_count)) in runs.iter().enumerate() {
+        let off = OFFSET_RUNS + i * RUN_ENTRY_SIZE;
+        data[off..off + 4].copy_from_slice(&first_page.0.to_le_bytes());
+        data[off + 4..off + 8].copy_from_slice(&entry_count.to_le_bytes());
+    }
+
+    page.update_checksum();
+}
+
+fn decode_manifest(page: &Page) -> Vec<(PageId, u32)> {
+    let data = page.as_slice();
+    let count = u32::from_le_bytes(
+        data[OFFSET_RUN_COUNT..OFFSET_RUN_COUNT + 4]
+            .try_into()
+            .unwrap(),
+    );
+    let count = (count as usize).min(MAX_RUNS);
+
+    (0..count)
+        .map(|i| {
+            let off = OFFSET_RUNS + i * RUN_ENTRY_SIZE;
+            let first_page = u32::from_le_bytes(data[off..off + 4].try_into().unwrap());
+            let entry_count = u32::from_le_bytes(data[off + 4..off + 8].try_into().unwrap());
+            (PageId::new(first_page), entry_count)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::DiskManager;
+    use tempfile::tempdir;
+
+    fn create_test_bpm(pool_size: usize) -> (BufferPoolManager, tempfile::TempDir) {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let dm = DiskManager::create(&path).unwrap();
+        (BufferPoolManager::new(pool_size, dm), dir)
+    }
+
+    #[test]
+    fn test_put_get_delete() {
+        let (bpm, _dir) = create_test_bpm(16);
+        let mut lsm = Lsm::create(&bpm).unwrap();
+
+        lsm.put(1, 10).unwrap();
+        lsm.put(2, 20).unwrap();
+        assert_eq!(lsm.get(1).unwrap(), Some(10));
+        assert_eq!(lsm.get(3).unwrap(), None);
+
+        lsm.put(1, 11).unwrap();
+        assert_eq!(lsm.get(1).unwrap(), Some(11));
+
+        lsm.delete(2).unwrap();
+        assert_eq!(lsm.get(2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_tombstone_shadows_flushed_value() {
+        let (bpm, _dir) = create_test_bpm(16);
+        let mut lsm = Lsm::create(&bpm).unwrap();
+
+        lsm.put(7, 70).unwrap();
+        lsm.flush().unwrap();
+        lsm.delete(7).unwrap();
+        assert_eq!(lsm.get(7).unwrap(), None);
+
+        // The tombstone survives its own flush
+        lsm.flush().unwrap();
+        assert_eq!(lsm.run_count(), 2);
+        assert_eq!(lsm.get(7).unwrap(), None);
+
+        // A newer put wins over the tombstone
+        lsm.put(7, 71).unwrap();
+        lsm.flush().unwrap();
+        assert_eq!(lsm.get(7).unwrap(), Some(71));
+    }
+
+    #[test]
+    fn test_automatic_flush_spans_pages() {
+        let (bpm, _dir) = create_test_bpm(16);
+        let mut lsm = Lsm::create(&bpm).unwrap().with_memtable_capacity(1000);
+
+        for key in 0..1000u64 {
+            lsm.put(key * 2, key).unwrap();
+        }
+        assert_eq!(lsm.run_count(), 1);
+        assert_eq!(lsm.memtable_len(), 0);
+
+        for key in 0..1000u64 {
+            assert_eq!(lsm.get(key * 2).unwrap(), Some(key));
+            assert_eq!(lsm.get(key * 2 + 1).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_flushed_run_readable_after_reopen() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let manifest = {
+            let bpm = BufferPoolManager::new(8, DiskManager::create(&path).unwrap());
+            let mut lsm = Lsm::create(&bpm).unwrap();
+            for key in 0..500u64 {
+                lsm.put(key, key + 1000).unwrap();
+            }
+            lsm.delete(42).unwrap();
+            lsm.flush().unwrap();
+            bpm.flush_all_pages().unwrap();
+            lsm.manifest_page_id()
+        };
+
+        let bpm = BufferPoolManager::new(8, DiskManager::open(&path).unwrap());
+        let lsm = Lsm::open(&bpm, manifest).unwrap();
+        assert_eq!(lsm.run_count(), 1);
+        assert_eq!(lsm.get(0).unwrap(), Some(1000));
+        assert_eq!(lsm.get(499).unwrap(), Some(1499));
+        assert_eq!(lsm.get(42).unwrap(), None);
+    }
+
+    #[test]
+    fn test_compaction_merges_overlapping_runs() {
+        let (bpm, _dir) = create_test_bpm(16);
+        let mut lsm = Lsm::create(&bpm).unwrap().with_compaction_threshold(100);
+
+        // Four flushes over overlapping key ranges, each overwriting the last
+        for round in 0..4u64 {
+            for key in (round * 100)..(round * 100 + 400) {
+                lsm.put(key, key * 10 + round).unwrap();
+            }
+            lsm.flush().unwrap();
+        }
+        for key in (0..700).step_by(7) {
+            lsm.delete(key).unwrap();
+        }
+        lsm.flush().unwrap();
+        assert_eq!(lsm.run_count(), 5);
+        let pages_before = lsm.page_count();
+
+        lsm.compact().unwrap();
+        assert_eq!(lsm.run_count(), 1);
+        assert!(lsm.page_count() < pages_before);
+
+        for key in 0..700u64 {
+            let expected = if key % 7 == 0 {
+                None
+            } else {
+                let latest_round = (key / 100).min(3);
+                Some(key * 10 + latest_round)
+            };
+            assert_eq!(lsm.get(key).unwrap(), expected, "key {}", key);
+        }
+
+        // Old run pages were freed, and the compacted tree survives a reopen
+        let manifest = lsm.manifest_page_id();
+        drop(lsm);
+        let lsm = Lsm::open(&bpm, manifest).unwrap();
+        assert_eq!(lsm.run_count(), 1);
+        assert_eq!(lsm.get(1).unwrap(), Some(10));
+        assert_eq!(lsm.get(7).unwrap(), None);
+    }
+
+    #[test]
+    fn test_compaction_frees_old_pages() {
+        let (bpm, _dir) = create_test_bpm(16);
+        let mut lsm = Lsm::create(&bpm).unwrap().with_compaction_threshold(100);
+
+        lsm.put(1, 10).unwrap();
+        lsm.flush().unwrap();
+        let old_page = lsm.runs[0].first_page();
+        lsm.delete(1).unwrap();
+        lsm.flush().unwrap();
+
+        lsm.compact().unwrap();
+        assert_eq!(lsm.run_count(), 0);
+        assert_eq!(lsm.page_count(), 1);
+        assert_eq!(lsm.get(1).unwrap(), None);
+
+        let guard = bpm.fetch_page_read(old_page).unwrap();
+        assert_eq!(guard.header().page_type, PageType::Free);
+        assert!(guard.verify_checksum());
+    }
+
+    #[test]
+    fn test_failed_manifest_write_keeps_runs() {
+        let (bpm, _dir) = create_test_bpm(16);
+        let mut lsm = Lsm::create(&bpm).unwrap().with_compaction_threshold(100);
+        for key in 1..=2u64 {
+            lsm.put(key, key * 10).unwrap();
+            lsm.flush().unwrap();
+        }
+        let manifest = lsm.manifest_page_id();
+
+        // Holding the only pin allowed on the manifest makes its write fail
+        bpm.set_max_pin_count(Some(1));
+        let held = bpm.fetch_page_read(manifest).unwrap();
+        // Each run is one page, and the merged run takes the next one
+        let merged_page = PageId::new(lsm.runs[1].first_page().0 + 1);
+        assert!(matches!(lsm.compact(), Err(Error::PinLimitExceeded(_))));
+        lsm.put(3, 30).unwrap();
+        assert!(matches!(lsm.flush(), Err(Error::PinLimitExceeded(_))));
+        drop(held);
+
+        // Memory still matches the manifest, and the merged run was freed
+        assert_eq!(lsm.run_count(), 2);
+        assert_eq!(lsm.memtable_len(), 1);
+        let reopened = Lsm::open(&bpm, manifest).unwrap();
+        assert_eq!(reopened.run_count(), 2);
+        assert_eq!(reopened.get(1).unwrap(), Some(10));
+        assert_eq!(reopened.get(2).unwrap(), Some(20));
+        let guard = bpm.fetch_page_read(merged_page).unwrap();
+        assert_eq!(guard.header().page_type, PageType::Free);
+    }
+
+    #[test]
+    fn test_flush_triggers_compaction() {
+        let (bpm, _dir) = create_test_bpm(16);
+        let mut lsm = Lsm::create(&bpm)
+            .unwrap()
+            .with_memtable_capacity(10)
+            .with_compaction_threshold(3);
+
+        for key in 0..40u64 {
+            lsm.put(key, key).unwrap();
+            assert!(lsm.run_count() <= 3);
+        }
+        for key in 0..40u64 {
+            assert_eq!(lsm.get(key).unwrap(), Some(key));
+        }
+    }
+
+    #[test]
+    fn test_open_rejects_non_manifest() {
+        let (bpm, _dir) = create_test_bpm(4);
+        let pid = bpm.new_page().unwrap().page_id();
+
+        assert!(matches!(
+            Lsm::open(&bpm, pid),
+            Err(Error::InvalidPageHeader(_))
+        ));
+    }
+
+    #[test]
+    fn test_range_merges_runs_and_memtable() {
+        let (bpm, _dir) = create_test_bpm(16);
+        let mut lsm = Lsm::create(&bpm).unwrap().with_compaction_threshold(100);
+
+        for key in 0..600u64 {
+            lsm.put(key, key).unwrap();
+        }
+        lsm.flush().unwrap();
+        for key in (0..600u64).step_by(2) {
+            lsm.put(key, key + 1000).unwrap();
+        }
+        lsm.flush().unwrap();
+        lsm.delete(301).unwrap();
+        lsm.put(302, 9).unwrap();
+
+        let got = lsm.range(298, 305).unwrap();
+        assert_eq!(
+            got,
+            vec![
+                (298, 1298),
+                (299, 299),
+                (300, 1300),
+                (302, 9),
+                (303, 303),
+                (304, 1304)
+            ]
+        );
+
+        assert_eq!(lsm.range(0, 600).unwrap().len(), 599);
+        assert!(lsm.range(10, 10).unwrap().is_empty());
+        assert!(lsm.range(700, 800).unwrap().is_empty());
+    }
+
+    /// Run a fixed sequence against any `Index` and return what it observed.
+    ///
+    /// Every index implementation must produce the same output.
+    fn exercise(index: &mut dyn Index) -> Vec<String> {
+        let mut observed = Vec::new();
+        for key in [5u64, 1, 9, 3, 7] {
+            index.insert(key, key * 10).unwrap();
+        }
+        index.insert(3, 33).unwrap();
+        observed.push(format!("{:?}", index.get(3).unwrap()));
+        observed.push(format!("{:?}", index.get(4).unwrap()));
+        observed.push(format!(
+            "{:?}",
+            index.range(2, 8).unwrap().collect::<Vec<_>>()
+        ));
+        observed.push(format!("{:?}", index.delete(5).unwrap()));
+        observed.push(format!("{:?}", index.delete(5).unwrap()));
+        observed.push(format!(
+            "{:?}",
+            index.range(0, u64::MAX).unwrap().collect::<Vec<_>>()
+        ));
+        observed
+    }
+
+    #[test]
+    fn test_index_trait_sequence() {
+        let (bpm, _dir) = create_test_bpm(8);
+
+        // Small memtable so the sequence spans flushed runs as well
+        let mut lsm = Lsm::create(&bpm).unwrap().with_memtable_capacity(2);
+        assert_eq!(
+            exercise(&mut lsm),
+            vec![
+                "Some(33)",
+                "None",
+                "[(3, 33), (5, 50), (7, 70)]",
+                "true",
+                "false",
+                "[(1, 10), (3, 33), (7, 70), (9, 90)]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index_trait() {
+        let (bpm, _dir) = create_test_bpm(8);
+        let mut lsm = Lsm::create(&bpm).unwrap();
+        let index: &mut dyn Index = &mut lsm;
+
+        index.insert(5, 50).unwrap();
+        assert_eq!(index.get(5).unwrap(), Some(50));
+        assert!(index.delete(5).unwrap());
+        assert!(!index.delete(5).unwrap());
+        assert_eq!(index.get(5).unwrap(), None);
+    }
+}
@@ -0,0 +1,24 @@
+//! Log-structured merge (LSM) tree index.
+//!
+//! The compile-time alternative to the B-tree from the architecture diagram.
+//!
+//! # Structure
+//! ```text
+//!   put/delete ──▶ memtable (BTreeMap, in memory)
+//!                      │ flush when full
+//!                      ▼
+//!   manifest page ──▶ [run 0] [run 1] ... [run N]   (immutable, on disk)
+//!                      oldest               newest
+//! ```
+//! A `get` checks the memtable first, then the runs from newest to oldest.
+//! Deletes are recorded as tombstones that shadow older values.
+//!
+//! - [`Lsm`] - The tree itself
+//! - [`SortedRun`] - An immutable sorted run stored in buffer pool pages
+
+#[allow(clippy::module_inception)]
+mod lsm;
+mod run;
+
+pub use lsm::Lsm;
+pub use run::SortedRun;
@@ -0,0 +1,261 @@
+//! Immutable sorted runs stored in buffer pool pages.
+//!
+//! # Run Page Layout
+//! ```text
+//! Offset  Size  Field
+//! ------  ----  -----
+//! 0       13    PageHeader (page_type = LsmRun)
+//! 13      4     next page ID (PageId::INVALID at the end of the run)
+//! 17      2     entry count
+//! 19      17×n  entries: key u64, value u64, flags u8 (bit 0 = tombstone)
+//! ```
+//! All integers are little-endian.
+
+use crate::buffer::BufferPoolManager;
+use crate::common::config::PAGE_SIZE;
+use crate::common::{Error, PageId, Result};
+use crate::storage::page::{Page, PageHeader, PageType};
+
+const OFFSET_NEXT: usize = PageHeader::SIZE;
+const OFFSET_COUNT: usize = OFFSET_NEXT + 4;
+const OFFSET_ENTRIES: usize = OFFSET_COUNT + 2;
+const ENTRY_SIZE: usize = 17;
+
+/// Maximum number of entries stored in one run page.
+pub(crate) const ENTRIES_PER_PAGE: usize = (PAGE_SIZE - OFFSET_ENTRIES) / ENTRY_SIZE;
+
+const FLAG_TOMBSTONE: u8 = 1;
+
+/// An immutable, sorted sequence of entries spread over linked pages.
+///
+/// Only the page chain and a small fence index (first key of each page) are
+/// kept in memory; entries are read through the buffer pool on lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortedRun {
+    /// First page of the chain.
+    first_page: PageId,
+    /// Total number of entries (including tombstones).
+    entry_count: u32,
+    /// First key of each page, with that page's ID.
+    fences: Vec<(u64, PageId)>,
+}
+
+impl SortedRun {
+    /// Write sorted entries to new pages and return the resulting run.
+    ///
+    /// `entries` must be sorted by key with no duplicates; `None` values are
+    /// tombstones.
+    pub(crate) fn write(bpm: &BufferPoolManager, entries: &[(u64, Option<u64>)]) -> Result<Self> {
+        debug_assert!(entries.windows(2).all(|w| w[0].0 < w[1].0));
+
+        let chunks: Vec<&[(u64, Option<u64>)]> = entries.chunks(ENTRIES_PER_PAGE).collect();
+        let page_ids: Vec<PageId> = (0..chunks.len().max(1))
+            .map(|_| bpm.allocate_page_id())
+            .collect::<Result<_>>()?;
+
+        let mut fences = Vec::with_capacity(chunks.len());
+        for (i, &page_id) in page_ids.iter().enumerate() {
+            let chunk = chunks.get(i).copied().unwrap_or(&[]);
+            let next = page_ids.get(i + 1).copied().unwrap_or(PageId::INVALID);
+
+            let mut guard = bpm.fetch_page_write_no_read(page_id)?;
+            encode_page(&mut guard, chunk, next);
+            if let Some(&(first_key, _)) = chunk.first() {
+                fences.push((first_key, page_id));
+            }
+        }
+
+        Ok(Self {
+            first_page: page_ids[0],
+            entry_count: entries.len() as u32,
+            fences,
+        })
+    }
+
+    /// Load a run's fence index by walking its page chain.
+    pub(crate) fn load(
+        bpm: &BufferPoolManager,
+        first_page: PageId,
+        entry_count: u32,
+    ) -> Result<Self> {
+        let mut fences = Vec::new();
+        let mut page_id = first_page;
+        while page_id.is_valid() {
+            let guard = bpm.fetch_page_read(page_id)?;
+            check_run_page(&guard, page_id)?;
+            if let Some((first_key, _)) = read_entries(&guard).first() {
+                fences.push((*first_key, page_id));
+            }
+            page_id = read_next(&guard);
+        }
+
+        Ok(Self {
+            first_page,
+            entry_count,
+            fences,
+        })
+    }
+
+    /// First page of the run's chain.
+    pub fn first_page(&self) -> PageId {
+        self.first_page
+    }
+
+    /// Number of entries in the run, including tombstones.
+    pub fn entry_count(&self) -> u32 {
+        self.entry_count
+    }
+
+    /// Pages that make up this run, in key order.
+    pub fn page_ids(&self) -> impl Iterator<Item = PageId> + '_ {
+        self.fences.iter().map(|&(_, page_id)| page_id)
+    }
+
+    /// Look up a key in this run.
+    ///
+    /// Returns `None` if the run has no entry for the key, `Some(None)` for a
+    /// tombstone, and `Some(Some(v))` for a live value.
+    pub(crate) fn get(&self, bpm: &BufferPoolManager, key: u64) -> Result<Option<Option<u64>>> {
+        let idx = self.fences.partition_point(|&(first, _)| first <= key);
+        if idx == 0 {
+            return Ok(None);
+        }
+        let page_id = self.fences[idx - 1].1;
+
+        let guard = bpm.fetch_page_read(page_id)?;
+        let entries = read_entries(&guard);
+        Ok(entries
+            .binary_search_by_key(&key, |&(k, _)| k)
+            .ok()
+            .map(|i| entries[i].1))
+    }
+
+    /// Read every entry of the run, in key order.
+    pub(crate) fn scan(&self, bpm: &BufferPoolManager) -> Result<Vec<(u64, Option<u64>)>> {
+        let mut entries = Vec::with_capacity(self.entry_count as usize);
+        for page_id in self.page_ids() {
+            let guard = bpm.fetch_page_read(page_id)?;
+            entries.extend(read_entries(&guard));
+        }
+        Ok(entries)
+    }
+
+    /// Read the entries with `start <= key < end`, in key order.
+    ///
+    /// Only pages whose key span overlaps the range are read.
+    pub(crate) fn scan_range(
+        &self,
+        bpm: &BufferPoolManager,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<(u64, Option<u64>)>> {
+        let mut entries = Vec::new();
+        if start >= end {
+            return Ok(entries);
+        }
+
+        // Begin at the last page whose first key is <= start
+        let first = self
+            .fences
+            .partition_point(|&(first, _)| first <= start)
+            .saturating_sub(1);
+        for &(first_key, page_id) in &self.fences[first..] {
+            if first_key >= end {
+                break;
+            }
+            let guard = bpm.fetch_page_read(page_id)?;
+            entries.extend(
+                read_entries(&guard)
+                    .into_iter()
+                    .filter(|&(key, _)| key >= start && key < end),
+            );
+        }
+        Ok(entries)
+    }
+
+    /// Mark every page of the run as free.
+    ///
+    /// The pages are rewritten with a checksummed `PageType::Free` header,
+    /// the same format [`DiskManager::deallocate_page`] uses, so
+    /// [`DiskManager::compact`] can reclaim them.
+    ///
+    /// [`DiskManager::deallocate_page`]: crate::storage::DiskManager::deallocate_page
+    /// [`DiskManager::compact`]: crate::storage::DiskManager::compact
+    pub(crate) fn free(self, bpm: &BufferPoolManager) -> Result<()> {
+        for page_id in self.page_ids() {
+            let mut guard = bpm.fetch_page_write_no_read(page_id)?;
+            guard.reset();
+            guard.set_header(&PageHeader::new(PageType::Free));
+            guard.update_checksum();
+        }
+        Ok(())
+    }
+}
+
+fn check_run_page(page: &Page, page_id: PageId) -> Result<()> {
+    if page.header().page_type != PageType::LsmRun {
+        return Err(Error::InvalidPageHeader(format!(
+            "{} is not an LSM run page",
+            page_id
+        )));
+    }
+    Ok(())
+}
+
+fn encode_page(page: &mut Page, entries: &[(u64, Option<u64>)], next: PageId) {
+    page.reset();
+    page.set_header(&PageHeader::new(PageType::LsmRun));
+
+    let data = page.as_mut_slice();
+    data[OFFSET_NEXT..OFFSET_NEXT + 4].copy_from_slice(&next.0.to_le_bytes());
+    data[OFFSET_COUNT..OFFSET_COUNT + 2].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+
+    for (i, &(key, value)) in entries.iter().enumerate() {
+        let off = OFFSET_ENTRIES + i * ENTRY_SIZE;
+        data[off..off + 8].copy_from_slice(&key.to_le_bytes());
+        data[off + 8..off + 16].copy_from_slice(&value.unwrap_or(0).to_le_bytes());
+        data[off + 16] = if value.is_none() { FLAG_TOMBSTONE } else { 0 };
+    }
+
+    page.update_checksum();
+}
+
+fn read_next(page: &Page) -> PageId {
+    let data = page.as_slice();
+    PageId::new(u32::from_le_bytes(
+        data[OFFSET_NEXT..OFFSET_NEXT + 4].try_into().unwrap(),
+    ))
+}
+
+fn read_entries(page: &Page) -> Vec<(u64, Option<u64>)> {
+    let data = page.as_slice();
+    let count = u16::from_le_bytes([data[OFFSET_COUNT], data[OFFSET_COUNT + 1]]) as usize;
+    let count = count.min(ENTRIES_PER_PAGE);
+
+    (0..count)
+        .map(|i| {
+            let off = OFFSET_ENTRIES + i * ENTRY_SIZE;
+            let key = u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+            let value = u64::from_le_bytes(data[off + 8..off + 16].try_into().unwrap());
+            let tombstone = data[off + 16] & FLAG_TOMBSTONE != 0;
+            (key, (!tombstone).then_some(value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_roundtrip() {
+        let mut page = Page::new();
+        let entries = vec![(1, Some(10)), (5, None), (9, Some(90))];
+        encode_page(&mut page, &entries, PageId::new(7));
+
+        assert_eq!(page.header().page_type, PageType::LsmRun);
+        assert!(page.verify_checksum());
+        assert_eq!(read_next(&page), PageId::new(7));
+        assert_eq!(read_entries(&page), entries);
+    }
+}
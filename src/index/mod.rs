@@ -2,5 +2,32 @@
 //!
 //! This module will contain index implementations:
 //! - B-tree (primary index structure)
+//! - [`lsm`] - Log-structured merge tree (compile-time alternative)
+//! - [`Index`] - Common interface so the query layer can use either
+//! - [`KeyComparator`] - Pluggable key ordering shared by all indexes
+
+use crate::common::Result;
 
 pub mod btree;
+mod comparator;
+pub mod lsm;
+
+pub use comparator::{AsciiCaseInsensitive, Descending, KeyComparator, Natural};
+
+/// Common interface over index structures mapping `u64` keys to `u64` values.
+///
+/// Programming against `dyn Index` keeps the query layer independent of
+/// whether the index is a B-tree or an LSM tree.
+pub trait Index {
+    /// Insert or overwrite a key.
+    fn insert(&mut self, key: u64, value: u64) -> Result<()>;
+
+    /// Look up a key.
+    fn get(&self, key: u64) -> Result<Option<u64>>;
+
+    /// Delete a key. Returns whether the key was present.
+    fn delete(&mut self, key: u64) -> Result<bool>;
+
+    /// Iterate live entries with `start <= key < end`, in key order.
+    fn range(&self, start: u64, end: u64) -> Result<Box<dyn Iterator<Item = (u64, u64)> + '_>>;
+}
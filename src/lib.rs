@@ -70,8 +70,10 @@ pub mod recovery;
 
 // Re-export commonly used items at crate root for convenience
 pub use common::config::PAGE_SIZE;
-pub use common::{Error, FrameId, PageId, Result};
+pub use common::{Error, FrameId, Lsn, PageId, RecordId, Result};
 
-pub use buffer::{BufferPoolStats, Frame, StatsSnapshot, BufferPoolManager};
+pub use buffer::{
+    AccessLatency, BufferPoolManager, BufferPoolStats, Frame, LatencyPercentiles, StatsSnapshot,
+};
 pub use storage::page::{Page, PageHeader, PageType};
 pub use storage::DiskManager;
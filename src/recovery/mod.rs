@@ -1,9 +1,46 @@
 //! Write-Ahead Logging and crash recovery.
 //!
+//! # Components
+//! - [`encode_record`] / [`LogReader`] - Checksummed log record framing
+//!
 //! # Implementation Plan (Weeks 8-9)
-//! - Log record format
+//! - Log record format (framing done; record types still to come)
 //! - WAL writer/reader
 //! - Crash recovery
 //! - Checkpointing
+//! - Crash-consistency tests: a test-only `FaultyStorage` that fails or
+//!   partially writes after the Nth operation, driving property tests that
+//!   `recover` restores committed and discards uncommitted data
+//! - `LogStats` (`records_appended`, `bytes_appended`, `fsync_count`,
+//!   `group_commit_batches`, average batch size) exposed through
+//!   `LogManager::stats().snapshot()`, mirroring `BufferPoolStats`
+//! - Allocate/free log records, so recovery can rebuild an in-memory page
+//!   free list once one exists. `DiskManager::deallocate_page` writes a
+//!   checksummed `PageType::Free` page with an fsync (through the
+//!   double-write buffer when enabled), so those frees already survive a
+//!   crash. Pages freed through the buffer pool, as LSM runs are, are only
+//!   marked Free in a dirty frame: a crash before the flush leaves them
+//!   unreferenced but not marked Free, and `compact` never reclaims them.
+//!   Logging frees closes that gap
+//! - Free list in checkpoints: once that in-memory free list exists,
+//!   `checkpoint` persists it (in the superblock, spilling to a dedicated
+//!   page) and `open` reloads it, so a clean restart has the free list
+//!   without replaying the WAL; the allocate/free records above still
+//!   rebuild it after a crash, including frees made through the buffer
+//!   pool whose Free pages never reached disk
+//! - `SyncPolicy` for the log (`Always`, `Periodic { every, max_delay }`):
+//!   periodic sync fsyncs after `every` appends or once `max_delay` has
+//!   passed since the last fsync, whichever comes first, so a trickle of
+//!   writes can't stay unsynced indefinitely
+//! - Point-in-time recovery: `recover_to(disk_manager, log_manager,
+//!   target_lsn)` redoes only records up to `target_lsn`, then undoes every
+//!   transaction without a commit record at or before it
+//! - WAL-ordered flushing: `flush_frame`/`flush_all_pages` skip (and count)
+//!   dirty pages whose header LSN is past the durable log LSN, with a
+//!   `force` flag for shutdown that flushes the log first
+
+mod log_record;
+
+pub use log_record::{encode_record, LogReader, RECORD_HEADER_SIZE};
 
 // TODO: Week 8-9 - Implement WAL
@@ -5,72 +5,145 @@
 //! - Allocating new pages
 //! - Managing the database file
 
-use std::fs::{File, OpenOptions};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions, TryLockError};
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::common::config::PAGE_SIZE;
-use crate::common::{PageId, Result};
-use crate::storage::page::Page;
+use crate::common::trace::trace_event;
+use crate::common::{Error, PageId, Result};
+use crate::storage::format::{
+    self, FormatVersion, Superblock, FEATURE_SECTOR_CHECKSUMS, SUPERBLOCK_SIZE,
+};
+use crate::storage::page::{Page, PageHeader, PageType};
 
 /// Manages disk I/O for a single database file.
 ///
 /// # File Layout
-/// The database is stored as a single file with pages laid out sequentially:
+/// The database is stored as a single file: a superblock (see
+/// [`format`](crate::storage::format)) followed by pages laid out
+/// sequentially:
 /// ```text
-/// ┌─────────┬─────────┬─────────┬─────────┬─────────┐
-/// │ Page 0  │ Page 1  │ Page 2  │  ...    │ Page N  │
-/// │ (4KB)   │ (4KB)   │ (4KB)   │         │ (4KB)   │
-/// └─────────┴─────────┴─────────┴─────────┴─────────┘
-/// Offset:  0      4096     8192    ...    N×4096
+/// ┌────────────┬─────────┬─────────┬─────────┬─────────┐
+/// │ Superblock │ Page 0  │ Page 1  │  ...    │ Page N  │
+/// │ (4KB)      │ (4KB)   │ (4KB)   │         │ (4KB)   │
+/// └────────────┴─────────┴─────────┴─────────┴─────────┘
+/// Offset:  0        4096      8192     ...   (N+1)×4096
 /// ```
 ///
-/// Page N is located at file offset `N × PAGE_SIZE`.
+/// Page N is located at file offset `4096 + N × PAGE_SIZE`. Files written
+/// before the superblock existed have none, and keep page N at `N × PAGE_SIZE`.
+/// With a [double-write buffer](Self::enable_double_write), its region sits
+/// between the superblock and page 0.
 ///
 /// # Thread Safety
 /// `DiskManager` is **single-threaded**. The `BufferPoolManager` is responsible
 /// for serializing access to the disk manager.
 ///
+/// # Locking
+/// The database file is exclusively locked (advisory `flock` on Unix,
+/// `LockFileEx` on Windows) for as long as the `DiskManager` is alive, so a
+/// second open of the same file - from this or another process - fails with
+/// `Error::DatabaseLocked` instead of corrupting it.
+///
 /// # Durability
 /// All writes are followed by `fsync()` to ensure durability. This is
 /// conservative and will be optimized when WAL group commit is implemented.
 pub struct DiskManager {
     file: File,
+    /// Path of the database file.
+    path: PathBuf,
     /// Number of pages in the file.
     page_count: u32,
+    /// File offset of page 0 (past the superblock, if any).
+    data_offset: u64,
+    /// Superblock contents (`Superblock::LEGACY` for files without one).
+    superblock: Superblock,
+    /// Per-sector checksum file, if the database opted in.
+    sector_sums: Option<File>,
 }
 
 impl DiskManager {
     /// Create a new database file.
     ///
     /// # Errors
-    /// Returns an error if the file already exists or cannot be created.
+    /// Returns an error if the file already exists or cannot be created, or
+    /// `Error::DatabaseLocked` if another handle already locked it.
     pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create_new(true)
-            .open(path)?;
+            .open(&path)?;
+        Self::lock(&file)?;
 
-        Ok(Self {
+        let mut dm = Self {
             file,
+            path: path.as_ref().to_path_buf(),
             page_count: 0,
-        })
+            data_offset: SUPERBLOCK_SIZE,
+            superblock: Superblock {
+                version: FormatVersion::CURRENT,
+                ..Superblock::LEGACY
+            },
+            sector_sums: None,
+        };
+        dm.write_superblock()?;
+        Ok(dm)
     }
 
     /// Open an existing database file.
     ///
+    /// If the database has a double-write buffer, pages torn by a crash are
+    /// [repaired](Self::recover_double_write) before returning.
+    ///
     /// # Errors
-    /// Returns an error if the file doesn't exist or cannot be opened.
+    /// Returns an error if the file doesn't exist or cannot be opened,
+    /// `Error::DatabaseLocked` if another handle holds it open, or
+    /// `Error::UnsupportedFormat` if its superblock names a newer format.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        Self::lock(&file)?;
+
+        let file_size = file.metadata()?.len();
+        let superblock = if file_size >= SUPERBLOCK_SIZE {
+            format::read_superblock(&read_page_at(&file, 0, PageId::INVALID)?)
+        } else {
+            None
+        };
+        let (data_offset, superblock) = match superblock {
+            Some(superblock) => (
+                Self::data_offset_for(superblock.double_write_pages),
+                superblock,
+            ),
+            None => (0, Superblock::LEGACY),
+        };
+        format::check(superblock.version)?;
+
+        let sector_sums = if superblock.features & FEATURE_SECTOR_CHECKSUMS != 0 {
+            let sums_path = sector_sums_path(path.as_ref());
+            Some(OpenOptions::new().read(true).write(true).open(sums_path)?)
+        } else {
+            None
+        };
 
         // Calculate page count from file size
-        let metadata = file.metadata()?;
-        let file_size = metadata.len();
-        let page_count = (file_size / PAGE_SIZE as u64) as u32;
+        let page_count = (file_size.saturating_sub(data_offset) / PAGE_SIZE as u64) as u32;
 
-        Ok(Self { file, page_count })
+        let mut dm = Self {
+            file,
+            path: path.as_ref().to_path_buf(),
+            page_count,
+            data_offset,
+            superblock,
+            sector_sums,
+        };
+        let restored = dm.recover_double_write()?;
+        if restored > 0 {
+            trace_event!(DEBUG, "double_write_restored", pages = restored);
+        }
+        Ok(dm)
     }
 
     /// Open an existing database file, or create if it doesn't exist.
@@ -82,20 +155,32 @@ impl DiskManager {
         }
     }
 
+    /// Take the exclusive advisory lock on the database file.
+    ///
+    /// The lock is released when the file is closed, i.e. when the
+    /// `DiskManager` is dropped.
+    fn lock(file: &File) -> Result<()> {
+        file.try_lock().map_err(|e| match e {
+            TryLockError::WouldBlock => Error::DatabaseLocked,
+            TryLockError::Error(e) => Error::Io(e),
+        })
+    }
+
     /// Read a page from disk.
     ///
     /// # Errors
-    /// Returns `Error::PageNotFound` if the page doesn't exist.
+    /// - `Error::PageNotFound` if the page doesn't exist
+    /// - `Error::ShortRead` if the file ends partway through the page
+    /// - `Error::TornPage` if sector checksums are enabled and a sector
+    ///   doesn't match the last write
     pub fn read_page(&mut self, page_id: PageId) -> Result<Page> {
         if page_id.0 >= self.page_count {
-            return Err(crate::common::Error::PageNotFound(page_id.0));
+            return Err(Error::PageNotFound(page_id.0));
         }
 
-        let offset = (page_id.0 as u64) * (PAGE_SIZE as u64);
-        self.file.seek(SeekFrom::Start(offset))?;
-
-        let mut page = Page::new();
-        self.file.read_exact(page.as_mut_slice())?;
+        let page = read_page_at(&self.file, self.page_offset(page_id), page_id)?;
+        self.check_sector_sums(page_id, &page)?;
+        trace_event!(TRACE, "disk_read", page_id = page_id.0);
 
         Ok(page)
     }
@@ -103,6 +188,8 @@ impl DiskManager {
     /// Write a page to disk.
     ///
     /// The page must have been previously allocated with `allocate_page()`.
+    /// With a double-write buffer enabled this goes through
+    /// [`write_pages`](Self::write_pages) as a batch of one.
     ///
     /// # Durability
     /// This method calls `fsync()` after writing to ensure the data is
@@ -112,13 +199,14 @@ impl DiskManager {
     /// Returns `Error::PageNotFound` if the page hasn't been allocated.
     pub fn write_page(&mut self, page_id: PageId, page: &Page) -> Result<()> {
         if page_id.0 >= self.page_count {
-            return Err(crate::common::Error::PageNotFound(page_id.0));
+            return Err(Error::PageNotFound(page_id.0));
+        }
+        if self.has_double_write() {
+            return self.write_pages(&[(page_id, page)]);
         }
 
-        let offset = (page_id.0 as u64) * (PAGE_SIZE as u64);
-        self.file.seek(SeekFrom::Start(offset))?;
-        self.file.write_all(page.as_slice())?;
-        self.file.sync_all()?; // fsync for durability
+        self.write_final(page_id, page)?;
+        self.sync_files()?; // fsync for durability
 
         Ok(())
     }
@@ -135,28 +223,705 @@ impl DiskManager {
         let page_id = PageId::new(self.page_count);
 
         // Extend file with a zeroed page
-        let offset = (page_id.0 as u64) * (PAGE_SIZE as u64);
+        let offset = self.page_offset(page_id);
         self.file.seek(SeekFrom::Start(offset))?;
 
         let zeros = [0u8; PAGE_SIZE];
         self.file.write_all(&zeros)?;
-        self.file.sync_all()?;
+        self.record_sector_sums(page_id, &[&Page::new()])?;
+        self.sync_files()?;
 
         self.page_count += 1;
         Ok(page_id)
     }
 
+    /// Append a fully-formed page to the end of the file.
+    ///
+    /// Allocates the next page ID and writes `page` there in one write,
+    /// instead of zero-filling it first like [`allocate_page`]. Meant for
+    /// bulk loaders that stream finished pages to disk without going through
+    /// (and polluting) the buffer pool.
+    ///
+    /// # Durability
+    /// Like `allocate_page`, the write is fsynced before returning.
+    ///
+    /// [`allocate_page`]: Self::allocate_page
+    pub fn append_page(&mut self, page: &Page) -> Result<PageId> {
+        let page_id = PageId::new(self.page_count);
+
+        let offset = self.page_offset(page_id);
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(page.as_slice())?;
+        self.record_sector_sums(page_id, &[page])?;
+        self.sync_files()?;
+        trace_event!(TRACE, "disk_write", page_id = page_id.0);
+
+        self.page_count += 1;
+        Ok(page_id)
+    }
+
+    /// Allocate `pages` zeroed pages at once, reserving their disk blocks.
+    ///
+    /// On Linux this uses `fallocate`, so the blocks are physically allocated
+    /// (and ideally contiguous) up front and later writes to these pages
+    /// can't fail with ENOSPC. Elsewhere, or if the filesystem doesn't support
+    /// `fallocate`, the file is extended with zeros and fsynced.
+    ///
+    /// Returns the ID of the first new page; the rest follow sequentially.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidConfig` if the file can't grow by `pages`
+    /// without running out of page IDs.
+    pub fn preallocate(&mut self, pages: usize) -> Result<PageId> {
+        let first = PageId::new(self.page_count);
+        if pages == 0 {
+            return Ok(first);
+        }
+
+        let new_count = (self.page_count as u64) + pages as u64;
+        if new_count > crate::common::config::MAX_PAGES - 1 {
+            return Err(Error::InvalidConfig(format!(
+                "cannot preallocate {} pages: {} of {} page IDs are in use",
+                pages,
+                self.page_count,
+                crate::common::config::MAX_PAGES - 1
+            )));
+        }
+
+        let offset = self.page_offset(first);
+        let len = (pages as u64) * (PAGE_SIZE as u64);
+
+        if !self.fallocate(offset, len)? {
+            self.file.seek(SeekFrom::Start(offset))?;
+            let zeros = [0u8; PAGE_SIZE];
+            for _ in 0..pages {
+                self.file.write_all(&zeros)?;
+            }
+        }
+        if self.sector_sums.is_some() {
+            let zeroed = Page::new();
+            self.record_sector_sums(first, &vec![&zeroed; pages])?;
+        }
+        self.sync_files()?;
+
+        self.page_count = new_count as u32;
+        Ok(first)
+    }
+
+    /// Reserve `[offset, offset + len)` with `fallocate`.
+    ///
+    /// Returns false if the filesystem doesn't support it.
+    #[cfg(target_os = "linux")]
+    fn fallocate(&mut self, offset: u64, len: u64) -> Result<bool> {
+        use std::os::unix::io::AsRawFd;
+
+        // SAFETY: the fd is owned by `self.file` and valid for this call.
+        let ret = unsafe {
+            libc::fallocate(
+                self.file.as_raw_fd(),
+                0,
+                offset as libc::off_t,
+                len as libc::off_t,
+            )
+        };
+        if ret == 0 {
+            return Ok(true);
+        }
+
+        let err = std::io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) => Ok(false),
+            _ => Err(err.into()),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn fallocate(&mut self, _offset: u64, _len: u64) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Flush `count` pages starting at `start` to disk.
+    ///
+    /// On Linux this uses `sync_file_range` to write back and wait on just
+    /// that byte range, which is cheaper than an fsync of the whole file
+    /// when only a few contiguous pages changed. It doesn't flush file
+    /// metadata or the drive's write cache, so it only suits pages already
+    /// inside the file. Elsewhere it falls back to `sync_all`.
+    ///
+    /// # Errors
+    /// Returns `Error::PageNotFound` if the range extends past the last page.
+    pub fn sync_range(&mut self, start: PageId, count: usize) -> Result<()> {
+        let end = start.0 as u64 + count as u64;
+        if end > self.page_count as u64 {
+            return Err(Error::PageNotFound(start.0.max(self.page_count)));
+        }
+        if count == 0 {
+            return Ok(());
+        }
+        let offset = self.page_offset(start);
+        let len = (count as u64) * (PAGE_SIZE as u64);
+        self.sync_file_range(offset, len)?;
+        trace_event!(TRACE, "disk_sync_range", page_id = start.0, count = count);
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn sync_file_range(&mut self, offset: u64, len: u64) -> Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        let flags = libc::SYNC_FILE_RANGE_WAIT_BEFORE
+            | libc::SYNC_FILE_RANGE_WRITE
+            | libc::SYNC_FILE_RANGE_WAIT_AFTER;
+        // SAFETY: the fd is owned by `self.file` and valid for this call.
+        let ret = unsafe {
+            libc::sync_file_range(
+                self.file.as_raw_fd(),
+                offset as libc::off64_t,
+                len as libc::off64_t,
+                flags,
+            )
+        };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn sync_file_range(&mut self, _offset: u64, _len: u64) -> Result<()> {
+        self.file.sync_all()?;
+        Ok(())
+    }
+
+    /// Deallocate a page, leaving a hole in the file.
+    ///
+    /// The page is overwritten with a zeroed page whose header is marked
+    /// `PageType::Free` and checksummed, so [`verify`](Self::verify) counts
+    /// it as intact. The file is not shrunk; use [`compact`](Self::compact)
+    /// to reclaim holes.
+    ///
+    /// # Errors
+    /// Returns `Error::PageNotFound` if the page hasn't been allocated.
+    pub fn deallocate_page(&mut self, page_id: PageId) -> Result<()> {
+        let mut page = Page::new();
+        page.set_header(&PageHeader::new(PageType::Free));
+        page.update_checksum();
+        self.write_page(page_id, &page)
+    }
+
+    /// Relocate live pages to the front of the file, filling holes.
+    ///
+    /// Pages whose header is `PageType::Free` are treated as holes. Live pages
+    /// keep their relative order. Every live page is recorded in `mapping_out`
+    /// as `old → new` (unmoved pages map to themselves) so callers can update
+    /// references. The file is then truncated to the live page count.
+    ///
+    /// Must only be run with a flushed, quiesced buffer pool: any cached page
+    /// IDs are invalid afterwards.
+    ///
+    /// # Errors
+    /// Returns I/O errors from reading, writing, or truncating the file.
+    pub fn compact(&mut self, mapping_out: &mut HashMap<PageId, PageId>) -> Result<()> {
+        let mut next = 0u32;
+
+        for old in 0..self.page_count {
+            let old_id = PageId::new(old);
+            let page = self.read_page(old_id)?;
+            if page.header().page_type == PageType::Free {
+                continue;
+            }
+
+            let new_id = PageId::new(next);
+            if new_id != old_id {
+                let offset = self.page_offset(new_id);
+                self.file.seek(SeekFrom::Start(offset))?;
+                self.file.write_all(page.as_slice())?;
+                self.record_sector_sums(new_id, &[&page])?;
+            }
+            mapping_out.insert(old_id, new_id);
+            next += 1;
+        }
+
+        self.file.set_len(self.page_offset(PageId::new(next)))?;
+        if let Some(sums) = &self.sector_sums {
+            sums.set_len(next as u64 * Self::SECTOR_SUMS_ENTRY_SIZE as u64)?;
+        }
+        // The last batch's page IDs no longer name the same pages
+        if self.has_double_write() {
+            self.file.seek(SeekFrom::Start(SUPERBLOCK_SIZE))?;
+            self.file.write_all(&[0u8; PAGE_SIZE])?;
+        }
+        self.sync_files()?;
+        self.page_count = next;
+
+        Ok(())
+    }
+
+    // ========================================================================
+    // Sector checksums
+    // ========================================================================
+
+    /// Size of one page's entry in the sector checksum file.
+    const SECTOR_SUMS_ENTRY_SIZE: usize = Page::SECTORS_PER_PAGE * 2;
+
+    /// Keep a checksum of every 512-byte sector of every page, and check
+    /// them on each read.
+    ///
+    /// A read whose sectors don't all match the last write fails with
+    /// `Error::TornPage`, catching a torn write even when the page CRC
+    /// happens to pass or the page has none. The checksums live in
+    /// `<path>.sectors` so page layouts keep every byte. The choice is
+    /// recorded in the superblock and applies to every later open. A crash
+    /// between writing a page and its checksums also reads as torn.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidConfig` if the database already has pages or
+    /// has no superblock.
+    pub fn enable_sector_checksums(&mut self) -> Result<()> {
+        if self.sector_sums.is_some() {
+            return Ok(());
+        }
+        if self.page_count != 0 || self.data_offset == 0 {
+            return Err(Error::InvalidConfig(
+                "sector checksums can only be enabled on a new database".to_string(),
+            ));
+        }
+        let sums = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(sector_sums_path(&self.path))?;
+        self.sector_sums = Some(sums);
+        self.superblock.features |= FEATURE_SECTOR_CHECKSUMS;
+        self.write_superblock()
+    }
+
+    /// Check if per-sector checksums are enabled.
+    #[inline]
+    pub fn has_sector_checksums(&self) -> bool {
+        self.sector_sums.is_some()
+    }
+
+    /// Store the sector checksums of `pages`, written at consecutive page
+    /// IDs from `first`. Not synced; a no-op unless enabled.
+    fn record_sector_sums(&mut self, first: PageId, pages: &[&Page]) -> Result<()> {
+        let Some(sums) = self.sector_sums.as_mut() else {
+            return Ok(());
+        };
+        let mut buf = Vec::with_capacity(pages.len() * Self::SECTOR_SUMS_ENTRY_SIZE);
+        for page in pages {
+            for sum in page.sector_checksums() {
+                buf.extend_from_slice(&sum.to_le_bytes());
+            }
+        }
+        sums.seek(SeekFrom::Start(
+            first.0 as u64 * Self::SECTOR_SUMS_ENTRY_SIZE as u64,
+        ))?;
+        sums.write_all(&buf)?;
+        Ok(())
+    }
+
+    /// Fail with `Error::TornPage` if `page` doesn't match the sector
+    /// checksums stored for `page_id`. A no-op unless enabled.
+    fn check_sector_sums(&mut self, page_id: PageId, page: &Page) -> Result<()> {
+        let Some(sums) = self.sector_sums.as_mut() else {
+            return Ok(());
+        };
+        let mut entry = [0u8; Self::SECTOR_SUMS_ENTRY_SIZE];
+        sums.seek(SeekFrom::Start(
+            page_id.0 as u64 * Self::SECTOR_SUMS_ENTRY_SIZE as u64,
+        ))?;
+        sums.read_exact(&mut entry)?;
+
+        let mut expected = [0u16; Page::SECTORS_PER_PAGE];
+        for (sum, bytes) in expected.iter_mut().zip(entry.chunks_exact(2)) {
+            *sum = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+        match page.find_torn_sector(&expected) {
+            Some(sector) => Err(Error::TornPage { page_id, sector }),
+            None => Ok(()),
+        }
+    }
+
+    // ========================================================================
+    // Double-write buffer
+    // ========================================================================
+
+    /// Offset of the double-write directory page, right after the superblock.
+    const DOUBLE_WRITE_DIRECTORY: u64 = SUPERBLOCK_SIZE;
+
+    /// Offset of the page count in the double-write directory.
+    const DOUBLE_WRITE_OFFSET_COUNT: usize = PageHeader::SIZE;
+
+    /// Offset of the entry array in the double-write directory. Entry `i`
+    /// describes slot `i`: the page ID (u32), then the CRC32 of the copy
+    /// (u32).
+    const DOUBLE_WRITE_OFFSET_ENTRIES: usize = Self::DOUBLE_WRITE_OFFSET_COUNT + 2;
+
+    /// Size of one double-write directory entry.
+    const DOUBLE_WRITE_ENTRY_SIZE: usize = 8;
+
+    /// Most page images a double-write region can hold (its directory page
+    /// must fit their entries).
+    pub const MAX_DOUBLE_WRITE_PAGES: u16 = ((PAGE_SIZE - Self::DOUBLE_WRITE_OFFSET_ENTRIES)
+        / Self::DOUBLE_WRITE_ENTRY_SIZE) as u16;
+
+    /// Reserve a double-write region of `pages` page images.
+    ///
+    /// The region sits between the superblock and page 0 and is recorded in
+    /// the superblock, so it applies to every later open. Every write of an
+    /// existing page then goes through [`write_pages`](Self::write_pages):
+    /// the batch is written to the region first (one fsync), then to its
+    /// final locations, so a torn final write can be repaired from the
+    /// region by [`recover_double_write`](Self::recover_double_write).
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidConfig` if `pages` is 0 or above
+    /// [`MAX_DOUBLE_WRITE_PAGES`](Self::MAX_DOUBLE_WRITE_PAGES), the database
+    /// already has pages or a region, or it has no superblock.
+    pub fn enable_double_write(&mut self, pages: u16) -> Result<()> {
+        if pages == 0 || pages > Self::MAX_DOUBLE_WRITE_PAGES {
+            return Err(Error::InvalidConfig(format!(
+                "double-write region of {} pages must be 1..={}",
+                pages,
+                Self::MAX_DOUBLE_WRITE_PAGES
+            )));
+        }
+        if self.page_count != 0 || self.data_offset == 0 || self.has_double_write() {
+            return Err(Error::InvalidConfig(
+                "a double-write buffer can only be enabled on a new database".to_string(),
+            ));
+        }
+        // Extend first: a crash before the superblock names the region
+        // leaves only unused bytes past the end
+        self.data_offset = Self::data_offset_for(pages);
+        self.file.set_len(self.data_offset)?;
+        self.superblock.double_write_pages = pages;
+        self.write_superblock()
+    }
+
+    /// Check if the double-write buffer is enabled.
+    #[inline]
+    pub fn has_double_write(&self) -> bool {
+        self.superblock.double_write_pages > 0
+    }
+
+    /// File offset of page 0 with a double-write region of `pages` images.
+    fn data_offset_for(double_write_pages: u16) -> u64 {
+        match double_write_pages {
+            0 => SUPERBLOCK_SIZE,
+            pages => Self::DOUBLE_WRITE_DIRECTORY + (1 + pages as u64) * PAGE_SIZE as u64,
+        }
+    }
+
+    /// Write a batch of pages, going through the double-write buffer if enabled.
+    ///
+    /// Without a double-write buffer this is equivalent to calling
+    /// `write_page` for each page, but with a single fsync at the end. With
+    /// one, batches larger than the region are written in region-sized
+    /// chunks.
+    ///
+    /// # Errors
+    /// Returns `Error::PageNotFound` if any page hasn't been allocated; in
+    /// that case nothing is written.
+    pub fn write_pages(&mut self, pages: &[(PageId, &Page)]) -> Result<()> {
+        if let Some(&(page_id, _)) = pages.iter().find(|(pid, _)| pid.0 >= self.page_count) {
+            return Err(Error::PageNotFound(page_id.0));
+        }
+
+        if !self.has_double_write() {
+            for &(page_id, page) in pages {
+                self.write_final(page_id, page)?;
+            }
+            return self.sync_files();
+        }
+
+        let slots = self.superblock.double_write_pages as usize;
+        for chunk in pages.chunks(slots) {
+            // Each entry carries its copy's CRC, so recovery can tell a
+            // complete copy of that page from a torn one or a leftover copy
+            // of another page from an earlier batch. The pages themselves are
+            // written unchanged.
+            let mut directory = Page::new();
+            directory.set_header(&PageHeader::new(PageType::DoubleWrite));
+            let data = directory.as_mut_slice();
+            let count_at = Self::DOUBLE_WRITE_OFFSET_COUNT;
+            data[count_at..count_at + 2].copy_from_slice(&(chunk.len() as u16).to_le_bytes());
+            for (i, (page_id, page)) in chunk.iter().enumerate() {
+                let at = Self::DOUBLE_WRITE_OFFSET_ENTRIES + i * Self::DOUBLE_WRITE_ENTRY_SIZE;
+                data[at..at + 4].copy_from_slice(&page_id.0.to_le_bytes());
+                let crc = crc32fast::hash(page.as_slice());
+                data[at + 4..at + 8].copy_from_slice(&crc.to_le_bytes());
+            }
+            directory.update_checksum();
+
+            let mut buf = Vec::with_capacity((1 + chunk.len()) * PAGE_SIZE);
+            buf.extend_from_slice(directory.as_slice());
+            for (_, page) in chunk {
+                buf.extend_from_slice(page.as_slice());
+            }
+            self.file
+                .seek(SeekFrom::Start(Self::DOUBLE_WRITE_DIRECTORY))?;
+            self.file.write_all(&buf)?;
+            self.file.sync_all()?;
+
+            for &(page_id, page) in chunk {
+                self.write_final(page_id, page)?;
+            }
+            self.sync_files()?;
+        }
+        Ok(())
+    }
+
+    /// Write `page` at its location and record its sector checksums,
+    /// without syncing.
+    fn write_final(&mut self, page_id: PageId, page: &Page) -> Result<()> {
+        let offset = self.page_offset(page_id);
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(page.as_slice())?;
+        self.record_sector_sums(page_id, &[page])?;
+        trace_event!(TRACE, "disk_write", page_id = page_id.0);
+        Ok(())
+    }
+
+    /// Repair pages torn by a crash from the double-write buffer.
+    ///
+    /// Every page of the last batch whose copy in the region is complete
+    /// (matches the CRC in the directory) but whose final location differs
+    /// from it was cut short by the crash, and is overwritten with the copy.
+    /// A copy that doesn't match its entry is torn, or left over from an
+    /// earlier batch whose directory was replaced, and is skipped. Later
+    /// writes of a page always go through a newer batch, so a matching copy
+    /// is never stale. [`open`](Self::open) runs this automatically.
+    ///
+    /// Returns the number of pages restored.
+    pub fn recover_double_write(&mut self) -> Result<usize> {
+        if !self.has_double_write() {
+            return Ok(0);
+        }
+
+        let directory = read_page_at(&self.file, Self::DOUBLE_WRITE_DIRECTORY, PageId::INVALID)?;
+        if directory.page_type() != PageType::DoubleWrite || !directory.verify_checksum() {
+            return Ok(0);
+        }
+        let data = directory.as_slice();
+        let count_at = Self::DOUBLE_WRITE_OFFSET_COUNT;
+        let count = u16::from_le_bytes([data[count_at], data[count_at + 1]])
+            .min(self.superblock.double_write_pages);
+
+        let mut restored = 0;
+        for i in 0..count as usize {
+            let at = Self::DOUBLE_WRITE_OFFSET_ENTRIES + i * Self::DOUBLE_WRITE_ENTRY_SIZE;
+            let u32_at = |at: usize| {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&data[at..at + 4]);
+                u32::from_le_bytes(bytes)
+            };
+            let page_id = PageId::new(u32_at(at));
+            let crc = u32_at(at + 4);
+            let copy_offset = Self::DOUBLE_WRITE_DIRECTORY + (1 + i as u64) * PAGE_SIZE as u64;
+            let copy = read_page_at(&self.file, copy_offset, page_id)?;
+
+            if page_id.0 >= self.page_count || crc32fast::hash(copy.as_slice()) != crc {
+                continue;
+            }
+            let current = read_page_at(&self.file, self.page_offset(page_id), page_id)?;
+            if current.as_slice() == copy.as_slice() {
+                continue;
+            }
+
+            self.write_final(page_id, &copy)?;
+            restored += 1;
+        }
+
+        if restored > 0 {
+            self.sync_files()?;
+        }
+        Ok(restored)
+    }
+
+    /// Check every page's integrity, one page at a time.
+    ///
+    /// A page passes if it is all zeros (never written) or carries a valid
+    /// checksum; see [`Page::is_integral`]. Sector checksums aren't checked.
+    pub fn verify(&mut self) -> Result<VerifyReport> {
+        let mut corrupt = Vec::new();
+        for i in 0..self.page_count {
+            let page_id = PageId::new(i);
+            let page = read_page_at(&self.file, self.page_offset(page_id), page_id)?;
+            if !page.is_integral() {
+                corrupt.push(page_id);
+            }
+        }
+        Ok(VerifyReport {
+            pages_checked: self.page_count,
+            corrupt,
+        })
+    }
+
+    /// Parallel version of [`verify`](Self::verify).
+    ///
+    /// Splits the pages into contiguous ranges, one per worker thread (at
+    /// most `threads`). Each worker reads through its own cloned handle
+    /// with positioned reads, so workers don't race on the file offset.
+    /// The report is the same as `verify`'s, with `corrupt` in page order.
+    ///
+    /// # Errors
+    /// - `Error::Internal` if a worker thread panicked
+    /// - Any error reading a page
+    pub fn checksum_all(&self, threads: usize) -> Result<VerifyReport> {
+        let page_count = self.page_count as usize;
+        let threads = threads.clamp(1, page_count.max(1));
+        let chunk = page_count.div_ceil(threads);
+
+        let data_offset = self.data_offset;
+        let handles = (0..threads)
+            .map(|_| self.file.try_clone())
+            .collect::<std::io::Result<Vec<File>>>()?;
+
+        let results: Vec<Result<Vec<PageId>>> = std::thread::scope(|s| {
+            let workers: Vec<_> = handles
+                .into_iter()
+                .enumerate()
+                .map(|(t, file)| {
+                    let start = t * chunk;
+                    let end = ((t + 1) * chunk).min(page_count);
+                    s.spawn(move || {
+                        let mut corrupt = Vec::new();
+                        for i in start..end {
+                            let page_id = PageId::new(i as u32);
+                            let offset = data_offset + (i as u64) * (PAGE_SIZE as u64);
+                            if !read_page_at(&file, offset, page_id)?.is_integral() {
+                                corrupt.push(page_id);
+                            }
+                        }
+                        Ok(corrupt)
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|w| {
+                    w.join().unwrap_or_else(|_| {
+                        Err(Error::Internal("checksum worker panicked".to_string()))
+                    })
+                })
+                .collect()
+        });
+
+        let mut corrupt = Vec::new();
+        for result in results {
+            corrupt.extend(result?);
+        }
+        Ok(VerifyReport {
+            pages_checked: self.page_count,
+            corrupt,
+        })
+    }
+
     /// Get the number of pages in the database.
     #[inline]
     pub fn page_count(&self) -> u32 {
         self.page_count
     }
 
-    /// Get the total size of the database file in bytes.
+    /// Get the total size of the database's pages in bytes.
+    ///
+    /// Excludes the superblock, so a new database reports 0.
     #[inline]
     pub fn file_size(&self) -> u64 {
         (self.page_count as u64) * (PAGE_SIZE as u64)
     }
+
+    /// Format version the database was written in.
+    #[inline]
+    pub fn format_version(&self) -> FormatVersion {
+        self.superblock.version
+    }
+
+    /// Rewrite the superblock from the in-memory version and features.
+    fn write_superblock(&mut self) -> Result<()> {
+        let mut page = Page::new();
+        format::write_superblock(&mut page, &self.superblock);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(page.as_slice())?;
+        self.sync_files()
+    }
+
+    /// Fsync the database file and, if enabled, the sector checksum file.
+    fn sync_files(&mut self) -> Result<()> {
+        self.file.sync_all()?;
+        if let Some(sums) = &self.sector_sums {
+            sums.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// File offset of `page_id`.
+    #[inline]
+    pub(crate) fn page_offset(&self, page_id: PageId) -> u64 {
+        self.data_offset + (page_id.0 as u64) * (PAGE_SIZE as u64)
+    }
+}
+
+/// Result of an integrity check over the whole file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Number of pages examined.
+    pub pages_checked: u32,
+    /// Pages that are neither zeroed nor carry a valid checksum, in order.
+    pub corrupt: Vec<PageId>,
+}
+
+impl VerifyReport {
+    /// Whether every page passed.
+    pub fn is_clean(&self) -> bool {
+        self.corrupt.is_empty()
+    }
+}
+
+/// Path of the sector checksum file kept next to the database at `path`.
+fn sector_sums_path(path: &Path) -> PathBuf {
+    let mut sums_path = path.as_os_str().to_owned();
+    sums_path.push(".sectors");
+    sums_path.into()
+}
+
+/// Read a full page at `offset` with positioned reads, independent of the
+/// file cursor. `page_id` is only used to report errors.
+///
+/// # Errors
+/// `Error::ShortRead` if the file ends partway through the page.
+fn read_page_at(file: &File, offset: u64, page_id: PageId) -> Result<Page> {
+    let mut page = Page::new();
+    let buf = page.as_mut_slice();
+    let mut got = 0;
+    while got < PAGE_SIZE {
+        match read_at(file, &mut buf[got..], offset + got as u64) {
+            Ok(0) => {
+                return Err(Error::ShortRead {
+                    page_id,
+                    got,
+                    expected: PAGE_SIZE,
+                })
+            }
+            Ok(n) => got += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(page)
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
 }
 
 #[cfg(test)]
@@ -191,6 +956,59 @@ mod tests {
         assert!(DiskManager::open(&path).is_err());
     }
 
+    #[test]
+    fn test_read_truncated_page_is_short_read() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let mut dm = DiskManager::create(&path).unwrap();
+        dm.allocate_page().unwrap();
+        let last = dm.allocate_page().unwrap();
+
+        // Truncated behind the disk manager's back, partway into the last page
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(SUPERBLOCK_SIZE + PAGE_SIZE as u64 + 100)
+            .unwrap();
+
+        match dm.read_page(last) {
+            Err(Error::ShortRead {
+                page_id,
+                got,
+                expected,
+            }) => {
+                assert_eq!(page_id, last);
+                assert_eq!(got, 100);
+                assert_eq!(expected, PAGE_SIZE);
+            }
+            other => panic!("expected ShortRead, got {:?}", other.map(|_| ())),
+        }
+        assert!(dm.read_page(PageId::new(0)).is_ok());
+    }
+
+    #[test]
+    fn test_second_open_is_locked() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let dm = DiskManager::create(&path).unwrap();
+        assert!(matches!(
+            DiskManager::open(&path),
+            Err(Error::DatabaseLocked)
+        ));
+        assert!(matches!(
+            DiskManager::open_or_create(&path),
+            Err(Error::DatabaseLocked)
+        ));
+
+        // Released on drop
+        drop(dm);
+        let _dm = DiskManager::open(&path).unwrap();
+        assert!(matches!(
+            DiskManager::open(&path),
+            Err(Error::DatabaseLocked)
+        ));
+    }
+
     #[test]
     fn test_allocate_and_read_page() {
         let dir = tempdir().unwrap();
@@ -310,6 +1128,357 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_preallocate() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let mut dm = DiskManager::create(&path).unwrap();
+        dm.allocate_page().unwrap();
+
+        let first = dm.preallocate(16).unwrap();
+        assert_eq!(first, PageId::new(1));
+        assert_eq!(dm.page_count(), 17);
+        assert_eq!(dm.allocate_page().unwrap(), PageId::new(17));
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.len(), SUPERBLOCK_SIZE + 18 * PAGE_SIZE as u64);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            // st_blocks is in 512-byte units; the blocks must be backed
+            assert!(metadata.blocks() * 512 >= 17 * PAGE_SIZE as u64);
+        }
+
+        // Preallocated pages read as zeros and accept writes
+        for i in 1..17 {
+            assert!(dm
+                .read_page(PageId::new(i))
+                .unwrap()
+                .as_slice()
+                .iter()
+                .all(|&b| b == 0));
+        }
+        let mut page = Page::new();
+        page.as_mut_slice()[0] = 0x5A;
+        dm.write_page(PageId::new(16), &page).unwrap();
+
+        drop(dm);
+        let mut dm = DiskManager::open(&path).unwrap();
+        assert_eq!(dm.page_count(), 18);
+        assert_eq!(dm.read_page(PageId::new(16)).unwrap().as_slice()[0], 0x5A);
+
+        // Too many pages is a configuration error, and allocates nothing
+        match dm.preallocate(u32::MAX as usize) {
+            Err(Error::InvalidConfig(msg)) => assert!(msg.contains(&u32::MAX.to_string())),
+            other => panic!("expected InvalidConfig, got {:?}", other),
+        }
+        assert_eq!(dm.page_count(), 18);
+    }
+
+    #[test]
+    fn test_compact_fills_holes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let mut dm = DiskManager::create(&path).unwrap();
+        for i in 0..6u32 {
+            let page_id = dm.allocate_page().unwrap();
+            let mut page = Page::new();
+            page.set_header(&PageHeader::new(PageType::Data));
+            page.as_mut_slice()[100] = i as u8;
+            dm.write_page(page_id, &page).unwrap();
+        }
+
+        // Leave holes at 1 and 3
+        dm.deallocate_page(PageId::new(1)).unwrap();
+        dm.deallocate_page(PageId::new(3)).unwrap();
+
+        let mut mapping = HashMap::new();
+        dm.compact(&mut mapping).unwrap();
+
+        assert_eq!(dm.page_count(), 4);
+        assert_eq!(dm.file_size(), 4 * PAGE_SIZE as u64);
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            SUPERBLOCK_SIZE + 4 * PAGE_SIZE as u64
+        );
+
+        let expected = [(0, 0), (2, 1), (4, 2), (5, 3)];
+        assert_eq!(mapping.len(), expected.len());
+        for (old, new) in expected {
+            assert_eq!(mapping[&PageId::new(old)], PageId::new(new));
+            let page = dm.read_page(PageId::new(new)).unwrap();
+            assert_eq!(page.as_slice()[100], old as u8);
+        }
+    }
+
+    #[test]
+    fn test_deallocated_page_verifies_clean() {
+        let dir = tempdir().unwrap();
+        let mut dm = DiskManager::create(dir.path().join("test.db")).unwrap();
+
+        let page_id = dm.allocate_page().unwrap();
+        let mut page = Page::new();
+        page.as_mut_slice()[100] = 0xAB;
+        page.update_checksum();
+        dm.write_page(page_id, &page).unwrap();
+        dm.deallocate_page(page_id).unwrap();
+
+        let page = dm.read_page(page_id).unwrap();
+        assert_eq!(page.header().page_type, PageType::Free);
+        assert!(page.verify_checksum());
+        assert!(dm.verify().unwrap().is_clean());
+        assert!(dm.checksum_all(2).unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_sector_checksums_persist_and_detect_tears() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let mut page = Page::new();
+        page.as_mut_slice()[3000] = 0x5A;
+        let page_id = {
+            let mut dm = DiskManager::create(&path).unwrap();
+            assert!(!dm.has_sector_checksums());
+            dm.enable_sector_checksums().unwrap();
+            let page_id = dm.allocate_page().unwrap();
+            dm.write_page(page_id, &page).unwrap();
+            page_id
+        };
+
+        let mut dm = DiskManager::open(&path).unwrap();
+        assert!(dm.has_sector_checksums());
+        assert_eq!(dm.read_page(page_id).unwrap().as_slice()[3000], 0x5A);
+
+        // Too late once the database has pages
+        let other = dir.path().join("other.db");
+        let mut other_dm = DiskManager::create(&other).unwrap();
+        other_dm.allocate_page().unwrap();
+        assert!(matches!(
+            other_dm.enable_sector_checksums(),
+            Err(Error::InvalidConfig(_))
+        ));
+
+        // Sector 5 reverts to its old (zeroed) contents
+        {
+            let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+            let offset = SUPERBLOCK_SIZE + 5 * Page::SECTOR_SIZE as u64;
+            file.seek(SeekFrom::Start(offset)).unwrap();
+            file.write_all(&[0u8; Page::SECTOR_SIZE]).unwrap();
+        }
+        assert!(matches!(
+            dm.read_page(page_id),
+            Err(Error::TornPage { sector: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn test_double_write_restores_torn_page() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let mut pages = Vec::new();
+        let torn_offset = {
+            let mut dm = DiskManager::create(&path).unwrap();
+            dm.enable_double_write(4).unwrap();
+            assert!(dm.has_double_write());
+            for i in 0..6u8 {
+                let page_id = dm.allocate_page().unwrap();
+                let mut page = Page::new();
+                page.set_header(&PageHeader::new(PageType::Data));
+                page.as_mut_slice()[100] = i + 1;
+                page.as_mut_slice()[4000] = i + 1;
+                pages.push((page_id, page));
+            }
+            // Six pages through a four-page region: the last chunk is 4 and 5
+            let batch: Vec<(PageId, &Page)> = pages.iter().map(|(pid, p)| (*pid, p)).collect();
+            dm.write_pages(&batch).unwrap();
+            dm.page_offset(PageId::new(5))
+        };
+
+        // Tear page 5: its second half never reached the final location
+        {
+            let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.seek(SeekFrom::Start(torn_offset + (PAGE_SIZE / 2) as u64))
+                .unwrap();
+            file.write_all(&[0u8; PAGE_SIZE / 2]).unwrap();
+        }
+
+        // Opening repairs it
+        let mut dm = DiskManager::open(&path).unwrap();
+        assert!(dm.has_double_write());
+        for (page_id, page) in &pages {
+            let read = dm.read_page(*page_id).unwrap();
+            assert!(read.as_slice() == page.as_slice(), "{} differs", page_id);
+        }
+
+        // Nothing left to repair
+        assert_eq!(dm.recover_double_write().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_double_write_skips_leftover_copy_of_another_page() {
+        let filled = |byte: u8| {
+            let mut page = Page::new();
+            page.as_mut_slice().fill(byte);
+            page
+        };
+
+        // The directory a batch writing A = 0xcc would put in the region
+        let dir = tempdir().unwrap();
+        let new_directory = {
+            let scratch = dir.path().join("scratch.db");
+            let mut dm = DiskManager::create(&scratch).unwrap();
+            dm.enable_double_write(1).unwrap();
+            let a = dm.allocate_page().unwrap();
+            dm.write_page(a, &filled(0xcc)).unwrap();
+            drop(dm);
+            let mut directory = vec![0u8; PAGE_SIZE];
+            let mut file = File::open(&scratch).unwrap();
+            file.seek(SeekFrom::Start(SUPERBLOCK_SIZE)).unwrap();
+            file.read_exact(&mut directory).unwrap();
+            directory
+        };
+
+        let path = dir.path().join("test.db");
+        let (a, b) = {
+            let mut dm = DiskManager::create(&path).unwrap();
+            dm.enable_double_write(1).unwrap();
+            let (a, b) = (dm.allocate_page().unwrap(), dm.allocate_page().unwrap());
+            dm.write_page(a, &filled(0xaa)).unwrap();
+            dm.write_page(b, &filled(0xbb)).unwrap();
+            (a, b)
+        };
+        // The crash tore the region write for A: only the directory landed,
+        // and the slot still holds B's copy from the previous batch
+        {
+            let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.seek(SeekFrom::Start(SUPERBLOCK_SIZE)).unwrap();
+            file.write_all(&new_directory).unwrap();
+        }
+
+        let mut dm = DiskManager::open(&path).unwrap();
+        assert!(dm.read_page(a).unwrap().as_slice() == filled(0xaa).as_slice());
+        assert!(dm.read_page(b).unwrap().as_slice() == filled(0xbb).as_slice());
+        assert_eq!(dm.recover_double_write().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_enable_double_write_validates() {
+        let dir = tempdir().unwrap();
+        let mut dm = DiskManager::create(dir.path().join("test.db")).unwrap();
+
+        for pages in [0, DiskManager::MAX_DOUBLE_WRITE_PAGES + 1] {
+            assert!(matches!(
+                dm.enable_double_write(pages),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+        dm.enable_double_write(2).unwrap();
+        assert!(matches!(
+            dm.enable_double_write(2),
+            Err(Error::InvalidConfig(_))
+        ));
+
+        // Pages start after the region
+        assert_eq!(dm.allocate_page().unwrap(), PageId::new(0));
+        assert_eq!(
+            dm.page_offset(PageId::new(0)),
+            SUPERBLOCK_SIZE + 3 * PAGE_SIZE as u64
+        );
+
+        let mut late = DiskManager::create(dir.path().join("late.db")).unwrap();
+        late.allocate_page().unwrap();
+        assert!(matches!(
+            late.enable_double_write(2),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_write_pages_rejects_unallocated() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+
+        let mut dm = DiskManager::create(&path).unwrap();
+        dm.allocate_page().unwrap();
+
+        let page = Page::new();
+        let result = dm.write_pages(&[(PageId::new(0), &page), (PageId::new(1), &page)]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_checksum_all_matches_verify() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let mut dm = DiskManager::create(&path).unwrap();
+
+        // Valid, zeroed, and corrupt pages mixed across the file
+        let mut expected = Vec::new();
+        for i in 0..37u32 {
+            let page_id = dm.allocate_page().unwrap();
+            if i % 5 == 0 {
+                continue;
+            }
+            let mut page = Page::new();
+            page.as_mut_slice()[200] = i as u8;
+            page.update_checksum();
+            if i % 7 == 3 {
+                page.as_mut_slice()[300] ^= 0xFF;
+                expected.push(page_id);
+            }
+            dm.write_page(page_id, &page).unwrap();
+        }
+        assert_eq!(expected.len(), 4);
+
+        let serial = dm.verify().unwrap();
+        assert_eq!(serial.pages_checked, 37);
+        assert_eq!(serial.corrupt, expected);
+        assert!(!serial.is_clean());
+        for threads in [1, 4, 64] {
+            assert_eq!(dm.checksum_all(threads).unwrap(), serial);
+        }
+
+        // The workers' reads don't disturb the main handle
+        assert_eq!(dm.read_page(PageId::new(1)).unwrap().as_slice()[200], 1);
+
+        let empty = DiskManager::create(dir.path().join("empty.db")).unwrap();
+        assert!(empty.checksum_all(4).unwrap().is_clean());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sync_range_persists_pages() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        {
+            let mut dm = DiskManager::create(&path).unwrap();
+            dm.preallocate(8).unwrap();
+            for i in 2..5u32 {
+                let mut page = Page::new();
+                page.as_mut_slice()[100] = i as u8;
+                dm.write_page(PageId::new(i), &page).unwrap();
+            }
+            dm.sync_range(PageId::new(2), 3).unwrap();
+            dm.sync_range(PageId::new(8), 0).unwrap();
+            assert!(matches!(
+                dm.sync_range(PageId::new(6), 3),
+                Err(Error::PageNotFound(_))
+            ));
+        }
+
+        let mut dm = DiskManager::open(&path).unwrap();
+        for i in 2..5u32 {
+            assert_eq!(
+                dm.read_page(PageId::new(i)).unwrap().as_slice()[100],
+                i as u8
+            );
+        }
+    }
+
     #[test]
     fn test_open_or_create() {
         let dir = tempdir().unwrap();
@@ -0,0 +1,274 @@
+//! On-disk format versioning.
+//!
+//! [`DiskManager::create`] writes a superblock ahead of page 0 recording the
+//! format version, so page 0 starts at byte [`SUPERBLOCK_SIZE`].
+//! [`DiskManager::open`] refuses files stamped with a version newer than
+//! [`FormatVersion::CURRENT`]. Files without a superblock (written before
+//! the stamp existed) keep page 0 at byte 0 and are treated as version 1.
+//!
+//! # Superblock Layout
+//! ```text
+//! Offset  Size  Field
+//! ------  ----  -----
+//! 0       13    PageHeader (page_type = Superblock)
+//! 13      4     magic ("IXDB")
+//! 17      2     format version
+//! 19      4     feature flags (`FEATURE_*`)
+//! 23      2     double-write region size in pages (0 = none)
+//! ```
+//! All integers are little-endian.
+//!
+//! Layout changes (e.g. a header version byte, mandatory per-sector
+//! checksums) bump the version and add a step to [`migrate`]. Opt-in
+//! features that only add data alongside the pages are flag bits instead.
+//!
+//! [`DiskManager::create`]: crate::storage::DiskManager::create
+//! [`DiskManager::open`]: crate::storage::DiskManager::open
+
+use std::fmt;
+
+use crate::common::config::PAGE_SIZE;
+use crate::common::{Error, Result};
+use crate::storage::page::{Page, PageHeader, PageType};
+use crate::storage::DiskManager;
+
+const OFFSET_MAGIC: usize = PageHeader::SIZE;
+const OFFSET_VERSION: usize = OFFSET_MAGIC + 4;
+const OFFSET_FEATURES: usize = OFFSET_VERSION + 2;
+const OFFSET_DOUBLE_WRITE_PAGES: usize = OFFSET_FEATURES + 4;
+const MAGIC: [u8; 4] = *b"IXDB";
+
+/// Bytes the superblock occupies at the start of the file, when present.
+pub const SUPERBLOCK_SIZE: u64 = PAGE_SIZE as u64;
+
+/// Feature flag: per-sector checksums are kept for every page and checked
+/// on read.
+pub const FEATURE_SECTOR_CHECKSUMS: u32 = 1 << 0;
+
+/// Version of the on-disk format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FormatVersion(pub u16);
+
+impl FormatVersion {
+    /// The first format: 13-byte page header, no format changes since.
+    pub const V1: FormatVersion = FormatVersion(1);
+
+    /// Newest format this build reads and writes.
+    pub const CURRENT: FormatVersion = Self::V1;
+}
+
+impl fmt::Display for FormatVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+/// Fields stored in the superblock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Superblock {
+    pub(crate) version: FormatVersion,
+    /// Optional features (`FEATURE_*` bits).
+    pub(crate) features: u32,
+    /// Page images the double-write region holds (0 = no region).
+    pub(crate) double_write_pages: u16,
+}
+
+impl Superblock {
+    /// Superblock of a file written before superblocks existed.
+    pub(crate) const LEGACY: Superblock = Superblock {
+        version: FormatVersion::V1,
+        features: 0,
+        double_write_pages: 0,
+    };
+}
+
+/// Initialize `page` as a superblock stamped with `version` and no
+/// features.
+pub fn init_superblock(page: &mut Page, version: FormatVersion) {
+    write_superblock(
+        page,
+        &Superblock {
+            version,
+            ..Superblock::LEGACY
+        },
+    );
+}
+
+/// Initialize `page` as a superblock holding `superblock`.
+pub(crate) fn write_superblock(page: &mut Page, superblock: &Superblock) {
+    page.reset();
+    page.set_header(&PageHeader::new(PageType::Superblock));
+    let data = page.as_mut_slice();
+    data[OFFSET_MAGIC..OFFSET_MAGIC + 4].copy_from_slice(&MAGIC);
+    data[OFFSET_VERSION..OFFSET_VERSION + 2].copy_from_slice(&superblock.version.0.to_le_bytes());
+    data[OFFSET_FEATURES..OFFSET_FEATURES + 4].copy_from_slice(&superblock.features.to_le_bytes());
+    data[OFFSET_DOUBLE_WRITE_PAGES..OFFSET_DOUBLE_WRITE_PAGES + 2]
+        .copy_from_slice(&superblock.double_write_pages.to_le_bytes());
+    page.update_checksum();
+}
+
+/// Format version recorded in `page`, or `None` if it isn't a superblock.
+pub fn superblock_version(page: &Page) -> Option<FormatVersion> {
+    read_superblock(page).map(|superblock| superblock.version)
+}
+
+/// Fields recorded in `page`, or `None` if it isn't a superblock.
+pub(crate) fn read_superblock(page: &Page) -> Option<Superblock> {
+    let data = page.as_slice();
+    if page.header().page_type != PageType::Superblock
+        || data[OFFSET_MAGIC..OFFSET_MAGIC + 4] != MAGIC
+    {
+        return None;
+    }
+    let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+    let mut features = [0u8; 4];
+    features.copy_from_slice(&data[OFFSET_FEATURES..OFFSET_FEATURES + 4]);
+    Some(Superblock {
+        version: FormatVersion(u16_at(OFFSET_VERSION)),
+        features: u32::from_le_bytes(features),
+        double_write_pages: u16_at(OFFSET_DOUBLE_WRITE_PAGES),
+    })
+}
+
+/// Read the format version of an open database.
+///
+/// Files without a superblock report [`FormatVersion::V1`].
+pub fn read_version(dm: &DiskManager) -> FormatVersion {
+    dm.format_version()
+}
+
+/// Fail if `version` is newer than this build.
+pub(crate) fn check(version: FormatVersion) -> Result<()> {
+    if version > FormatVersion::CURRENT {
+        return Err(unsupported(version));
+    }
+    Ok(())
+}
+
+/// Upgrade a database from format `from` to format `to`.
+///
+/// Each version bump adds a single-version step here that rewrites what
+/// changed and restamps the superblock. With only [`FormatVersion::V1`]
+/// defined there are no steps yet, so this is a no-op for `from == to`.
+///
+/// # Errors
+/// Returns `Error::UnsupportedFormat` naming the offending version if:
+/// - `from` or `to` is newer than [`FormatVersion::CURRENT`]
+/// - the database isn't at `from` (names the version it is at)
+/// - `to` is older than `from` (no downgrades)
+pub fn migrate(from: FormatVersion, to: FormatVersion, dm: &mut DiskManager) -> Result<()> {
+    for version in [from, to] {
+        check(version)?;
+    }
+    let actual = read_version(dm);
+    if actual != from {
+        return Err(unsupported(actual));
+    }
+    if to < from {
+        return Err(unsupported(to));
+    }
+
+    // Steps go here in order, e.g. `if from < V2 { migrate_v1_to_v2(dm)? }`
+    Ok(())
+}
+
+fn unsupported(version: FormatVersion) -> Error {
+    Error::UnsupportedFormat {
+        found: version.0,
+        supported: FormatVersion::CURRENT.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Seek, SeekFrom, Write};
+    use tempfile::tempdir;
+
+    /// Create a database, then overwrite its superblock with `version`.
+    fn stamp(path: &std::path::Path, version: FormatVersion) {
+        DiskManager::create(path).unwrap();
+        let mut page = Page::new();
+        init_superblock(&mut page, version);
+        let mut file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.write_all(page.as_slice()).unwrap();
+    }
+
+    #[test]
+    fn test_create_stamps_current_format() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("new.db");
+        let dm = DiskManager::create(&path).unwrap();
+        assert_eq!(read_version(&dm), FormatVersion::CURRENT);
+        drop(dm);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len() as u64, SUPERBLOCK_SIZE);
+        let mut page = Page::new();
+        page.as_mut_slice().copy_from_slice(&bytes);
+        assert_eq!(superblock_version(&page), Some(FormatVersion::CURRENT));
+        assert!(page.verify_checksum());
+    }
+
+    #[test]
+    fn test_future_format_fails_to_open() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("future.db");
+        stamp(&path, FormatVersion(FormatVersion::CURRENT.0 + 1));
+
+        assert!(matches!(
+            DiskManager::open(&path),
+            Err(Error::UnsupportedFormat {
+                found: 2,
+                supported: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_current_format_opens() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("current.db");
+        stamp(&path, FormatVersion::CURRENT);
+
+        let mut dm = DiskManager::open(&path).unwrap();
+        assert_eq!(read_version(&dm), FormatVersion::CURRENT);
+        migrate(FormatVersion::V1, FormatVersion::CURRENT, &mut dm).unwrap();
+        assert!(matches!(
+            migrate(FormatVersion(9), FormatVersion::CURRENT, &mut dm),
+            Err(Error::UnsupportedFormat { found: 9, .. })
+        ));
+
+        // Migrating from the wrong version, or downgrading, is refused
+        assert!(matches!(
+            migrate(FormatVersion(0), FormatVersion::V1, &mut dm),
+            Err(Error::UnsupportedFormat { found: 1, .. })
+        ));
+        assert!(matches!(
+            migrate(FormatVersion::V1, FormatVersion(0), &mut dm),
+            Err(Error::UnsupportedFormat { found: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_file_without_superblock_is_v1() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("legacy.db");
+        let mut page = Page::new();
+        page.set_header(&PageHeader::new(PageType::Data));
+        page.as_mut_slice()[100] = 0x5A;
+        std::fs::write(&path, page.as_slice()).unwrap();
+
+        // Page 0 stays at byte 0
+        let mut dm = DiskManager::open(&path).unwrap();
+        assert_eq!(read_version(&dm), FormatVersion::V1);
+        assert_eq!(dm.page_count(), 1);
+        assert_eq!(
+            dm.read_page(crate::common::PageId::new(0))
+                .unwrap()
+                .as_slice()[100],
+            0x5A
+        );
+    }
+}
@@ -3,8 +3,10 @@
 //! This module handles persistent storage:
 //! - [`DiskManager`] - Low-level file I/O
 //! - [`page`] - Page types and layouts
+//! - [`format`](mod@format) - On-disk format versioning and migrations
 
 mod disk_manager;
+pub mod format;
 pub mod page;
 
-pub use disk_manager::DiskManager;
\ No newline at end of file
+pub use disk_manager::{DiskManager, VerifyReport};
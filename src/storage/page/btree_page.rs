@@ -0,0 +1,165 @@
+//! Initialization and header fields for B-tree node pages.
+//!
+//! # Node Page Layout
+//! ```text
+//! Offset  Size  Field
+//! ------  ----  -----
+//! 0       13    PageHeader (page_type = BTreeLeaf or BTreeInternal)
+//! 13      2     key count
+//! 15      4     right sibling page ID (PageId::INVALID if none)
+//! 19      ...   entries
+//! ```
+//! All integers are little-endian. Leaves use the sibling pointer for the
+//! leaf chain walked by range scans; internal nodes keep it for B-link style
+//! traversal.
+
+use crate::common::PageId;
+
+use super::{Page, PageHeader, PageType};
+
+const OFFSET_KEY_COUNT: usize = PageHeader::SIZE;
+const OFFSET_SIBLING: usize = OFFSET_KEY_COUNT + 2;
+
+/// Size of the node header, including the page header. Entries start here.
+pub const BTREE_NODE_HEADER_SIZE: usize = OFFSET_SIBLING + 4;
+
+/// Initialize `page` as an empty B-tree leaf.
+///
+/// The page is zeroed, stamped `PageType::BTreeLeaf` with zero keys and no
+/// sibling, and its checksum updated.
+pub fn init_btree_leaf(page: &mut Page) {
+    init_node(page, PageType::BTreeLeaf);
+}
+
+/// Initialize `page` as an empty B-tree internal node.
+///
+/// Same as [`init_btree_leaf`], but stamped `PageType::BTreeInternal`.
+pub fn init_btree_internal(page: &mut Page) {
+    init_node(page, PageType::BTreeInternal);
+}
+
+fn init_node(page: &mut Page, page_type: PageType) {
+    page.reset();
+    page.set_header(&PageHeader::new(page_type));
+    btree_set_sibling(page, PageId::INVALID);
+    page.update_checksum();
+}
+
+/// Number of keys stored in a B-tree node.
+pub fn btree_key_count(page: &Page) -> u16 {
+    let data = page.as_slice();
+    u16::from_le_bytes([data[OFFSET_KEY_COUNT], data[OFFSET_KEY_COUNT + 1]])
+}
+
+/// Set the number of keys stored in a B-tree node.
+pub fn btree_set_key_count(page: &mut Page, count: u16) {
+    page.as_mut_slice()[OFFSET_KEY_COUNT..OFFSET_KEY_COUNT + 2]
+        .copy_from_slice(&count.to_le_bytes());
+}
+
+/// Right sibling of a B-tree node, or `PageId::INVALID` if none.
+pub fn btree_sibling(page: &Page) -> PageId {
+    let data = page.as_slice();
+    PageId::new(u32::from_le_bytes(
+        data[OFFSET_SIBLING..OFFSET_SIBLING + 4].try_into().unwrap(),
+    ))
+}
+
+/// Set the right sibling of a B-tree node.
+pub fn btree_set_sibling(page: &mut Page, sibling: PageId) {
+    page.as_mut_slice()[OFFSET_SIBLING..OFFSET_SIBLING + 4]
+        .copy_from_slice(&sibling.0.to_le_bytes());
+}
+
+/// Read-only typed view of a B-tree node page.
+///
+/// Created with [`BTreeNodePage::new`], which checks the page type, so the
+/// accessors can assume a node layout.
+#[derive(Clone, Copy)]
+pub struct BTreeNodePage<'a> {
+    page: &'a Page,
+}
+
+impl<'a> BTreeNodePage<'a> {
+    /// View `page` as a B-tree node, or `None` if it isn't a leaf or
+    /// internal node.
+    pub fn new(page: &'a Page) -> Option<Self> {
+        matches!(
+            page.header().page_type,
+            PageType::BTreeLeaf | PageType::BTreeInternal
+        )
+        .then_some(Self { page })
+    }
+
+    /// Whether this node is a leaf.
+    pub fn is_leaf(&self) -> bool {
+        self.page.header().page_type == PageType::BTreeLeaf
+    }
+
+    /// Number of keys stored in the node.
+    pub fn key_count(&self) -> u16 {
+        btree_key_count(self.page)
+    }
+
+    /// Right sibling, or `PageId::INVALID` if none.
+    pub fn sibling(&self) -> PageId {
+        btree_sibling(self.page)
+    }
+
+    /// Entry area following the node header.
+    pub fn entries(&self) -> &'a [u8] {
+        &self.page.as_slice()[BTREE_NODE_HEADER_SIZE..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Lsn;
+
+    #[test]
+    fn test_init_btree_leaf() {
+        let mut page = Page::new();
+        page.as_mut_slice().fill(0xAB);
+
+        init_btree_leaf(&mut page);
+        assert_eq!(page.header().page_type, PageType::BTreeLeaf);
+        assert_eq!(page.header().lsn, Lsn::ZERO);
+        assert_eq!(btree_key_count(&page), 0);
+        assert_eq!(btree_sibling(&page), PageId::INVALID);
+        assert!(page.as_slice()[BTREE_NODE_HEADER_SIZE..]
+            .iter()
+            .all(|&b| b == 0));
+        assert!(page.verify_checksum());
+    }
+
+    #[test]
+    fn test_leaf_sibling_chain() {
+        let mut leaf = Page::new();
+        init_btree_leaf(&mut leaf);
+        assert_eq!(btree_sibling(&leaf), PageId::INVALID);
+
+        btree_set_sibling(&mut leaf, PageId::new(42));
+        assert_eq!(btree_sibling(&leaf), PageId::new(42));
+        assert_eq!(btree_key_count(&leaf), 0);
+
+        // End of chain again, e.g. after a merge with the old sibling
+        btree_set_sibling(&mut leaf, PageId::INVALID);
+        assert!(!btree_sibling(&leaf).is_valid());
+    }
+
+    #[test]
+    fn test_init_btree_internal() {
+        let mut page = Page::new();
+        init_btree_internal(&mut page);
+        assert_eq!(page.header().page_type, PageType::BTreeInternal);
+        assert_eq!(btree_key_count(&page), 0);
+        assert_eq!(btree_sibling(&page), PageId::INVALID);
+
+        btree_set_key_count(&mut page, 12);
+        btree_set_sibling(&mut page, PageId::new(9));
+        assert_eq!(btree_key_count(&page), 12);
+        assert_eq!(btree_sibling(&page), PageId::new(9));
+        assert_eq!(page.header().page_type, PageType::BTreeInternal);
+    }
+}
@@ -4,10 +4,17 @@
 //! - [`Page`] - The raw 4KB data container
 //! - [`PageHeader`] - Metadata at the start of every page
 //! - [`PageType`] - Discriminator for different page formats
+//! - [`init_btree_leaf`] / [`init_btree_internal`] - Empty B-tree node setup
+//! - [`BTreeNodePage`] - Typed read-only view of a B-tree node
 
+mod btree_page;
 #[allow(clippy::module_inception)]
 mod page;
 mod page_header;
 
+pub use btree_page::{
+    btree_key_count, btree_set_key_count, btree_set_sibling, btree_sibling, init_btree_internal,
+    init_btree_leaf, BTreeNodePage, BTREE_NODE_HEADER_SIZE,
+};
 pub use page::Page;
 pub use page_header::{PageHeader, PageType};
\ No newline at end of file
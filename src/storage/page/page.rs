@@ -4,9 +4,13 @@
 //! between disk and memory. Pages are stored in [`Frame`]s within the
 //! buffer pool.
 
+use std::fmt::Write;
+use std::ops::Range;
+
 use crate::common::config::PAGE_SIZE;
+use crate::common::Lsn;
 
-use super::page_header::PageHeader;
+use super::page_header::{PageHeader, PageType};
 
 /// A page of data (4KB, 4KB-aligned).
 ///
@@ -83,6 +87,30 @@ impl Page {
         header.write_to(&mut self.data);
     }
 
+    /// Read just the page type from the header.
+    #[inline]
+    pub fn page_type(&self) -> PageType {
+        PageType::from_u8(self.data[PageHeader::OFFSET_PAGE_TYPE])
+    }
+
+    /// Read just the LSN from the header.
+    #[inline]
+    pub fn lsn(&self) -> Lsn {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self.data[PageHeader::OFFSET_LSN..PageHeader::OFFSET_LSN + 8]);
+        Lsn(u64::from_le_bytes(bytes))
+    }
+
+    /// Overwrite just the LSN in the header.
+    ///
+    /// Like any modification, this invalidates the checksum until
+    /// `update_checksum` is called.
+    #[inline]
+    pub fn set_lsn(&mut self, lsn: Lsn) {
+        self.data[PageHeader::OFFSET_LSN..PageHeader::OFFSET_LSN + 8]
+            .copy_from_slice(&lsn.0.to_le_bytes());
+    }
+
     /// Compute and store checksum in the header.
     ///
     /// Call this after all modifications to the page are complete.
@@ -97,6 +125,118 @@ impl Page {
     pub fn verify_checksum(&self) -> bool {
         self.header().verify_checksum(&self.data)
     }
+
+    /// Check whether the page is entirely zero (never written).
+    pub fn is_zeroed(&self) -> bool {
+        self.data.iter().all(|&b| b == 0)
+    }
+
+    /// Check whether the page is intact: either never written (all zeros)
+    /// or carrying a valid checksum.
+    ///
+    /// Freshly allocated pages are zero-filled without a checksum, so
+    /// integrity checks should use this rather than `verify_checksum` alone.
+    pub fn is_integral(&self) -> bool {
+        self.is_zeroed() || self.verify_checksum()
+    }
+
+    // ========================================================================
+    // Debug printing
+    // ========================================================================
+
+    /// Dump the bytes in `range` as offset, hex and ASCII columns.
+    ///
+    /// Each line covers 16 bytes and starts with the page offset of its first
+    /// byte. Non-printable bytes show as `.` in the ASCII column.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds for the page.
+    ///
+    /// # Example
+    /// ```
+    /// use interchangedb::storage::page::Page;
+    ///
+    /// let mut page = Page::new();
+    /// page.as_mut_slice()[16..18].copy_from_slice(b"hi");
+    /// assert!(page.hexdump(16..18).starts_with("0010  68 69"));
+    /// ```
+    pub fn hexdump(&self, range: Range<usize>) -> String {
+        let start = range.start;
+        let mut out = String::new();
+        for (i, line) in self.data[range].chunks(16).enumerate() {
+            let _ = write!(out, "{:04x} ", start + i * 16);
+            for col in 0..16 {
+                if col == 8 {
+                    out.push(' ');
+                }
+                match line.get(col) {
+                    Some(b) => {
+                        let _ = write!(out, " {:02x}", b);
+                    }
+                    None => out.push_str("   "),
+                }
+            }
+            out.push_str("  |");
+            out.extend(line.iter().map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            }));
+            out.push_str("|\n");
+        }
+        out
+    }
+
+    /// Pretty-print the decoded page header, one field per line.
+    pub fn debug_header(&self) -> String {
+        let header = self.header();
+        let status = if self.verify_checksum() {
+            "valid"
+        } else {
+            "invalid"
+        };
+        format!(
+            "page_type: {:?}\nchecksum:  {:#010x} ({})\nlsn:       {}\n",
+            header.page_type, header.checksum, status, header.lsn
+        )
+    }
+
+    // ========================================================================
+    // Torn-write detection (opt-in)
+    // ========================================================================
+
+    /// Size of a disk sector; a torn write leaves a mix of old and new sectors.
+    pub const SECTOR_SIZE: usize = 512;
+
+    /// Number of sectors in a page.
+    pub const SECTORS_PER_PAGE: usize = PAGE_SIZE / Self::SECTOR_SIZE;
+
+    /// CRC32 (truncated to 16 bits) of each 512-byte sector.
+    ///
+    /// The checksums are kept outside the page (see
+    /// `DiskManager::enable_sector_checksums`), so every byte of the page
+    /// stays available to page layouts.
+    pub fn sector_checksums(&self) -> [u16; Self::SECTORS_PER_PAGE] {
+        let mut sums = [0u16; Self::SECTORS_PER_PAGE];
+        for (sum, sector) in sums
+            .iter_mut()
+            .zip(self.data.chunks_exact(Self::SECTOR_SIZE))
+        {
+            *sum = crc32fast::hash(sector) as u16;
+        }
+        sums
+    }
+
+    /// Find the first sector whose contents don't match `expected`.
+    ///
+    /// Returns `None` if every sector verifies. A mismatch means the last
+    /// write to this page was torn, even if the page CRC happens to pass.
+    pub fn find_torn_sector(&self, expected: &[u16; Self::SECTORS_PER_PAGE]) -> Option<usize> {
+        let actual = self.sector_checksums();
+        (0..Self::SECTORS_PER_PAGE).find(|&sector| actual[sector] != expected[sector])
+    }
 }
 
 impl Default for Page {
@@ -122,6 +262,7 @@ impl Clone for Page {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::page::PageType;
 
     #[test]
     fn test_page_size_and_alignment() {
@@ -162,6 +303,84 @@ mod tests {
         assert_eq!(page.as_slice()[100], 0);
     }
 
+    #[test]
+    fn test_hexdump() {
+        let mut page = Page::new();
+        page.as_mut_slice()[32..37].copy_from_slice(b"Hi!\x00\xff");
+
+        let dump = page.hexdump(32..37);
+        assert_eq!(
+            dump,
+            "0020  48 69 21 00 ff                                    |Hi!..|\n"
+        );
+
+        let dump = page.hexdump(16..48);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("0010  00 00"));
+        assert!(lines[1].starts_with("0020  48 69 21 00 ff 00 00 00  00"));
+        assert!(lines[1].ends_with("|Hi!.............|"));
+    }
+
+    #[test]
+    fn test_debug_header() {
+        let mut page = Page::new();
+        page.set_header(&PageHeader::new(PageType::BTreeLeaf));
+        page.update_checksum();
+
+        let text = page.debug_header();
+        assert!(text.contains("page_type: BTreeLeaf"));
+        assert!(text.contains("(valid)"));
+        assert!(text.contains("lsn:       Lsn(0)"));
+    }
+
+    #[test]
+    fn test_is_integral() {
+        // Never-written page
+        let page = Page::new();
+        assert!(page.is_zeroed());
+        assert!(!page.verify_checksum());
+        assert!(page.is_integral());
+
+        // Correctly checksummed page
+        let mut page = Page::new();
+        page.set_header(&PageHeader::new(PageType::Data));
+        page.as_mut_slice()[500] = 0x42;
+        page.update_checksum();
+        assert!(page.is_integral());
+
+        // Corrupted after checksumming
+        page.as_mut_slice()[500] = 0x43;
+        assert!(!page.is_zeroed());
+        assert!(!page.is_integral());
+    }
+
+    #[test]
+    fn test_sector_checksums_detect_torn_write() {
+        let mut old = Page::new();
+        old.as_mut_slice()[1000] = 0x11;
+        old.update_checksum();
+
+        let mut new = old.clone();
+        new.as_mut_slice()[1000] = 0x22;
+        new.as_mut_slice()[3000] = 0x33;
+        new.update_checksum();
+        let sums = new.sector_checksums();
+        assert_eq!(new.find_torn_sector(&sums), None);
+
+        // Sector 5 (bytes 2560..3072) never made it to disk
+        let mut torn = new.clone();
+        let range = 5 * Page::SECTOR_SIZE..6 * Page::SECTOR_SIZE;
+        torn.as_mut_slice()[range.clone()].copy_from_slice(&old.as_slice()[range]);
+        assert_eq!(torn.find_torn_sector(&sums), Some(5));
+
+        // Sector 0 holds the header, including the page checksum
+        let mut torn = new.clone();
+        let range = 0..Page::SECTOR_SIZE;
+        torn.as_mut_slice()[range.clone()].copy_from_slice(&old.as_slice()[range]);
+        assert_eq!(torn.find_torn_sector(&sums), Some(0));
+    }
+
     #[test]
     fn test_page_clone_in_tests() {
         let mut page = Page::new();
@@ -5,11 +5,14 @@
 //! - CRC32 checksum for integrity
 //! - LSN for WAL/recovery
 
+use crate::common::{Error, Lsn, Result};
+
 /// Type of page stored on disk.
 ///
 /// Uses `#[repr(u8)]` to guarantee a 1-byte representation for serialization.
 #[repr(u8)]
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PageType {
     /// Uninitialized or corrupted page.
     #[default]
@@ -22,6 +25,14 @@ pub enum PageType {
     BTreeLeaf = 3,
     /// Page on the free list.
     Free = 4,
+    /// LSM-tree manifest listing the sorted runs.
+    LsmManifest = 5,
+    /// Page of an immutable LSM-tree sorted run.
+    LsmRun = 6,
+    /// Database superblock holding the on-disk format version.
+    Superblock = 7,
+    /// Directory of the pages held in the double-write region.
+    DoubleWrite = 8,
 }
 
 impl PageType {
@@ -32,6 +43,10 @@ impl PageType {
             2 => PageType::BTreeInternal,
             3 => PageType::BTreeLeaf,
             4 => PageType::Free,
+            5 => PageType::LsmManifest,
+            6 => PageType::LsmRun,
+            7 => PageType::Superblock,
+            8 => PageType::DoubleWrite,
             _ => PageType::Invalid,
         }
     }
@@ -56,13 +71,14 @@ impl PageType {
 /// Included from day 1 for WAL/MVCC forward-compatibility, even though
 /// it won't be used until the WAL implementation (Week 8).
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PageHeader {
     /// Type of this page.
     pub page_type: PageType,
     /// CRC32 checksum of the page contents.
     pub checksum: u32,
     /// Log Sequence Number of last modification.
-    pub lsn: u64,
+    pub lsn: Lsn,
 }
 
 impl PageHeader {
@@ -76,21 +92,37 @@ impl PageHeader {
 
     /// Create a new header with the given page type.
     ///
-    /// Checksum and LSN are initialized to zero.
+    /// Checksum is zero and the LSN is `Lsn::ZERO`.
     pub fn new(page_type: PageType) -> Self {
         Self {
             page_type,
             checksum: 0,
-            lsn: 0,
+            lsn: Lsn::ZERO,
         }
     }
 
     /// Read a header from the beginning of a byte slice.
     ///
+    /// For input that may be truncated or untrusted, use
+    /// [`try_from_bytes`](Self::try_from_bytes).
+    ///
     /// # Panics
     /// Panics if `data.len() < PageHeader::SIZE`.
     pub fn from_bytes(data: &[u8]) -> Self {
-        assert!(data.len() >= Self::SIZE, "buffer too small for PageHeader");
+        Self::try_from_bytes(data).expect("buffer too small for PageHeader")
+    }
+
+    /// Read a header from the beginning of a byte slice, without panicking.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidPageHeader` if `data.len() < PageHeader::SIZE`.
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < Self::SIZE {
+            return Err(Error::InvalidPageHeader(format!(
+                "{} bytes is too short",
+                data.len()
+            )));
+        }
 
         let page_type = PageType::from_u8(data[Self::OFFSET_PAGE_TYPE]);
 
@@ -101,7 +133,7 @@ impl PageHeader {
             data[Self::OFFSET_CHECKSUM + 3],
         ]);
 
-        let lsn = u64::from_le_bytes([
+        let lsn = Lsn(u64::from_le_bytes([
             data[Self::OFFSET_LSN],
             data[Self::OFFSET_LSN + 1],
             data[Self::OFFSET_LSN + 2],
@@ -110,13 +142,13 @@ impl PageHeader {
             data[Self::OFFSET_LSN + 5],
             data[Self::OFFSET_LSN + 6],
             data[Self::OFFSET_LSN + 7],
-        ]);
+        ]));
 
-        Self {
+        Ok(Self {
             page_type,
             checksum,
             lsn,
-        }
+        })
     }
 
     /// Write this header to the beginning of a byte slice.
@@ -131,7 +163,7 @@ impl PageHeader {
         let checksum_bytes = self.checksum.to_le_bytes();
         data[Self::OFFSET_CHECKSUM..Self::OFFSET_CHECKSUM + 4].copy_from_slice(&checksum_bytes);
 
-        let lsn_bytes = self.lsn.to_le_bytes();
+        let lsn_bytes = self.lsn.0.to_le_bytes();
         data[Self::OFFSET_LSN..Self::OFFSET_LSN + 8].copy_from_slice(&lsn_bytes);
     }
 
@@ -190,6 +222,10 @@ mod tests {
         assert_eq!(PageType::from_u8(2), PageType::BTreeInternal);
         assert_eq!(PageType::from_u8(3), PageType::BTreeLeaf);
         assert_eq!(PageType::from_u8(4), PageType::Free);
+        assert_eq!(PageType::from_u8(5), PageType::LsmManifest);
+        assert_eq!(PageType::from_u8(6), PageType::LsmRun);
+        assert_eq!(PageType::from_u8(7), PageType::Superblock);
+        assert_eq!(PageType::from_u8(8), PageType::DoubleWrite);
         assert_eq!(PageType::from_u8(255), PageType::Invalid);
     }
 
@@ -205,7 +241,7 @@ mod tests {
         let header = PageHeader::new(PageType::Data);
         assert_eq!(header.page_type, PageType::Data);
         assert_eq!(header.checksum, 0);
-        assert_eq!(header.lsn, 0);
+        assert_eq!(header.lsn, Lsn::ZERO);
     }
 
     #[test]
@@ -213,7 +249,7 @@ mod tests {
         let header = PageHeader::default();
         assert_eq!(header.page_type, PageType::Invalid);
         assert_eq!(header.checksum, 0);
-        assert_eq!(header.lsn, 0);
+        assert_eq!(header.lsn, Lsn::ZERO);
     }
 
     #[test]
@@ -221,7 +257,7 @@ mod tests {
         let original = PageHeader {
             page_type: PageType::BTreeLeaf,
             checksum: 0xDEADBEEF,
-            lsn: 0x123456789ABCDEF0,
+            lsn: Lsn(0x123456789ABCDEF0),
         };
 
         let mut buffer = [0u8; PageHeader::SIZE];
@@ -231,12 +267,33 @@ mod tests {
         assert_eq!(original, recovered);
     }
 
+    #[test]
+    fn test_page_header_try_from_bytes() {
+        let header = PageHeader {
+            page_type: PageType::LsmRun,
+            checksum: 0xCAFEF00D,
+            lsn: Lsn(42),
+        };
+        let mut buffer = [0u8; PageHeader::SIZE];
+        header.write_to(&mut buffer);
+
+        assert_eq!(
+            PageHeader::try_from_bytes(&buffer).unwrap(),
+            PageHeader::from_bytes(&buffer)
+        );
+        match PageHeader::try_from_bytes(&buffer[..5]) {
+            Err(Error::InvalidPageHeader(msg)) => assert!(msg.contains('5')),
+            other => panic!("expected InvalidPageHeader, got {:?}", other),
+        }
+        assert!(PageHeader::try_from_bytes(&[]).is_err());
+    }
+
     #[test]
     fn test_page_header_byte_layout() {
         let header = PageHeader {
             page_type: PageType::Data,
             checksum: 0x04030201, // Little-endian: 01 02 03 04
-            lsn: 0x0807060504030201, // Little-endian: 01 02 03 04 05 06 07 08
+            lsn: Lsn(0x0807060504030201), // Little-endian: 01 02 03 04 05 06 07 08
         };
 
         let mut buffer = [0u8; PageHeader::SIZE];
@@ -308,7 +365,7 @@ mod tests {
         let header = PageHeader {
             page_type: PageType::Data,
             checksum,
-            lsn: 0,
+            lsn: Lsn::ZERO,
         };
 
         assert!(header.verify_checksum(&page_data));
@@ -317,4 +374,24 @@ mod tests {
         page_data[100] = 0xFF;
         assert!(!header.verify_checksum(&page_data));
     }
-}
\ No newline at end of file
+
+    // --- Serde tests ---
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_page_header_serde_roundtrip() {
+        let header = PageHeader {
+            page_type: PageType::BTreeLeaf,
+            checksum: 0xDEADBEEF,
+            lsn: Lsn(99),
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        assert_eq!(serde_json::from_str::<PageHeader>(&json).unwrap(), header);
+
+        let json = serde_json::to_string(&PageType::Free).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PageType>(&json).unwrap(),
+            PageType::Free
+        );
+    }
+}
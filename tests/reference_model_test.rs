@@ -0,0 +1,243 @@
+//! Model-based tests: random operation sequences run against both the real
+//! buffer pool and a simple reference model, which must agree on every
+//! observable outcome.
+//!
+//! The model mirrors the pool's FIFO eviction policy. Operations never keep
+//! a page pinned, so eviction (and therefore hit/miss) is deterministic.
+
+use std::collections::{HashMap, VecDeque};
+
+use interchangedb::buffer::BufferPoolManager;
+use interchangedb::common::PageId;
+use interchangedb::storage::DiskManager;
+use proptest::prelude::*;
+use tempfile::tempdir;
+
+/// Offset of the byte each operation reads or writes (past the header).
+const VALUE_OFFSET: usize = 100;
+
+#[derive(Debug, Clone)]
+enum Op {
+    New,
+    Read(usize),
+    Write(usize, u8),
+    Delete(usize),
+    FlushAll,
+}
+
+/// A HashMap-and-queue model of the buffer pool.
+///
+/// Tracks the value byte of every page on "disk" and in the cache, the
+/// cache's load order, and which cached pages are dirty.
+struct ReferenceBufferPool {
+    pool_size: usize,
+    disk: Vec<u8>,
+    cached: HashMap<PageId, (u8, bool)>,
+    load_order: VecDeque<PageId>,
+}
+
+impl ReferenceBufferPool {
+    fn new(pool_size: usize) -> Self {
+        Self {
+            pool_size,
+            disk: Vec::new(),
+            cached: HashMap::new(),
+            load_order: VecDeque::new(),
+        }
+    }
+
+    fn page_count(&self) -> usize {
+        self.disk.len()
+    }
+
+    /// Make room for one more page, writing back a dirty victim.
+    fn make_room(&mut self) {
+        if self.load_order.len() == self.pool_size {
+            let victim = self.load_order.pop_front().unwrap();
+            let (value, dirty) = self.cached.remove(&victim).unwrap();
+            if dirty {
+                self.disk[victim.0 as usize] = value;
+            }
+        }
+    }
+
+    fn new_page(&mut self) -> PageId {
+        let page_id = PageId::new(self.disk.len() as u32);
+        self.disk.push(0);
+        self.make_room();
+        self.cached.insert(page_id, (0, true));
+        self.load_order.push_back(page_id);
+        page_id
+    }
+
+    /// Bring a page into the cache. Returns whether it was a hit.
+    fn fetch(&mut self, page_id: PageId) -> bool {
+        if self.cached.contains_key(&page_id) {
+            return true;
+        }
+        self.make_room();
+        self.cached
+            .insert(page_id, (self.disk[page_id.0 as usize], false));
+        self.load_order.push_back(page_id);
+        false
+    }
+
+    fn read(&mut self, page_id: PageId) -> (u8, bool) {
+        let hit = self.fetch(page_id);
+        (self.cached[&page_id].0, hit)
+    }
+
+    fn write(&mut self, page_id: PageId, value: u8) -> bool {
+        let hit = self.fetch(page_id);
+        self.cached.insert(page_id, (value, true));
+        hit
+    }
+
+    /// Drop a page from the cache without writing it back.
+    fn delete(&mut self, page_id: PageId) {
+        if self.cached.remove(&page_id).is_some() {
+            self.load_order.retain(|&pid| pid != page_id);
+        }
+    }
+
+    fn flush_all(&mut self) {
+        for (&page_id, entry) in self.cached.iter_mut() {
+            if entry.1 {
+                self.disk[page_id.0 as usize] = entry.0;
+                entry.1 = false;
+            }
+        }
+    }
+}
+
+/// Apply `ops` to a real pool and the model, asserting they agree.
+fn check_against_model(pool_size: usize, ops: &[Op]) {
+    let dir = tempdir().unwrap();
+    let dm = DiskManager::create(dir.path().join("model.db")).unwrap();
+    let bpm = BufferPoolManager::new(pool_size, dm);
+    let mut model = ReferenceBufferPool::new(pool_size);
+
+    let pick =
+        |model: &ReferenceBufferPool, idx: usize| PageId::new((idx % model.page_count()) as u32);
+
+    for (step, op) in ops.iter().enumerate() {
+        let op = match op {
+            // Page operations need a page to act on
+            Op::Read(_) | Op::Write(..) | Op::Delete(_) if model.page_count() == 0 => &Op::New,
+            op => op,
+        };
+
+        let hits_before = bpm.stats().snapshot().cache_hits;
+        let expected_hit = match *op {
+            Op::New => {
+                let page_id = bpm.new_page().unwrap().page_id();
+                assert_eq!(page_id, model.new_page(), "step {}: {:?}", step, op);
+                None
+            }
+            Op::Read(idx) => {
+                let page_id = pick(&model, idx);
+                let value = bpm.fetch_page_read(page_id).unwrap().as_slice()[VALUE_OFFSET];
+                let (expected, hit) = model.read(page_id);
+                assert_eq!(value, expected, "step {}: {:?}", step, op);
+                Some(hit)
+            }
+            Op::Write(idx, value) => {
+                let page_id = pick(&model, idx);
+                bpm.fetch_page_write(page_id).unwrap().as_mut_slice()[VALUE_OFFSET] = value;
+                Some(model.write(page_id, value))
+            }
+            Op::Delete(idx) => {
+                let page_id = pick(&model, idx);
+                bpm.delete_page(page_id).unwrap();
+                model.delete(page_id);
+                None
+            }
+            Op::FlushAll => {
+                bpm.flush_all_pages().unwrap();
+                model.flush_all();
+                None
+            }
+        };
+
+        if let Some(hit) = expected_hit {
+            let hits = bpm.stats().snapshot().cache_hits - hits_before;
+            assert_eq!(hits, hit as u64, "step {}: hit/miss of {:?}", step, op);
+        }
+        assert_eq!(
+            bpm.page_count(),
+            model.cached.len(),
+            "step {}: {:?}",
+            step,
+            op
+        );
+        for &page_id in &model.load_order {
+            assert!(
+                bpm.contains_page(page_id),
+                "step {}: {} not resident",
+                step,
+                page_id
+            );
+        }
+    }
+
+    // Everything written reads back, and survives a final flush
+    bpm.flush_all_pages().unwrap();
+    model.flush_all();
+    for idx in 0..model.page_count() {
+        let page_id = PageId::new(idx as u32);
+        let value = bpm.fetch_page_read(page_id).unwrap().as_slice()[VALUE_OFFSET];
+        assert_eq!(value, model.read(page_id).0, "final read of {}", page_id);
+    }
+}
+
+/// Deterministic xorshift generator for the fixed-seed run.
+fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+fn random_ops(seed: u64, len: usize) -> Vec<Op> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            let r = xorshift(&mut state);
+            let idx = (r >> 8) as usize;
+            match r % 20 {
+                0..=2 => Op::New,
+                3..=10 => Op::Read(idx),
+                11..=16 => Op::Write(idx, (r >> 40) as u8),
+                17..=18 => Op::Delete(idx),
+                _ => Op::FlushAll,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_model_fixed_seed() {
+    check_against_model(4, &random_ops(0x5EED_1234_ABCD_0001, 2000));
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        2 => Just(Op::New),
+        6 => any::<usize>().prop_map(Op::Read),
+        5 => (any::<usize>(), any::<u8>()).prop_map(|(idx, v)| Op::Write(idx, v)),
+        1 => any::<usize>().prop_map(Op::Delete),
+        1 => Just(Op::FlushAll),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_model_random_ops(
+        pool_size in 1usize..6,
+        ops in prop::collection::vec(op_strategy(), 1..200),
+    ) {
+        check_against_model(pool_size, &ops);
+    }
+}
@@ -0,0 +1,90 @@
+//! Tests for the trace replay example (`examples/replay.rs`).
+
+#[path = "../examples/replay.rs"]
+#[allow(dead_code)]
+mod replay;
+
+use std::io::Cursor;
+
+use interchangedb::common::{Error, PageId};
+use tempfile::tempdir;
+
+fn run_on_trace(trace: &str, args: &[&str]) -> interchangedb::Result<String> {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("trace.txt");
+    std::fs::write(&path, trace).unwrap();
+
+    let mut argv = vec![path.to_string_lossy().into_owned()];
+    argv.extend(args.iter().map(|s| s.to_string()));
+
+    let mut out = Vec::new();
+    replay::run(&argv, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn printed_hit_rate(output: &str) -> f64 {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("hit rate:"))
+        .expect("hit rate line")
+        .trim()
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn test_parse_trace_skips_comments_and_blanks() {
+    let trace = replay::parse_trace(Cursor::new("# header\n3\n\n 1 \n# done\n")).unwrap();
+    assert_eq!(trace, vec![PageId::new(3), PageId::new(1)]);
+
+    assert!(matches!(
+        replay::parse_trace(Cursor::new("1\nnope\n")),
+        Err(Error::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn test_replay_hit_rate_within_bounds() {
+    // Working set of 4 pages cycled 10 times: only the first pass misses
+    let trace: String = (0..10)
+        .flat_map(|_| 0..4u32)
+        .map(|pid| format!("{}\n", pid))
+        .collect();
+
+    let output = run_on_trace(&trace, &["8", "fifo"]).unwrap();
+    assert!(output.contains("accesses"));
+    let hit_rate = printed_hit_rate(&output);
+    assert!((0.85..=0.95).contains(&hit_rate), "hit rate {}", hit_rate);
+
+    // A pool too small for a cyclic working set thrashes under FIFO
+    let output = run_on_trace(&trace, &["3"]).unwrap();
+    assert!(printed_hit_rate(&output) < 0.1);
+}
+
+#[test]
+fn test_replay_rejects_bad_arguments() {
+    assert!(matches!(
+        run_on_trace("1\n", &["0"]),
+        Err(Error::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        run_on_trace("1\n", &["4", "clock"]),
+        Err(Error::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        replay::run(&[], &mut Vec::new()),
+        Err(Error::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn test_replay_sparse_ids_stay_small() {
+    // Huge IDs must not preallocate terabytes of pages
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("replay.db");
+    let trace = [4_000_000_000, 7, 4_000_000_000, 7].map(PageId::new);
+
+    let stats = replay::replay(&db, 4, &trace).unwrap();
+    assert_eq!(stats.cache_hits, 2);
+    assert!(std::fs::metadata(&db).unwrap().len() < 1 << 20);
+}